@@ -0,0 +1,95 @@
+// Benchmarks the solvers against the bundled corpus of hard puzzles.
+// Run with `cargo bench`, optionally followed by `-- <filter>` to only run matching puzzles.
+
+use puzzle_solver::{
+    solve_with_sat, Budget, Encode, Gattai, Nonogram, Outcome, Puzzle, Report, Sudoku,
+};
+use std::{
+    env,
+    error::Error,
+    fs,
+    hint::black_box,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+const CORPUS: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/benches/corpus");
+const ITERATIONS: usize = 3;
+const TIMEOUT: Duration = Duration::from_secs(60);
+
+fn solve_once<P: Puzzle + Encode>(contents: &str, sat: bool) -> Result<Report, Box<dyn Error>> {
+    let mut puzzle = P::build(&mut contents.to_string())?;
+    let budget = Budget::unlimited().with_timeout(TIMEOUT);
+    let report = match sat {
+        true => solve_with_sat(&mut puzzle, &budget),
+        false => puzzle.solve_within(&budget),
+    };
+    Ok(black_box(report))
+}
+
+fn bench_file(path: &Path, sat: bool) -> Result<(), Box<dyn Error>> {
+    let name = path.file_stem().unwrap_or_default().to_string_lossy();
+    let contents = fs::read_to_string(path)?;
+    let mut reports: Vec<Report> = Vec::new();
+    for _ in 0..ITERATIONS {
+        let report = match name.split('_').next() {
+            Some("sudoku") => solve_once::<Sudoku>(&contents, sat)?,
+            Some("nonogram") => solve_once::<Nonogram>(&contents, sat)?,
+            Some("gattai") => solve_once::<Gattai>(&contents, sat)?,
+            _ => return Err(Box::from(format!("Unknown puzzle type for {}", name))),
+        };
+        let finished = report.outcome != Outcome::Solved;
+        reports.push(report);
+        if finished {
+            break;
+        }
+    }
+
+    reports.sort_by_key(|report| report.stats.elapsed);
+    let (fastest, median) = (&reports[0], &reports[reports.len() / 2]);
+    let outcome = match fastest.outcome {
+        Outcome::Solved => String::from("solved"),
+        Outcome::Unsolvable => String::from("unsolvable"),
+        Outcome::GaveUp(reason) => format!("gave up: {}", reason.describe()),
+    };
+    println!(
+        "{:<28} {:<8} {:>12.2?} {:>12.2?} {:>12} {:>12} {:>14} {:>6}  {}",
+        name,
+        if sat { "sat" } else { "search" },
+        fastest.stats.elapsed,
+        median.stats.elapsed,
+        fastest.stats.nodes,
+        fastest.stats.backtracks,
+        fastest.stats.propagations,
+        fastest.stats.max_depth,
+        outcome
+    );
+    Ok(())
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    // cargo passes `--bench` to custom harnesses, so only plain arguments are treated as filters
+    let filters: Vec<String> = env::args()
+        .skip(1)
+        .filter(|arg| !arg.starts_with("--"))
+        .collect();
+    let mut files: Vec<PathBuf> = fs::read_dir(CORPUS)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext == "txt"))
+        .filter(|path| {
+            let name = path.to_string_lossy();
+            filters.is_empty() || filters.iter().any(|filter| name.contains(filter.as_str()))
+        })
+        .collect();
+    files.sort();
+
+    println!(
+        "{:<28} {:<8} {:>12} {:>12} {:>12} {:>12} {:>14} {:>6}  outcome",
+        "puzzle", "backend", "fastest", "median", "nodes", "backtracks", "propagations", "depth"
+    );
+    for path in files {
+        bench_file(&path, false)?;
+        bench_file(&path, true)?;
+    }
+    Ok(())
+}
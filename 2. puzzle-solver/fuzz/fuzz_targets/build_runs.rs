@@ -0,0 +1,10 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use puzzle_solver::build_runs;
+
+fuzz_target!(|data: &str| {
+    if let Ok(runs) = build_runs(data) {
+        assert_eq!(runs.len(), data.split_whitespace().count());
+    }
+});
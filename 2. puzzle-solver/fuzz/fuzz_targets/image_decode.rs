@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use puzzle_solver::{recognize_sudoku, GrayImage};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(image) = GrayImage::decode(data) {
+        assert_eq!(image.pixels.len(), image.width * image.height);
+        let _ = recognize_sudoku(&image);
+    }
+});
@@ -0,0 +1,13 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use puzzle_solver::{Budget, Nonogram, Puzzle};
+
+fuzz_target!(|data: &str| {
+    if let Ok(mut nonogram) = Nonogram::build(&mut data.to_string()) {
+        // Line logic is fast enough to run on whatever parses, and must not panic either
+        let budget = Budget::unlimited().with_max_nodes(1_000);
+        nonogram.solve_by_logic(&budget);
+        assert_eq!(nonogram.format().lines().count(), nonogram.height());
+    }
+});
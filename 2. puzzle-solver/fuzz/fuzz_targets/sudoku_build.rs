@@ -0,0 +1,11 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use puzzle_solver::{Puzzle, Sudoku};
+
+fuzz_target!(|data: &str| {
+    if let Ok(sudoku) = Sudoku::build(&mut data.to_string()) {
+        assert_eq!(sudoku.board.len(), 9);
+        assert!(sudoku.board.iter().all(|row| row.len() == 9));
+    }
+});
@@ -0,0 +1,48 @@
+#ifndef PUZZLE_SOLVER_H
+#define PUZZLE_SOLVER_H
+
+/* The C interface in src/ffi.rs. Regenerate with cbindgen (see cbindgen.toml) instead of editing by hand. */
+
+#include <stdint.h>
+
+// Solves a Sudoku and returns the solution as a line of 81 digits.
+//
+// # Safety
+// `input` must be NULL or point to a NUL-terminated string.
+char *puzzle_solve_sudoku(const char *input, double timeout_seconds);
+
+// Solves a Nonogram and returns the picture, one line per row with # for filled cells.
+//
+// # Safety
+// `input` must be NULL or point to a NUL-terminated string.
+char *puzzle_solve_nonogram(const char *input, double timeout_seconds);
+
+// Counts the solutions of a Sudoku, stopping at `limit`, or returns -1 on failure.
+//
+// # Safety
+// `input` must be NULL or point to a NUL-terminated string.
+int64_t puzzle_count_sudoku_solutions(const char *input, uint32_t limit, double timeout_seconds);
+
+// Counts the solutions of a Nonogram, stopping at `limit`, or returns -1 on failure.
+//
+// # Safety
+// `input` must be NULL or point to a NUL-terminated string.
+int64_t puzzle_count_nonogram_solutions(const char *input, uint32_t limit, double timeout_seconds);
+
+// Makes a Sudoku with a unique solution as a line of 81 digits, with 0 for empty cells.
+char *puzzle_generate_sudoku(uint64_t seed);
+
+// Makes a Nonogram with a unique solution and returns its two lines of clues.
+char *puzzle_generate_nonogram(uint32_t width, uint32_t height, uint64_t seed);
+
+// The reason the last failed call on this thread failed, or NULL if none has. The string
+// belongs to the library and stays valid until the next failure on the same thread.
+const char *puzzle_last_error(void);
+
+// Releases a string returned by the library. Passing NULL does nothing.
+//
+// # Safety
+// `text` must be NULL or a string returned by this library that has not been freed yet.
+void puzzle_free_string(char *text);
+
+#endif  /* PUZZLE_SOLVER_H */
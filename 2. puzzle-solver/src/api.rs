@@ -0,0 +1,123 @@
+// Text-in, text-out versions of the solvers, shared by the WebAssembly, C and Python bindings.
+// Puzzles use the same formats as the input files.
+use std::error::Error;
+
+use crate::{
+    collection_line,
+    generate::{generate_nonogram as make_nonogram, generate_sudoku as make_sudoku},
+    nonogram::{Nonogram, FILLED},
+    report_result,
+    rng::Rng,
+    search::count_solutions,
+    sudoku::Sudoku,
+    Budget, Puzzle,
+};
+
+/// Solves a Sudoku and returns the solution as a line of 81 digits.
+pub(crate) fn solve_sudoku(input: &str, budget: &Budget) -> Result<String, Box<dyn Error>> {
+    let mut sudoku = Sudoku::build(&mut input.to_string())?;
+    let report = sudoku.solve_within(budget);
+    report_result("sudoku", collection_line(&sudoku), report)
+}
+
+/// Solves a Nonogram and returns the picture with one line per row, # for filled cells and .
+/// for empty ones.
+pub(crate) fn solve_nonogram(input: &str, budget: &Budget) -> Result<String, Box<dyn Error>> {
+    let mut nonogram = Nonogram::build(&mut input.to_string())?;
+    let report = nonogram.solve_within(budget);
+    report_result("nonogram", picture(&nonogram), report)
+}
+
+fn picture(nonogram: &Nonogram) -> String {
+    (0..nonogram.height())
+        .map(|row| {
+            (0..nonogram.width())
+                .map(|col| match nonogram.cell(row, col) {
+                    FILLED => '#',
+                    _ => '.',
+                })
+                .collect::<String>()
+        })
+        .collect::<Vec<String>>()
+        .join("\n")
+}
+
+fn count_error(reason: crate::GiveUp) -> Box<dyn Error> {
+    Box::from(format!(
+        "Gave up counting solutions ({}).",
+        reason.describe()
+    ))
+}
+
+/// Counts the solutions of a Sudoku, up to `limit`.
+pub(crate) fn count_sudoku_solutions(
+    input: &str,
+    limit: usize,
+    budget: &Budget,
+) -> Result<usize, Box<dyn Error>> {
+    let mut sudoku = Sudoku::build(&mut input.to_string())?;
+    // The search assumes the givens do not already clash
+    if !sudoku.is_valid() {
+        return Ok(0);
+    }
+    count_solutions(&mut sudoku, limit, budget).map_err(count_error)
+}
+
+/// Counts the solutions of a Nonogram, up to `limit`.
+pub(crate) fn count_nonogram_solutions(
+    input: &str,
+    limit: usize,
+    budget: &Budget,
+) -> Result<usize, Box<dyn Error>> {
+    let mut nonogram = Nonogram::build(&mut input.to_string())?;
+    count_solutions(&mut nonogram, limit, budget).map_err(count_error)
+}
+
+// The C interface has no way to hand back a list, so it leaves checking out
+/// Describes every mistake in a full or partial Sudoku grid, also comparing it with the
+/// solution of `original` when one is given. Only solving `original` uses the budget.
+#[cfg(any(feature = "wasm", feature = "python"))]
+pub(crate) fn check_sudoku(
+    grid: &str,
+    original: Option<&str>,
+    budget: &Budget,
+) -> Result<Vec<String>, Box<dyn Error>> {
+    let grid = Sudoku::build(&mut grid.to_string())?;
+    let violations = match original {
+        Some(original) => grid.check_against(&Sudoku::build(&mut original.to_string())?, budget)?,
+        None => grid.check(),
+    };
+    Ok(violations.iter().map(ToString::to_string).collect())
+}
+
+/// Names every row and column of a Nonogram picture that does not match its clues, counting
+/// from 1.
+#[cfg(any(feature = "wasm", feature = "python"))]
+pub(crate) fn check_nonogram(clues: &str, grid: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    use crate::check::Unit;
+
+    let mut nonogram = Nonogram::build(&mut clues.to_string())?;
+    nonogram.read_grid(grid)?;
+    Ok(nonogram
+        .mismatched_lines()
+        .into_iter()
+        .map(|(unit, line)| match unit {
+            Unit::Row => format!("Row {} does not match its clues", line + 1),
+            _ => format!("Column {} does not match its clues", line + 1),
+        })
+        .collect())
+}
+
+/// Makes a Sudoku with a unique solution as a line of 81 digits, with 0 for empty cells.
+pub(crate) fn generate_sudoku(seed: u64) -> String {
+    collection_line(&make_sudoku(&mut Rng::new(seed)))
+}
+
+/// Makes a Nonogram with a unique solution and returns its two lines of clues.
+pub(crate) fn generate_nonogram(
+    width: usize,
+    height: usize,
+    seed: u64,
+) -> Result<String, Box<dyn Error>> {
+    Ok(make_nonogram(&mut Rng::new(seed), width, height)?.format_clues())
+}
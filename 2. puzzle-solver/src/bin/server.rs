@@ -0,0 +1,56 @@
+use puzzle_solver::{serve, ServerConfig};
+use std::{env, net::TcpListener, process, time::Duration};
+
+fn parse_config(
+    mut args: impl Iterator<Item = String>,
+) -> Result<(String, ServerConfig), &'static str> {
+    args.next();
+
+    let mut address = String::from("127.0.0.1:8080");
+    let mut config = ServerConfig::default();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--timeout" => {
+                let seconds = args
+                    .next()
+                    .and_then(|value| value.parse::<f64>().ok())
+                    .filter(|seconds| *seconds > 0.0)
+                    .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                    .ok_or("--timeout requires a positive number of seconds.")?;
+                config.solve_timeout = seconds;
+            }
+            "--max-bytes" => {
+                config.max_request_bytes = args
+                    .next()
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .filter(|bytes| *bytes > 0)
+                    .ok_or("--max-bytes requires a positive whole number.")?;
+            }
+            "--workers" => {
+                config.workers = args
+                    .next()
+                    .and_then(|value| value.parse::<usize>().ok())
+                    .filter(|workers| *workers > 0)
+                    .ok_or("--workers requires a positive whole number.")?;
+            }
+            _ => address = arg,
+        }
+    }
+    Ok((address, config))
+}
+
+fn main() {
+    let (address, config) = parse_config(env::args()).unwrap_or_else(|err| {
+        eprintln!("Application Error: {err}");
+        process::exit(1);
+    });
+    let listener = TcpListener::bind(&address).unwrap_or_else(|err| {
+        eprintln!("Application Error: {err}");
+        process::exit(1);
+    });
+    eprintln!("Serving puzzles on http://{}", address);
+    if let Err(err) = serve(listener, config) {
+        eprintln!("Application Error: {err}");
+        process::exit(1);
+    }
+}
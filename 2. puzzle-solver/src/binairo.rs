@@ -0,0 +1,153 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, Report, Tracker},
+    search::{Backtrack, Search},
+    Puzzle,
+};
+
+/// A Binairo (Takuzu) grid of 0s and 1s. No row or column has three of the same digit in a
+/// row, every row and column holds as many 0s as 1s, and no two rows or two columns are the
+/// same.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binairo {
+    size: usize,
+    // The digit in each cell by row, or None while empty
+    cells: Vec<Option<u8>>,
+    givens: Vec<bool>,
+}
+
+impl Binairo {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> Option<u8> {
+        self.cells[row * self.size + col]
+    }
+
+    fn row(&self, row: usize) -> Vec<Option<u8>> {
+        (0..self.size).map(|col| self.cell(row, col)).collect()
+    }
+
+    fn column(&self, col: usize) -> Vec<Option<u8>> {
+        (0..self.size).map(|row| self.cell(row, col)).collect()
+    }
+
+    // A line has no run of three and can still be balanced
+    fn line_allows(&self, line: &[Option<u8>]) -> bool {
+        let half = self.size / 2;
+        let no_triples = line
+            .windows(3)
+            .all(|run| run[0].is_none() || run[0] != run[1] || run[1] != run[2]);
+        no_triples
+            && [0, 1]
+                .iter()
+                .all(|&digit| line.iter().filter(|&&d| d == Some(digit)).count() <= half)
+    }
+
+    // A finished line must differ from every other finished line in the same direction
+    fn line_unique(&self, index: usize, lines: impl Fn(usize) -> Vec<Option<u8>>) -> bool {
+        let line = lines(index);
+        line.contains(&None) || (0..self.size).all(|other| other == index || lines(other) != line)
+    }
+
+    fn is_valid(&self) -> bool {
+        (0..self.size).all(|i| {
+            self.line_allows(&self.row(i))
+                && self.line_allows(&self.column(i))
+                && self.line_unique(i, |row| self.row(row))
+                && self.line_unique(i, |col| self.column(col))
+        })
+    }
+}
+
+impl Backtrack for Binairo {
+    type Value = u8;
+
+    fn cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn options(&self, index: usize) -> Option<Vec<u8>> {
+        match self.givens[index] {
+            true => None,
+            false => Some(vec![0, 1]),
+        }
+    }
+
+    fn place(&mut self, index: usize, digit: u8) -> bool {
+        self.cells[index] = Some(digit);
+        let (row, col) = (index / self.size, index % self.size);
+        self.line_allows(&self.row(row))
+            && self.line_allows(&self.column(col))
+            && self.line_unique(row, |row| self.row(row))
+            && self.line_unique(col, |col| self.column(col))
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.cells[index] = None;
+    }
+}
+
+impl Puzzle for Binairo {
+    // One row per line with 0 or 1 for given digits and . for empty cells. Spaces between cells
+    // are optional, and the grid must be square with an even size.
+    fn build(contents: &mut String) -> Result<Self, Box<dyn Error>> {
+        let mut cells = Vec::new();
+        let mut row_lengths = Vec::new();
+        for (row, line) in contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+        {
+            for c in line.chars().filter(|c| !c.is_whitespace()) {
+                cells.push(match c {
+                    '0' => Some(0),
+                    '1' => Some(1),
+                    '.' | '_' => None,
+                    c => {
+                        return Err(Box::from(format!(
+                            "Row {} has an invalid cell \"{}\". Use 0, 1 or . for empty cells.",
+                            row + 1,
+                            c
+                        )))
+                    }
+                });
+            }
+            row_lengths.push(cells.len() - row_lengths.iter().sum::<usize>());
+        }
+        let size = row_lengths.len();
+        if size == 0 || size % 2 != 0 || row_lengths.iter().any(|&length| length != size) {
+            return Err(Box::from(
+                "Failed to read puzzle. A Binairo grid must be square with an even number of rows.",
+            ));
+        }
+        Ok(Binairo {
+            size,
+            givens: cells.iter().map(Option::is_some).collect(),
+            cells,
+        })
+    }
+
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        // The search only checks lines as it fills them, so clashing givens are caught first
+        match self.is_valid() {
+            true => Search::new().run(self, budget),
+            false => Tracker::new(budget).finish(Ok(false)),
+        }
+    }
+
+    fn format(&self) -> String {
+        (0..self.size)
+            .map(|row| {
+                self.row(row)
+                    .iter()
+                    .map(|cell| cell.map_or(".".to_string(), |digit| digit.to_string()))
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fmt,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+#[cfg(not(all(target_arch = "wasm32", feature = "wasm")))]
+use std::time::Instant;
+
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+use clock::Instant;
+
+// Browsers give WebAssembly no clock through std, so the time is read from JavaScript instead
+#[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+mod clock {
+    use std::time::Duration;
+    use wasm_bindgen::prelude::*;
+
+    #[wasm_bindgen]
+    extern "C" {
+        #[wasm_bindgen(js_namespace = Date)]
+        fn now() -> f64;
+    }
+
+    #[derive(Debug, Clone, Copy)]
+    pub struct Instant(f64);
+
+    impl Instant {
+        pub fn now() -> Instant {
+            Instant(now())
+        }
+
+        pub fn elapsed(&self) -> Duration {
+            Duration::from_secs_f64((now() - self.0).max(0.0) / 1000.0)
+        }
+    }
+}
+
+// Reading the clock on every node is wasteful, so the timeout is only checked periodically.
+const CLOCK_INTERVAL: u64 = 256;
+
+/// Shared flag used to ask a running solver to stop early.
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelToken {
+    pub fn new() -> CancelToken {
+        CancelToken::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Limits placed on a single solve. The default budget is unlimited.
+#[derive(Debug, Clone, Default)]
+pub struct Budget {
+    pub timeout: Option<Duration>,
+    pub max_nodes: Option<u64>,
+    pub cancel: CancelToken,
+}
+
+impl Budget {
+    pub fn unlimited() -> Budget {
+        Budget::default()
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Budget {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_max_nodes(mut self, max_nodes: u64) -> Budget {
+        self.max_nodes = Some(max_nodes);
+        self
+    }
+
+    pub fn with_cancel(mut self, cancel: CancelToken) -> Budget {
+        self.cancel = cancel;
+        self
+    }
+}
+
+/// The reason a solver stopped before reaching an answer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GiveUp {
+    Timeout,
+    NodeLimit,
+    Cancelled,
+    // Deduction alone could not decide every cell and guessing was not allowed
+    NeedsGuess,
+}
+
+impl GiveUp {
+    pub fn describe(&self) -> &'static str {
+        match self {
+            GiveUp::Timeout => "time limit reached",
+            GiveUp::NodeLimit => "node limit reached",
+            GiveUp::Cancelled => "cancelled",
+            GiveUp::NeedsGuess => "line logic alone is not enough",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Outcome {
+    Solved,
+    Unsolvable,
+    GaveUp(GiveUp),
+}
+
+/// Counters describing how much work a solver did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stats {
+    pub nodes: u64,
+    pub backtracks: u64,
+    pub propagations: u64,
+    pub max_depth: usize,
+    pub elapsed: Duration,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Nodes visited: {}\nBacktracks: {}\nPropagation steps: {}\nMax depth: {}\nElapsed: {:.2?}",
+            self.nodes, self.backtracks, self.propagations, self.max_depth, self.elapsed
+        )
+    }
+}
+
+/// The result of a budgeted solve along with the statistics collected up to that point.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Report {
+    pub outcome: Outcome,
+    pub stats: Stats,
+}
+
+/// Counts search nodes against a budget while a solver is running.
+pub struct Tracker<'a> {
+    budget: &'a Budget,
+    start: Instant,
+    stats: Stats,
+    // Work done by earlier runs of a resumed search, which does not count against this budget
+    earlier_nodes: u64,
+    earlier_elapsed: Duration,
+}
+
+impl<'a> Tracker<'a> {
+    pub fn new(budget: &'a Budget) -> Tracker<'a> {
+        Tracker::resume(budget, Stats::default())
+    }
+
+    pub fn resume(budget: &'a Budget, stats: Stats) -> Tracker<'a> {
+        Tracker {
+            budget,
+            start: Instant::now(),
+            earlier_nodes: stats.nodes,
+            earlier_elapsed: stats.elapsed,
+            stats,
+        }
+    }
+
+    // Called once per node the solver enters, with the depth of that node in the search tree
+    pub fn visit(&mut self, depth: usize) -> Result<(), GiveUp> {
+        if self.budget.cancel.is_cancelled() {
+            return Err(GiveUp::Cancelled);
+        }
+        let nodes = self.stats.nodes - self.earlier_nodes;
+        if let Some(max_nodes) = self.budget.max_nodes {
+            if nodes >= max_nodes {
+                return Err(GiveUp::NodeLimit);
+            }
+        }
+        if let Some(timeout) = self.budget.timeout {
+            if nodes.is_multiple_of(CLOCK_INTERVAL) && self.start.elapsed() >= timeout {
+                return Err(GiveUp::Timeout);
+            }
+        }
+        self.stats.nodes += 1;
+        self.stats.max_depth = self.stats.max_depth.max(depth);
+        Ok(())
+    }
+
+    // Called when a placement is undone because nothing below it led to a solution
+    pub fn backtrack(&mut self) {
+        self.stats.backtracks += 1;
+    }
+
+    // Called for every constraint check made against a proposed placement
+    pub fn propagate(&mut self) {
+        self.stats.propagations += 1;
+    }
+
+    pub fn finish(mut self, result: Result<bool, GiveUp>) -> Report {
+        self.stats.elapsed = self.earlier_elapsed + self.start.elapsed();
+        let outcome = match result {
+            Ok(true) => Outcome::Solved,
+            Ok(false) => Outcome::Unsolvable,
+            Err(reason) => Outcome::GaveUp(reason),
+        };
+        Report {
+            outcome,
+            stats: self.stats,
+        }
+    }
+}
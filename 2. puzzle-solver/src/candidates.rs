@@ -0,0 +1,146 @@
+use std::error::Error;
+
+use crate::sudoku::{Sudoku, ALL_CANDIDATES, SUDOKU_SIZE};
+
+const SIZE: usize = SUDOKU_SIZE as usize;
+
+// Splits a candidate grid into one entry per cell. Box borders drawn with |, - and + are
+// skipped, so grids written by `format_candidates` read back in.
+fn cell_entries(text: &str) -> Vec<String> {
+    text.replace('|', " ")
+        .split_whitespace()
+        .filter(|entry| !entry.chars().all(|c| matches!(c, '-' | '+' | '=')))
+        .map(String::from)
+        .collect()
+}
+
+// A grid with one entry per cell is read as pencil marks. Plain digit grids written one digit
+// per cell read the same either way, while packed rows like "600079032" have far fewer entries.
+// Commas only appear in the bracketed rows written by `Puzzle::format`, which are plain digits.
+pub(crate) fn is_candidate_grid(text: &str) -> bool {
+    !text.contains(',') && cell_entries(text).len() == SIZE * SIZE
+}
+
+fn mask_of(digits: impl Iterator<Item = u32>) -> u16 {
+    digits.fold(0, |mask, digit| mask | 1 << digit)
+}
+
+fn digits_of(mask: u16) -> impl Iterator<Item = u32> {
+    (1..=SUDOKU_SIZE).filter(move |digit| mask & 1 << digit != 0)
+}
+
+impl Sudoku {
+    /// Reads a grid of pencil marks with one whitespace-separated entry per cell, by row. A
+    /// single digit is a filled cell, 0 or . is an empty cell that may hold anything, several
+    /// digits are the candidates left for an empty cell, and digits in brackets are always
+    /// candidates (so [5] is an empty cell that can only be 5).
+    pub fn from_candidates(text: &str) -> Result<Sudoku, Box<dyn Error>> {
+        let entries = cell_entries(text);
+        if entries.len() != SIZE * SIZE {
+            return Err(Box::from(format!(
+                "Failed to read candidate grid. Expected 81 cells but found {}.",
+                entries.len()
+            )));
+        }
+        let mut sudoku = Sudoku::from_board(vec![vec![0; SIZE]; SIZE]);
+        for (index, entry) in entries.iter().enumerate() {
+            let invalid = || {
+                format!(
+                    "Cell at row {}, column {} has an invalid entry \"{}\".",
+                    index / SIZE + 1,
+                    index % SIZE + 1,
+                    entry
+                )
+            };
+            let (marks, bracketed) = match entry.strip_prefix('[') {
+                Some(rest) => (rest.strip_suffix(']').ok_or_else(invalid)?, true),
+                None => (entry.as_str(), false),
+            };
+            let digits = marks
+                .chars()
+                .map(|c| c.to_digit(10).filter(|&digit| digit > 0))
+                .collect::<Option<Vec<u32>>>();
+            match (marks, digits) {
+                ("0" | ".", _) if !bracketed => {}
+                (_, Some(digits)) if digits.len() == 1 && !bracketed => {
+                    sudoku.board[index / SIZE][index % SIZE] = digits[0];
+                }
+                (_, Some(digits)) => sudoku.candidates[index] = mask_of(digits.into_iter()),
+                (_, None) => return Err(Box::from(invalid())),
+            }
+        }
+        Ok(sudoku)
+    }
+
+    /// Writes the grid in the format read by [`Sudoku::from_candidates`], with columns lined
+    /// up and borders drawn between boxes.
+    pub fn format_candidates(&self) -> String {
+        let entries: Vec<String> = (0..SIZE * SIZE)
+            .map(|index| {
+                let (row, col) = (index / SIZE, index % SIZE);
+                let mask = self.candidates[index];
+                match self.board[row][col] {
+                    0 if mask.count_ones() > 1 => digits_of(mask).map(|d| d.to_string()).collect(),
+                    // Brackets keep a lone candidate from reading back as a filled cell
+                    0 => format!(
+                        "[{}]",
+                        digits_of(mask).map(|d| d.to_string()).collect::<String>()
+                    ),
+                    digit => digit.to_string(),
+                }
+            })
+            .collect();
+        let width = entries.iter().map(String::len).max().unwrap_or(1);
+        let border = vec!["-".repeat(3 * width + 2); 3].join("-+-");
+        let mut lines = Vec::new();
+        for (row, cells) in entries.chunks(SIZE).enumerate() {
+            if row > 0 && row % 3 == 0 {
+                lines.push(border.clone());
+            }
+            let boxes: Vec<String> = cells
+                .chunks(3)
+                .map(|cells| {
+                    let padded: Vec<String> = cells
+                        .iter()
+                        .map(|entry| format!("{:width$}", entry))
+                        .collect();
+                    padded.join(" ")
+                })
+                .collect();
+            lines.push(boxes.join(" | ").trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+
+    /// The digits still pencilled in for a cell. Filled cells only have their own digit.
+    pub fn candidates(&self, row: usize, col: usize) -> Vec<u32> {
+        match self.board[row][col] {
+            0 => digits_of(self.candidates[row * SIZE + col]).collect(),
+            digit => vec![digit],
+        }
+    }
+
+    pub fn set_candidates(&mut self, row: usize, col: usize, digits: &[u32]) {
+        self.candidates[row * SIZE + col] = mask_of(digits.iter().copied()) & ALL_CANDIDATES;
+    }
+
+    /// Rubs out every pencil mark that clashes with a digit already in the cell's row, column
+    /// or box.
+    pub fn eliminate_candidates(&mut self) {
+        for index in 0..SIZE * SIZE {
+            let (row, col) = (index / SIZE, index % SIZE);
+            if self.board[row][col] != 0 {
+                continue;
+            }
+            let (band, stack) = (row / 3 * 3, col / 3 * 3);
+            let mut blocked = 0u16;
+            for i in 0..SIZE {
+                let box_cell = self.board[band + i / 3][stack + i % 3];
+                for digit in [self.board[row][i], self.board[i][col], box_cell] {
+                    blocked |= 1 << digit;
+                }
+            }
+            self.candidates[index] &= !blocked;
+        }
+    }
+}
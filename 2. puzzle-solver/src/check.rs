@@ -0,0 +1,206 @@
+use std::{error::Error, fmt};
+
+use crate::{
+    budget::{Budget, GiveUp, Outcome},
+    search::Search,
+    sudoku::{Sudoku, SUDOKU_SIZE},
+};
+
+const SIZE: usize = SUDOKU_SIZE as usize;
+
+/// A row, column or 3x3 box of a Sudoku.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Row,
+    Column,
+    Box,
+}
+
+/// A problem found when checking a Sudoku grid. Cells are (row, column) pairs counted from 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The same digit appears twice in one row, column or box.
+    Duplicate {
+        digit: u32,
+        unit: Unit,
+        cells: [(usize, usize); 2],
+    },
+    /// A clue from the original puzzle was changed or erased, which leaves 0.
+    ChangedClue {
+        cell: (usize, usize),
+        clue: u32,
+        found: u32,
+    },
+    /// A digit differs from the solution of the original puzzle.
+    WrongDigit {
+        cell: (usize, usize),
+        found: u32,
+        expected: u32,
+    },
+}
+
+// Cells are written counting from 1, the way players number them
+fn describe_cell(f: &mut fmt::Formatter, (row, col): (usize, usize)) -> fmt::Result {
+    write!(f, "row {}, column {}", row + 1, col + 1)
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Violation::Duplicate { digit, unit, cells } => {
+                let unit = match unit {
+                    Unit::Row => "row",
+                    Unit::Column => "column",
+                    Unit::Box => "box",
+                };
+                write!(f, "{} appears twice in the same {}: ", digit, unit)?;
+                describe_cell(f, cells[0])?;
+                write!(f, " and ")?;
+                describe_cell(f, cells[1])
+            }
+            Violation::ChangedClue { cell, clue, found } => {
+                write!(f, "The clue at ")?;
+                describe_cell(f, *cell)?;
+                match found {
+                    0 => write!(f, " was {} but has been erased", clue),
+                    _ => write!(f, " was {} but has been changed to {}", clue, found),
+                }
+            }
+            Violation::WrongDigit {
+                cell,
+                found,
+                expected,
+            } => {
+                describe_cell(f, *cell)?;
+                write!(f, " holds {} but the solution has {}", found, expected)
+            }
+        }
+    }
+}
+
+impl Sudoku {
+    /// Checks a full or partial grid against the rules, returning every pair of clashing
+    /// digits. Empty cells are not a problem, so an unfinished grid with no mistakes passes.
+    pub fn check(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let mut others = self.clone();
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let digit = self.board[row][col];
+                if digit == 0 {
+                    continue;
+                }
+                // With the cell emptied, any copy of its digit left in a unit is a clash
+                others.board[row][col] = 0;
+                let clashes = [
+                    (Unit::Row, others.safe_row(row, col, digit)),
+                    (Unit::Column, others.safe_col(row, col, digit)),
+                    (Unit::Box, others.safe_matrix(row, col, digit)),
+                ];
+                others.board[row][col] = digit;
+                for (unit, safe) in clashes {
+                    if safe {
+                        continue;
+                    }
+                    // Each pair is reported once, from its later cell
+                    for cell in unit_cells(unit, row, col) {
+                        if cell < (row, col) && self.board[cell.0][cell.1] == digit {
+                            violations.push(Violation::Duplicate {
+                                digit,
+                                unit,
+                                cells: [cell, (row, col)],
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        violations
+    }
+
+    /// Checks a grid played from `original`, adding any changed clues and any digits that
+    /// differ from the puzzle's solution to the rule violations. Fails when the original puzzle
+    /// does not have exactly one solution, since there is then no answer to compare against,
+    /// or when solving it runs out of budget.
+    pub fn check_against(
+        &self,
+        original: &Sudoku,
+        budget: &Budget,
+    ) -> Result<Vec<Violation>, Box<dyn Error>> {
+        let solution = original.unique_solution(budget)?;
+        let mut violations = self.check();
+        for row in 0..SIZE {
+            for col in 0..SIZE {
+                let (clue, found) = (original.board[row][col], self.board[row][col]);
+                let expected = solution.board[row][col];
+                if clue != 0 && found != clue {
+                    violations.push(Violation::ChangedClue {
+                        cell: (row, col),
+                        clue,
+                        found,
+                    });
+                } else if found != 0 && found != expected {
+                    violations.push(Violation::WrongDigit {
+                        cell: (row, col),
+                        found,
+                        expected,
+                    });
+                }
+            }
+        }
+        Ok(violations)
+    }
+
+    fn unique_solution(&self, budget: &Budget) -> Result<Sudoku, Box<dyn Error>> {
+        if !self.is_valid() {
+            return Err(Box::from("The original puzzle breaks the rules."));
+        }
+        let gave_up = |reason: GiveUp| {
+            Box::from(format!(
+                "Gave up solving the original puzzle ({}).",
+                reason.describe()
+            ))
+        };
+        let mut puzzle = Sudoku::from_board(self.board.clone());
+        let mut search = Search::new();
+        let first = search.run(&mut puzzle, budget);
+        match first.outcome {
+            Outcome::Solved => {}
+            Outcome::Unsolvable => return Err(Box::from("The original puzzle has no solution.")),
+            Outcome::GaveUp(reason) => return Err(gave_up(reason)),
+        }
+        let solution = puzzle.clone();
+        // The search carries on from the first solution to look for another, with whatever
+        // the first run left of the budget
+        let rest = Budget {
+            timeout: budget
+                .timeout
+                .map(|timeout| timeout.saturating_sub(first.stats.elapsed)),
+            max_nodes: budget
+                .max_nodes
+                .map(|nodes| nodes.saturating_sub(first.stats.nodes)),
+            ..budget.clone()
+        };
+        match search.run(&mut puzzle, &rest).outcome {
+            Outcome::Solved => Err(Box::from(
+                "The original puzzle has more than one solution, so there is no single answer to compare against.",
+            )),
+            Outcome::Unsolvable => Ok(solution),
+            Outcome::GaveUp(reason) => Err(gave_up(reason)),
+        }
+    }
+}
+
+// Every cell in the given unit through (row, col)
+fn unit_cells(unit: Unit, row: usize, col: usize) -> Vec<(usize, usize)> {
+    match unit {
+        Unit::Row => (0..SIZE).map(|c| (row, c)).collect(),
+        Unit::Column => (0..SIZE).map(|r| (r, col)).collect(),
+        Unit::Box => {
+            let (top, left) = (row - row % 3, col - col % 3);
+            (top..top + 3)
+                .flat_map(|r| (left..left + 3).map(move |c| (r, c)))
+                .collect()
+        }
+    }
+}
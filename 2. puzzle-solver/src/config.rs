@@ -0,0 +1,274 @@
+use std::{
+    env,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{Backend, PuzzleType};
+
+/// The config file looked for in the current directory.
+pub const PROJECT_CONFIG: &str = "puzzle-solver.toml";
+
+/// How puzzle files are read.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum InputFormat {
+    // Images are recognized by their extension and everything else is read as text
+    #[default]
+    Auto,
+    Text,
+    Image,
+}
+
+impl InputFormat {
+    pub(crate) fn from_name(name: &str) -> Option<InputFormat> {
+        match name.to_lowercase().as_str() {
+            "auto" => Some(InputFormat::Auto),
+            "text" => Some(InputFormat::Text),
+            "image" => Some(InputFormat::Image),
+            _ => None,
+        }
+    }
+}
+
+/// How solutions are written.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub(crate) enum OutputFormat {
+    // A header naming the puzzle file above the grid, except when writing to stdout
+    #[default]
+    Report,
+    // Just the grid, as written to stdout
+    Grid,
+}
+
+impl OutputFormat {
+    pub(crate) fn from_name(name: &str) -> Option<OutputFormat> {
+        match name.to_lowercase().as_str() {
+            "report" => Some(OutputFormat::Report),
+            "grid" => Some(OutputFormat::Grid),
+            _ => None,
+        }
+    }
+}
+
+/// Default settings read from TOML config files. Every setting is optional, and settings
+/// given on the command line take precedence over the config.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct Config {
+    pub(crate) puzzle: Option<String>,
+    pub(crate) input_format: Option<InputFormat>,
+    pub(crate) output_format: Option<OutputFormat>,
+    pub(crate) output_dir: Option<String>,
+    pub(crate) timeout: Option<Duration>,
+    pub(crate) max_nodes: Option<u64>,
+    pub(crate) backend: Option<Backend>,
+    pub(crate) stats: Option<bool>,
+}
+
+// A value on the right of a TOML key
+#[derive(Debug, PartialEq)]
+enum Value {
+    String(String),
+    Integer(i64),
+    Float(f64),
+    Boolean(bool),
+}
+
+impl Config {
+    /// Reads the settings from the text of a config file. Only top-level keys with string,
+    /// number and boolean values are supported, which covers every setting.
+    pub fn parse(text: &str) -> Result<Config, String> {
+        let mut config = Config::default();
+        for (number, line) in text.lines().enumerate() {
+            let line = strip_comment(line).trim();
+            if line.is_empty() {
+                continue;
+            }
+            let error = |message: &str| format!("Line {} of the config: {}", number + 1, message);
+            if line.starts_with('[') {
+                return Err(error(
+                    "tables are not supported, so keys must be at the top level.",
+                ));
+            }
+            let (key, value) = line
+                .split_once('=')
+                .ok_or_else(|| error("expected a line like key = value."))?;
+            let key = key.trim().trim_matches('"');
+            let value = parse_value(value.trim()).map_err(|message| error(&message))?;
+            config.set(key, value).map_err(|message| error(&message))?;
+        }
+        Ok(config)
+    }
+
+    fn set(&mut self, key: &str, value: Value) -> Result<(), String> {
+        let text = |value: Value| match value {
+            Value::String(text) => Ok(text),
+            _ => Err(format!("{} must be a string.", key)),
+        };
+        match key {
+            "puzzle" => {
+                let name = text(value)?.to_lowercase();
+                if PuzzleType::from_name(&name).is_none() {
+                    return Err(format!("\"{}\" is not a supported puzzle.", name));
+                }
+                self.puzzle = Some(name);
+            }
+            "input_format" => {
+                self.input_format = Some(
+                    InputFormat::from_name(&text(value)?)
+                        .ok_or("input_format must be auto, text or image.")?,
+                )
+            }
+            "output_format" => {
+                self.output_format = Some(
+                    OutputFormat::from_name(&text(value)?)
+                        .ok_or("output_format must be report or grid.")?,
+                )
+            }
+            "output_dir" => self.output_dir = Some(text(value)?),
+            "backend" => {
+                self.backend = Some(
+                    Backend::from_name(&text(value)?).ok_or("backend must be search or sat.")?,
+                )
+            }
+            "timeout" => {
+                let seconds = match value {
+                    Value::Integer(seconds) => seconds as f64,
+                    Value::Float(seconds) => seconds,
+                    _ => f64::NAN,
+                };
+                match Duration::try_from_secs_f64(seconds) {
+                    Ok(timeout) if seconds > 0.0 => self.timeout = Some(timeout),
+                    _ => return Err("timeout must be a positive number of seconds.".to_string()),
+                }
+            }
+            "max_nodes" => match value {
+                Value::Integer(nodes) if nodes > 0 => self.max_nodes = Some(nodes as u64),
+                _ => return Err("max_nodes must be a positive whole number.".to_string()),
+            },
+            "stats" => match value {
+                Value::Boolean(stats) => self.stats = Some(stats),
+                _ => return Err("stats must be true or false.".to_string()),
+            },
+            _ => return Err(format!("unknown setting \"{}\".", key)),
+        }
+        Ok(())
+    }
+
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Config, Box<dyn Error>> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)
+            .map_err(|err| format!("Could not read config {}: {}", path.display(), err))?;
+        Ok(Config::parse(&text).map_err(|err| format!("{} ({})", err, path.display()))?)
+    }
+
+    /// Settings from this config, replaced by any that `other` sets.
+    pub fn merge(self, other: Config) -> Config {
+        Config {
+            puzzle: other.puzzle.or(self.puzzle),
+            input_format: other.input_format.or(self.input_format),
+            output_format: other.output_format.or(self.output_format),
+            output_dir: other.output_dir.or(self.output_dir),
+            timeout: other.timeout.or(self.timeout),
+            max_nodes: other.max_nodes.or(self.max_nodes),
+            backend: other.backend.or(self.backend),
+            stats: other.stats.or(self.stats),
+        }
+    }
+
+    /// Loads the user config, then the project config in the current directory on top of it,
+    /// so a team can check shared settings into a project while people keep their own
+    /// defaults. A config named with `--config` replaces the project config. Missing files
+    /// are skipped.
+    pub fn load(explicit: Option<&str>) -> Result<Config, Box<dyn Error>> {
+        let mut config = Config::default();
+        if let Some(path) = user_config_path().filter(|path| path.is_file()) {
+            config = config.merge(Config::from_file(path)?);
+        }
+        match explicit {
+            Some(path) => config = config.merge(Config::from_file(path)?),
+            None if Path::new(PROJECT_CONFIG).is_file() => {
+                config = config.merge(Config::from_file(PROJECT_CONFIG)?)
+            }
+            None => {}
+        }
+        Ok(config)
+    }
+}
+
+/// Where the user config lives: `$XDG_CONFIG_HOME/puzzle-solver/config.toml`, falling back to
+/// `~/.config/puzzle-solver/config.toml`.
+pub fn user_config_path() -> Option<PathBuf> {
+    let base = env::var_os("XDG_CONFIG_HOME")
+        .filter(|dir| !dir.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(base.join("puzzle-solver").join("config.toml"))
+}
+
+// Cuts a line off at a # that is not inside a string
+fn strip_comment(line: &str) -> &str {
+    let mut quote = None;
+    let mut escaped = false;
+    for (index, c) in line.char_indices() {
+        match (quote, c) {
+            (Some('"'), '\\') if !escaped => {
+                escaped = true;
+                continue;
+            }
+            (Some(open), c) if c == open && !escaped => quote = None,
+            (None, '"' | '\'') => quote = Some(c),
+            (None, '#') => return &line[..index],
+            _ => {}
+        }
+        escaped = false;
+    }
+    line
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    if let Some(literal) = text.strip_prefix('\'') {
+        return literal
+            .strip_suffix('\'')
+            .map(|literal| Value::String(literal.to_string()))
+            .ok_or_else(|| "unterminated string.".to_string());
+    }
+    if let Some(quoted) = text.strip_prefix('"') {
+        let quoted = quoted
+            .strip_suffix('"')
+            .ok_or_else(|| "unterminated string.".to_string())?;
+        let mut value = String::new();
+        let mut chars = quoted.chars();
+        while let Some(c) = chars.next() {
+            if c != '\\' {
+                value.push(c);
+                continue;
+            }
+            value.push(match chars.next() {
+                Some('"') => '"',
+                Some('\\') => '\\',
+                Some('n') => '\n',
+                Some('t') => '\t',
+                _ => return Err("unsupported escape in string.".to_string()),
+            });
+        }
+        return Ok(Value::String(value));
+    }
+    match text {
+        "true" => return Ok(Value::Boolean(true)),
+        "false" => return Ok(Value::Boolean(false)),
+        _ => {}
+    }
+    // TOML allows underscores between digits, as in 1_000_000
+    let number = text.replace('_', "");
+    if let Ok(integer) = number.parse::<i64>() {
+        return Ok(Value::Integer(integer));
+    }
+    number
+        .parse::<f64>()
+        .ok()
+        .filter(|float| float.is_finite())
+        .map(Value::Float)
+        .ok_or_else(|| format!("could not read the value {}.", text))
+}
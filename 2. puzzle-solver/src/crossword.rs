@@ -0,0 +1,206 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, Report},
+    search::{Backtrack, Search},
+    wordsearch::{read_words, split_word_list},
+    Puzzle,
+};
+
+/// A black square in a crossword pattern.
+pub const BLOCK: char = '#';
+/// A white square with no letter in it yet.
+pub const BLANK: char = '.';
+
+// A row and column in the pattern
+type Square = (usize, usize);
+
+/// A crossword pattern to be filled from a word list. Every across and down run of two or more
+/// white squares (a slot) takes one word from the list, and each word is used at most once.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Crossword {
+    grid: Vec<Vec<char>>,
+    words: Vec<Vec<char>>,
+    // The squares of each slot, ordered so each slot crosses an earlier one where possible
+    slots: Vec<Vec<Square>>,
+    used: Vec<bool>,
+    // The word in each slot, with the squares it filled in that were blank before
+    filled: Vec<Option<(usize, Vec<Square>)>>,
+}
+
+impl Crossword {
+    pub fn height(&self) -> usize {
+        self.grid.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.grid.first().map_or(0, Vec::len)
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> char {
+        self.grid[row][col]
+    }
+
+    fn fits(&self, slot: usize, word: usize) -> bool {
+        let (slot, word) = (&self.slots[slot], &self.words[word]);
+        slot.len() == word.len()
+            && slot
+                .iter()
+                .zip(word)
+                .all(|(&(r, c), &letter)| self.grid[r][c] == BLANK || self.grid[r][c] == letter)
+    }
+}
+
+// Every across and down run of at least two white squares
+fn find_slots(grid: &[Vec<char>]) -> Vec<Vec<Square>> {
+    let (height, width) = (grid.len(), grid.first().map_or(0, Vec::len));
+    let mut slots = Vec::new();
+    let mut runs = |line: Vec<Square>| {
+        for run in line.split(|&(r, c)| grid[r][c] == BLOCK) {
+            if run.len() > 1 {
+                slots.push(run.to_vec());
+            }
+        }
+    };
+    for row in 0..height {
+        runs((0..width).map(|col| (row, col)).collect());
+    }
+    for col in 0..width {
+        runs((0..height).map(|row| (row, col)).collect());
+    }
+    slots
+}
+
+// Puts the longest slot first and then, wherever possible, a slot crossing one already placed,
+// so a dead end shows up before unrelated parts of the grid are filled in
+fn order_slots(mut slots: Vec<Vec<Square>>) -> Vec<Vec<Square>> {
+    slots.sort_by_key(|slot| std::cmp::Reverse(slot.len()));
+    let mut ordered: Vec<Vec<Square>> = Vec::with_capacity(slots.len());
+    while !slots.is_empty() {
+        let crossing = slots
+            .iter()
+            .position(|slot| {
+                ordered
+                    .iter()
+                    .any(|placed| placed.iter().any(|square| slot.contains(square)))
+            })
+            .unwrap_or(0);
+        ordered.push(slots.remove(crossing));
+    }
+    ordered
+}
+
+impl Backtrack for Crossword {
+    type Value = usize;
+
+    fn cells(&self) -> usize {
+        self.slots.len()
+    }
+
+    // The unused words that agree with the letters already crossing the slot
+    fn options(&self, index: usize) -> Option<Vec<usize>> {
+        let options = (0..self.words.len())
+            .filter(|&word| !self.used[word] && self.fits(index, word))
+            // A repeated word only needs trying once
+            .filter(|&word| {
+                !(0..word)
+                    .any(|earlier| !self.used[earlier] && self.words[earlier] == self.words[word])
+            })
+            .collect();
+        Some(options)
+    }
+
+    fn place(&mut self, index: usize, word: usize) -> bool {
+        if self.used[word] || !self.fits(index, word) {
+            return false;
+        }
+        let mut written = Vec::new();
+        for (&(r, c), &letter) in self.slots[index].iter().zip(&self.words[word]) {
+            if self.grid[r][c] == BLANK {
+                self.grid[r][c] = letter;
+                written.push((r, c));
+            }
+        }
+        self.used[word] = true;
+        self.filled[index] = Some((word, written));
+        true
+    }
+
+    fn clear(&mut self, index: usize) {
+        if let Some((word, written)) = self.filled[index].take() {
+            for (r, c) in written {
+                self.grid[r][c] = BLANK;
+            }
+            self.used[word] = false;
+        }
+    }
+}
+
+impl Puzzle for Crossword {
+    // The pattern comes first, with # for black squares, . or _ for blank squares and letters
+    // for squares already filled in, then a blank line and the word list
+    fn build(contents: &mut String) -> Result<Self, Box<dyn Error>> {
+        let (rows, words) = split_word_list(contents).ok_or(
+            "Failed to read puzzle. For crossword, put the word list after a blank line below the pattern.",
+        )?;
+        let mut grid = Vec::new();
+        for (row, line) in rows.iter().enumerate() {
+            let squares = line
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| match c {
+                    BLOCK => Ok(BLOCK),
+                    BLANK | '_' => Ok(BLANK),
+                    c if c.is_alphabetic() => Ok(c.to_uppercase().next().unwrap_or(c)),
+                    c => Err(format!(
+                        "Row {} of the pattern has an invalid square \"{}\".",
+                        row + 1,
+                        c
+                    )),
+                })
+                .collect::<Result<Vec<char>, String>>()?;
+            grid.push(squares);
+        }
+        if grid.iter().any(|row| row.len() != grid[0].len()) {
+            return Err(Box::from(
+                "Failed to read puzzle. Every row of the pattern must be the same length.",
+            ));
+        }
+        let words: Vec<Vec<char>> = read_words(words)?
+            .iter()
+            .map(|word| word.chars().collect())
+            .collect();
+        let slots = order_slots(find_slots(&grid));
+        if slots.len() > words.len() {
+            return Err(Box::from(format!(
+                "Failed to read puzzle. The pattern has {} slots but only {} words were given.",
+                slots.len(),
+                words.len()
+            )));
+        }
+        Ok(Crossword {
+            grid,
+            used: vec![false; words.len()],
+            filled: vec![None; slots.len()],
+            words,
+            slots,
+        })
+    }
+
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        Search::new().run(self, budget)
+    }
+
+    fn format(&self) -> String {
+        self.grid
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(char::to_string)
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,175 @@
+// A C interface to the solvers, declared in include/puzzle_solver.h. Strings passed in must be
+// NUL-terminated UTF-8, and every string handed back must be released with
+// puzzle_free_string. Failures return NULL (or -1 for counts), with the reason available from
+// puzzle_last_error on the same thread.
+use std::{
+    cell::RefCell,
+    error::Error,
+    ffi::{c_char, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+    time::Duration,
+};
+
+use crate::{api, Budget};
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_error(message: String) {
+    // Messages never contain NUL, but one would otherwise make CString::new fail
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+// Runs a call, recording its error, and keeps panics from unwinding into C
+fn guard<T>(failed: T, call: impl FnOnce() -> Result<T, Box<dyn Error>>) -> T {
+    match panic::catch_unwind(AssertUnwindSafe(call)) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => {
+            set_error(err.to_string());
+            failed
+        }
+        Err(_) => {
+            set_error("The solver panicked.".to_string());
+            failed
+        }
+    }
+}
+
+unsafe fn read_str<'a>(text: *const c_char) -> Result<&'a str, Box<dyn Error>> {
+    if text.is_null() {
+        return Err(Box::from("A NULL string was passed."));
+    }
+    Ok(CStr::from_ptr(text).to_str()?)
+}
+
+fn into_c_string(text: String) -> Result<*mut c_char, Box<dyn Error>> {
+    Ok(CString::new(text)?.into_raw())
+}
+
+// A timeout of 0 or less means no limit, as does one too long to represent
+fn budget(timeout_seconds: f64) -> Budget {
+    match Duration::try_from_secs_f64(timeout_seconds) {
+        Ok(timeout) if timeout_seconds > 0.0 => Budget::unlimited().with_timeout(timeout),
+        _ => Budget::unlimited(),
+    }
+}
+
+/// Solves a Sudoku and returns the solution as a line of 81 digits.
+///
+/// # Safety
+/// `input` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn puzzle_solve_sudoku(
+    input: *const c_char,
+    timeout_seconds: f64,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        into_c_string(api::solve_sudoku(
+            read_str(input)?,
+            &budget(timeout_seconds),
+        )?)
+    })
+}
+
+/// Solves a Nonogram and returns the picture, one line per row with # for filled cells.
+///
+/// # Safety
+/// `input` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn puzzle_solve_nonogram(
+    input: *const c_char,
+    timeout_seconds: f64,
+) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        into_c_string(api::solve_nonogram(
+            read_str(input)?,
+            &budget(timeout_seconds),
+        )?)
+    })
+}
+
+/// Counts the solutions of a Sudoku, stopping at `limit`, or returns -1 on failure.
+///
+/// # Safety
+/// `input` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn puzzle_count_sudoku_solutions(
+    input: *const c_char,
+    limit: u32,
+    timeout_seconds: f64,
+) -> i64 {
+    guard(-1, || {
+        let count = api::count_sudoku_solutions(
+            read_str(input)?,
+            limit as usize,
+            &budget(timeout_seconds),
+        )?;
+        Ok(count as i64)
+    })
+}
+
+/// Counts the solutions of a Nonogram, stopping at `limit`, or returns -1 on failure.
+///
+/// # Safety
+/// `input` must be NULL or point to a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn puzzle_count_nonogram_solutions(
+    input: *const c_char,
+    limit: u32,
+    timeout_seconds: f64,
+) -> i64 {
+    guard(-1, || {
+        let count = api::count_nonogram_solutions(
+            read_str(input)?,
+            limit as usize,
+            &budget(timeout_seconds),
+        )?;
+        Ok(count as i64)
+    })
+}
+
+/// Makes a Sudoku with a unique solution as a line of 81 digits, with 0 for empty cells.
+#[no_mangle]
+pub extern "C" fn puzzle_generate_sudoku(seed: u64) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        into_c_string(api::generate_sudoku(seed))
+    })
+}
+
+/// Makes a Nonogram with a unique solution and returns its two lines of clues.
+#[no_mangle]
+pub extern "C" fn puzzle_generate_nonogram(width: u32, height: u32, seed: u64) -> *mut c_char {
+    guard(ptr::null_mut(), || {
+        into_c_string(api::generate_nonogram(
+            width as usize,
+            height as usize,
+            seed,
+        )?)
+    })
+}
+
+/// The reason the last failed call on this thread failed, or NULL if none has. The string
+/// belongs to the library and stays valid until the next failure on the same thread.
+#[no_mangle]
+pub extern "C" fn puzzle_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Releases a string returned by the library. Passing NULL does nothing.
+///
+/// # Safety
+/// `text` must be NULL or a string returned by this library that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn puzzle_free_string(text: *mut c_char) {
+    if !text.is_null() {
+        drop(CString::from_raw(text));
+    }
+}
@@ -0,0 +1,244 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, Report, Tracker},
+    search::{Backtrack, Search},
+    sudoku::SUDOKU_SIZE,
+    Puzzle,
+};
+
+const SIZE: usize = SUDOKU_SIZE as usize;
+// Keeps a typo in an offset from allocating an enormous board
+const MAX_BOARD: usize = 99;
+
+// Named layouts, as the top left corner of each grid in (row, column) board coordinates
+const LAYOUTS: [(&str, &[(usize, usize)]); 5] = [
+    ("samurai", &[(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)]),
+    ("twodoku", &[(0, 0), (6, 6)]),
+    ("butterfly", &[(0, 0), (0, 3), (3, 0), (3, 3)]),
+    ("sohei", &[(0, 6), (6, 0), (6, 12), (12, 6)]),
+    ("flower", &[(0, 6), (6, 0), (6, 6), (6, 12), (12, 6)]),
+];
+
+/// Several 9x9 Sudoku grids laid out on one board so that some of them overlap (gattai), such
+/// as Samurai Sudoku. A cell shared by two grids has to satisfy the rules of both.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Gattai {
+    height: usize,
+    width: usize,
+    // Board coordinates of every cell covered by a grid, by row
+    positions: Vec<(usize, usize)>,
+    // The digit in each covered cell, in the same order, with 0 for empty cells
+    pub values: Vec<u32>,
+    // Every row, column and box of every grid, as indices into `values`
+    pub(crate) units: Vec<Vec<usize>>,
+    // The cells sharing a unit with each cell
+    peers: Vec<Vec<usize>>,
+}
+
+impl Gattai {
+    /// An empty board with a grid at each of the given (row, column) offsets. Offsets must be
+    /// multiples of 3 so that the boxes of overlapping grids line up.
+    pub fn with_offsets(offsets: &[(usize, usize)]) -> Result<Gattai, Box<dyn Error>> {
+        if offsets.is_empty() {
+            return Err(Box::from("A gattai layout needs at least one grid."));
+        }
+        if offsets
+            .iter()
+            .any(|&(row, col)| row % 3 != 0 || col % 3 != 0)
+        {
+            return Err(Box::from(
+                "Grid offsets must be multiples of 3 so the boxes of overlapping grids line up.",
+            ));
+        }
+        let height = offsets
+            .iter()
+            .map(|&(row, _)| row + SIZE)
+            .max()
+            .unwrap_or(0);
+        let width = offsets
+            .iter()
+            .map(|&(_, col)| col + SIZE)
+            .max()
+            .unwrap_or(0);
+        if height > MAX_BOARD || width > MAX_BOARD {
+            return Err(Box::from(format!(
+                "Gattai boards can be at most {} cells across.",
+                MAX_BOARD
+            )));
+        }
+        let covered = |row: usize, col: usize| {
+            offsets.iter().any(|&(top, left)| {
+                (top..top + SIZE).contains(&row) && (left..left + SIZE).contains(&col)
+            })
+        };
+        let positions: Vec<(usize, usize)> = (0..height)
+            .flat_map(|row| (0..width).map(move |col| (row, col)))
+            .filter(|&(row, col)| covered(row, col))
+            .collect();
+        let index_of = |row: usize, col: usize| {
+            positions
+                .binary_search(&(row, col))
+                .expect("every grid cell is covered")
+        };
+
+        let mut units: Vec<Vec<usize>> = Vec::new();
+        for &(top, left) in offsets {
+            for i in 0..SIZE {
+                units.push((0..SIZE).map(|j| index_of(top + i, left + j)).collect());
+                units.push((0..SIZE).map(|j| index_of(top + j, left + i)).collect());
+                let (box_row, box_col) = (top + i / 3 * 3, left + i % 3 * 3);
+                units.push(
+                    (0..SIZE)
+                        .map(|j| index_of(box_row + j / 3, box_col + j % 3))
+                        .collect(),
+                );
+            }
+        }
+        let mut peers: Vec<Vec<usize>> = vec![Vec::new(); positions.len()];
+        for unit in &units {
+            for &cell in unit {
+                peers[cell].extend(unit.iter().filter(|&&other| other != cell));
+            }
+        }
+        for cell_peers in peers.iter_mut() {
+            cell_peers.sort_unstable();
+            cell_peers.dedup();
+        }
+
+        Ok(Gattai {
+            height,
+            width,
+            values: vec![0; positions.len()],
+            positions,
+            units,
+            peers,
+        })
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    // The digit at a board position, or None where no grid covers the board
+    pub fn cell(&self, row: usize, col: usize) -> Option<u32> {
+        self.positions
+            .binary_search(&(row, col))
+            .ok()
+            .map(|index| self.values[index])
+    }
+
+    fn safe_space(&self, index: usize, digit: u32) -> bool {
+        self.peers[index]
+            .iter()
+            .all(|&peer| self.values[peer] != digit)
+    }
+
+    fn is_valid(&self) -> bool {
+        (0..self.values.len())
+            .all(|index| self.values[index] == 0 || self.safe_space(index, self.values[index]))
+    }
+}
+
+// Reads the layout line, which is either a layout name or a list of row,column offsets
+fn read_layout(line: &str) -> Result<Vec<(usize, usize)>, Box<dyn Error>> {
+    let name = line.trim().to_lowercase();
+    if let Some((_, offsets)) = LAYOUTS.iter().find(|(layout, _)| *layout == name) {
+        return Ok(offsets.to_vec());
+    }
+    line.split_whitespace()
+        .map(|offset| {
+            offset
+                .split_once(',')
+                .and_then(|(row, col)| Some((row.parse().ok()?, col.parse().ok()?)))
+        })
+        .collect::<Option<Vec<(usize, usize)>>>()
+        .ok_or_else(|| {
+            let names: Vec<&str> = LAYOUTS.iter().map(|(name, _)| *name).collect();
+            Box::from(format!(
+                "Failed to read puzzle. For Gattai, the first line should name a layout ({}) or list grid offsets such as 0,0 6,6.",
+                names.join(", ")
+            ))
+        })
+}
+
+impl Backtrack for Gattai {
+    type Value = u32;
+
+    fn cells(&self) -> usize {
+        self.values.len()
+    }
+
+    fn options(&self, index: usize) -> Option<Vec<u32>> {
+        match self.values[index] {
+            0 => Some((1..=SUDOKU_SIZE).collect()),
+            _ => None,
+        }
+    }
+
+    fn place(&mut self, index: usize, digit: u32) -> bool {
+        let safe = self.safe_space(index, digit);
+        if safe {
+            self.values[index] = digit;
+        }
+        safe
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.values[index] = 0;
+    }
+}
+
+impl Puzzle for Gattai {
+    // The first line gives the layout, and the rest holds the digits of every covered cell by
+    // row, with 0 or . for empty cells. Anything else, such as spacing, is ignored.
+    fn build(contents: &mut String) -> Result<Self, Box<dyn Error>> {
+        let contents = contents.trim_start();
+        let (layout, digits) = contents.split_once('\n').unwrap_or((contents, ""));
+        let mut gattai = Gattai::with_offsets(&read_layout(layout)?)?;
+        let digits: Vec<u32> = digits
+            .chars()
+            .filter_map(|c| match c {
+                '.' => Some(0),
+                c => c.to_digit(10),
+            })
+            .collect();
+        if digits.len() != gattai.values.len() {
+            return Err(Box::from(format!(
+                "Failed to read puzzle. This layout has {} cells but {} digits were found.",
+                gattai.values.len(),
+                digits.len()
+            )));
+        }
+        gattai.values = digits;
+        Ok(gattai)
+    }
+
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        match self.is_valid() {
+            true => Search::new().run(self, budget),
+            false => Tracker::new(budget).finish(Ok(false)),
+        }
+    }
+
+    // Lays the grids out as they overlap, leaving blanks where no grid covers the board
+    fn format(&self) -> String {
+        (0..self.height)
+            .map(|row| {
+                let cells: Vec<String> = (0..self.width)
+                    .map(|col| match self.cell(row, col) {
+                        Some(0) => ".".to_string(),
+                        Some(digit) => digit.to_string(),
+                        None => " ".to_string(),
+                    })
+                    .collect();
+                cells.join(" ").trim_end().to_string()
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
@@ -0,0 +1,72 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, Outcome},
+    nonogram::Nonogram,
+    rng::Rng,
+    search::{count_solutions, Search},
+    sudoku::{Sudoku, SUDOKU_SIZE},
+};
+
+const SIZE: usize = SUDOKU_SIZE as usize;
+// A clue is kept when proving the puzzle unique without it takes longer than this, which keeps
+// generation quick at the cost of the odd extra clue
+const UNIQUENESS_NODES: u64 = 20_000;
+// Random pictures tried before giving up on making a Nonogram
+const NONOGRAM_ATTEMPTS: usize = 1000;
+
+/// Makes a random Sudoku with exactly one solution. A random solved grid is made first, then
+/// its clues are removed in random order for as long as the solution stays unique.
+pub fn generate_sudoku(rng: &mut Rng) -> Sudoku {
+    let mut board = vec![vec![0; SIZE]; SIZE];
+    // The boxes on the diagonal share no row or column, so any order of digits fits in them
+    for corner in [0, 3, 6] {
+        let mut digits: Vec<u32> = (1..=SUDOKU_SIZE).collect();
+        rng.shuffle(&mut digits);
+        for (i, digit) in digits.into_iter().enumerate() {
+            board[corner + i / 3][corner + i % 3] = digit;
+        }
+    }
+    let mut solved = Sudoku::from_board(board);
+    let report = Search::new().run(&mut solved, &Budget::unlimited());
+    debug_assert_eq!(report.outcome, Outcome::Solved);
+
+    let mut board = solved.board;
+    let mut cells: Vec<usize> = (0..SIZE * SIZE).collect();
+    rng.shuffle(&mut cells);
+    let budget = Budget::unlimited().with_max_nodes(UNIQUENESS_NODES);
+    for index in cells {
+        let (row, col) = (index / SIZE, index % SIZE);
+        let digit = board[row][col];
+        board[row][col] = 0;
+        let mut puzzle = Sudoku::from_board(board.clone());
+        if count_solutions(&mut puzzle, 2, &budget) != Ok(1) {
+            board[row][col] = digit;
+        }
+    }
+    Sudoku::from_board(board)
+}
+
+/// Makes a random Nonogram of the given size that line logic alone can solve, which also means
+/// its solution is unique. Fails if no such picture turns up after many tries, which only
+/// happens for very small or oddly shaped grids.
+pub fn generate_nonogram(
+    rng: &mut Rng,
+    width: usize,
+    height: usize,
+) -> Result<Nonogram, Box<dyn Error>> {
+    for _ in 0..NONOGRAM_ATTEMPTS {
+        // Slightly more filled than empty cells makes for longer runs, which logic handles well
+        let picture: Vec<Vec<bool>> = (0..height)
+            .map(|_| (0..width).map(|_| rng.below(100) < 60).collect())
+            .collect();
+        let mut puzzle = Nonogram::from_solution(&picture)?;
+        if puzzle.solve_by_logic(&Budget::unlimited()).outcome == Outcome::Solved {
+            return Nonogram::from_solution(&picture);
+        }
+    }
+    Err(Box::from(format!(
+        "Could not make a {}x{} nonogram with a unique solution.",
+        width, height
+    )))
+}
@@ -0,0 +1,266 @@
+use std::error::Error;
+
+use crate::inflate::zlib_decompress;
+
+const PNG_SIGNATURE: [u8; 8] = [137, 80, 78, 71, 13, 10, 26, 10];
+// Larger images are refused rather than decoded, so a bad header cannot exhaust memory
+const MAX_PIXELS: usize = 64 * 1024 * 1024;
+
+/// An 8-bit grayscale image, stored by row with 0 for black and 255 for white.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrayImage {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+impl GrayImage {
+    pub fn new(width: usize, height: usize) -> GrayImage {
+        GrayImage {
+            width,
+            height,
+            pixels: vec![255; width * height],
+        }
+    }
+
+    pub fn get(&self, x: usize, y: usize) -> u8 {
+        self.pixels[y * self.width + x]
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, value: u8) {
+        self.pixels[y * self.width + x] = value;
+    }
+
+    /// Reads a PNG or PGM (P2 or P5) image, telling the two apart by their first bytes. Colour
+    /// images are converted to grayscale, with any transparency laid over white.
+    pub fn decode(bytes: &[u8]) -> Result<GrayImage, Box<dyn Error>> {
+        match bytes {
+            [137, 80, 78, 71, ..] => decode_png(bytes),
+            [b'P', b'2' | b'5', ..] => decode_pgm(bytes),
+            _ => Err(Box::from(
+                "Unrecognized image format. Images must be PNG or PGM files.",
+            )),
+        }
+    }
+}
+
+fn check_size(width: usize, height: usize) -> Result<(), Box<dyn Error>> {
+    match width > 0 && height > 0 && width.saturating_mul(height) <= MAX_PIXELS {
+        true => Ok(()),
+        false => Err(Box::from(format!(
+            "The image is {}x{} pixels, which is empty or too large to read.",
+            width, height
+        ))),
+    }
+}
+
+fn decode_pgm(bytes: &[u8]) -> Result<GrayImage, Box<dyn Error>> {
+    let binary = bytes[1] == b'5';
+    let mut position = 2;
+    // Reads the next number in the header, skipping whitespace and # comments
+    let number = |position: &mut usize| -> Result<usize, Box<dyn Error>> {
+        loop {
+            match bytes.get(*position) {
+                Some(b'#') => {
+                    while bytes.get(*position).is_some_and(|&byte| byte != b'\n') {
+                        *position += 1;
+                    }
+                }
+                Some(byte) if byte.is_ascii_whitespace() => *position += 1,
+                _ => break,
+            }
+        }
+        let start = *position;
+        while bytes.get(*position).is_some_and(u8::is_ascii_digit) {
+            *position += 1;
+        }
+        std::str::from_utf8(&bytes[start..*position])
+            .ok()
+            .and_then(|digits| digits.parse().ok())
+            .ok_or_else(|| Box::from("The PGM image has an invalid header."))
+    };
+    let width = number(&mut position)?;
+    let height = number(&mut position)?;
+    let max_value = number(&mut position)?;
+    check_size(width, height)?;
+    if max_value == 0 || max_value > 65535 {
+        return Err(Box::from("The PGM image has an invalid maximum value."));
+    }
+
+    let count = width * height;
+    let samples: Vec<usize> = match binary {
+        true => {
+            // A single whitespace byte separates the header from the pixels
+            let data = bytes.get(position + 1..).unwrap_or_default();
+            match max_value {
+                0..=255 => data.iter().take(count).map(|&byte| byte.into()).collect(),
+                _ => data
+                    .chunks_exact(2)
+                    .take(count)
+                    .map(|pair| usize::from(pair[0]) << 8 | usize::from(pair[1]))
+                    .collect(),
+            }
+        }
+        false => bytes[position..]
+            .split(u8::is_ascii_whitespace)
+            .filter(|token| !token.is_empty())
+            .take(count)
+            .map(|token| std::str::from_utf8(token).ok()?.parse().ok())
+            .collect::<Option<Vec<usize>>>()
+            .ok_or("The PGM image has an invalid pixel value.")?,
+    };
+    if samples.len() < count {
+        return Err(Box::from("The PGM image ends early."));
+    }
+    Ok(GrayImage {
+        width,
+        height,
+        pixels: samples
+            .iter()
+            .map(|&sample| (sample.min(max_value) * 255 / max_value) as u8)
+            .collect(),
+    })
+}
+
+fn decode_png(bytes: &[u8]) -> Result<GrayImage, Box<dyn Error>> {
+    if bytes.len() < 8 || bytes[..8] != PNG_SIGNATURE {
+        return Err(Box::from("The PNG image has an invalid signature."));
+    }
+    let mut header = None;
+    let (mut palette, mut data) = (Vec::new(), Vec::new());
+    let mut position = 8;
+    while position + 8 <= bytes.len() {
+        let length = u32::from_be_bytes(bytes[position..position + 4].try_into()?) as usize;
+        let kind = &bytes[position + 4..position + 8];
+        let chunk = bytes
+            .get(position + 8..position + 8 + length)
+            .ok_or("The PNG image ends early.")?;
+        match kind {
+            b"IHDR" if chunk.len() == 13 => header = Some(chunk),
+            b"PLTE" => palette = chunk.to_vec(),
+            b"IDAT" => data.extend_from_slice(chunk),
+            b"IEND" => break,
+            _ => {}
+        }
+        // Skips the chunk and its checksum
+        position += length + 12;
+    }
+    let header = header.ok_or("The PNG image has no header.")?;
+    let width = u32::from_be_bytes(header[0..4].try_into()?) as usize;
+    let height = u32::from_be_bytes(header[4..8].try_into()?) as usize;
+    let (depth, color_type, interlace) = (header[8], header[9], header[12]);
+    check_size(width, height)?;
+    if interlace != 0 {
+        return Err(Box::from("Interlaced PNG images are not supported."));
+    }
+    let channels = match (color_type, depth) {
+        (0, 1 | 2 | 4 | 8 | 16) => 1,
+        (3, 1 | 2 | 4 | 8) => 1,
+        (4, 8 | 16) => 2,
+        (2, 8 | 16) => 3,
+        (6, 8 | 16) => 4,
+        _ => {
+            return Err(Box::from(format!(
+                "PNG images with color type {} and bit depth {} are not supported.",
+                color_type, depth
+            )))
+        }
+    };
+
+    let bits_per_pixel = channels * depth as usize;
+    let stride = (width * bits_per_pixel).div_ceil(8);
+    let raw = zlib_decompress(&data, height * (stride + 1))?;
+    let rows = unfilter(&raw, height, stride, bits_per_pixel.div_ceil(8))?;
+
+    let mut image = GrayImage::new(width, height);
+    for (y, row) in rows.chunks(stride).enumerate() {
+        for x in 0..width {
+            // Samples are scaled to 8 bits, keeping the high byte of 16-bit samples
+            let sample = |channel: usize| -> u32 {
+                let index = x * channels + channel;
+                match depth {
+                    16 => row[index * 2].into(),
+                    8 => row[index].into(),
+                    _ => {
+                        let bit = index * depth as usize;
+                        let value =
+                            (row[bit / 8] >> (8 - depth as usize - bit % 8)) & ((1 << depth) - 1);
+                        match color_type {
+                            3 => value.into(),
+                            _ => u32::from(value) * 255 / ((1 << depth) - 1),
+                        }
+                    }
+                }
+            };
+            let luma = |r: u32, g: u32, b: u32| (r * 299 + g * 587 + b * 114) / 1000;
+            let over_white = |gray: u32, alpha: u32| (gray * alpha + 255 * (255 - alpha)) / 255;
+            let gray = match color_type {
+                0 => sample(0),
+                2 => luma(sample(0), sample(1), sample(2)),
+                3 => {
+                    let entry = palette
+                        .get(sample(0) as usize * 3..sample(0) as usize * 3 + 3)
+                        .ok_or("The PNG image uses a colour missing from its palette.")?;
+                    luma(entry[0].into(), entry[1].into(), entry[2].into())
+                }
+                4 => over_white(sample(0), sample(1)),
+                _ => over_white(luma(sample(0), sample(1), sample(2)), sample(3)),
+            };
+            image.set(x, y, gray as u8);
+        }
+    }
+    Ok(image)
+}
+
+// Undoes the filter each PNG row starts with, returning the rows without their filter bytes
+fn unfilter(
+    raw: &[u8],
+    height: usize,
+    stride: usize,
+    bytes_per_pixel: usize,
+) -> Result<Vec<u8>, Box<dyn Error>> {
+    if raw.len() < height * (stride + 1) {
+        return Err(Box::from("The PNG image data ends early."));
+    }
+    let mut rows = vec![0u8; height * stride];
+    for y in 0..height {
+        let filter = raw[y * (stride + 1)];
+        let line = &raw[y * (stride + 1) + 1..(y + 1) * (stride + 1)];
+        for x in 0..stride {
+            let left = match x >= bytes_per_pixel {
+                true => rows[y * stride + x - bytes_per_pixel],
+                false => 0,
+            };
+            let up = match y > 0 {
+                true => rows[(y - 1) * stride + x],
+                false => 0,
+            };
+            let up_left = match x >= bytes_per_pixel && y > 0 {
+                true => rows[(y - 1) * stride + x - bytes_per_pixel],
+                false => 0,
+            };
+            let predicted = match filter {
+                0 => 0,
+                1 => left,
+                2 => up,
+                3 => ((u16::from(left) + u16::from(up)) / 2) as u8,
+                4 => paeth(left, up, up_left),
+                _ => return Err(Box::from("The PNG image has an invalid row filter.")),
+            };
+            rows[y * stride + x] = line[x].wrapping_add(predicted);
+        }
+    }
+    Ok(rows)
+}
+
+fn paeth(left: u8, up: u8, up_left: u8) -> u8 {
+    let estimate = i16::from(left) + i16::from(up) - i16::from(up_left);
+    let distance = |value: u8| (estimate - i16::from(value)).abs();
+    if distance(left) <= distance(up) && distance(left) <= distance(up_left) {
+        left
+    } else if distance(up) <= distance(up_left) {
+        up
+    } else {
+        up_left
+    }
+}
@@ -0,0 +1,225 @@
+// A small decoder for zlib streams (RFC 1950 and 1951), which is all PNG images need
+
+// Extra bits and base values for each length and distance code
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+// The order code length code lengths are stored in for dynamic blocks
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+const MAX_BITS: usize = 15;
+
+struct BitReader<'a> {
+    data: &'a [u8],
+    // Position in bits, read from the lowest bit of each byte up
+    position: usize,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, count: u32) -> Result<u32, String> {
+        let mut value = 0;
+        for bit in 0..count {
+            let byte = self
+                .data
+                .get(self.position / 8)
+                .ok_or("The compressed image data ends early.")?;
+            value |= ((byte >> (self.position % 8)) as u32 & 1) << bit;
+            self.position += 1;
+        }
+        Ok(value)
+    }
+
+    fn align_to_byte(&mut self) {
+        self.position = self.position.div_ceil(8) * 8;
+    }
+}
+
+// A canonical Huffman code, stored as the number of codes of each length and the symbols in
+// code order
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Huffman {
+        let mut counts = [0; MAX_BITS + 1];
+        for &length in lengths {
+            counts[length as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0; MAX_BITS + 2];
+        for length in 1..=MAX_BITS {
+            offsets[length + 1] = offsets[length] + counts[length] as usize;
+        }
+        let mut symbols = vec![0; offsets[MAX_BITS + 1]];
+        for (symbol, &length) in lengths.iter().enumerate() {
+            if length > 0 {
+                symbols[offsets[length as usize]] = symbol as u16;
+                offsets[length as usize] += 1;
+            }
+        }
+        Huffman { counts, symbols }
+    }
+
+    fn decode(&self, reader: &mut BitReader) -> Result<u16, String> {
+        let (mut code, mut first, mut index) = (0i32, 0i32, 0i32);
+        for length in 1..=MAX_BITS {
+            code |= reader.bits(1)? as i32;
+            let count = self.counts[length] as i32;
+            if code - count < first {
+                return Ok(self.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        Err("The compressed image data has an invalid code.".to_string())
+    }
+}
+
+/// Decompresses a zlib stream, refusing to produce more than `limit` bytes.
+pub(crate) fn zlib_decompress(data: &[u8], limit: usize) -> Result<Vec<u8>, String> {
+    let [method, flags, ..] = *data else {
+        return Err("The compressed image data is empty.".to_string());
+    };
+    if method & 0x0f != 8 || (u16::from(method) << 8 | u16::from(flags)) % 31 != 0 {
+        return Err("The image data is not a valid zlib stream.".to_string());
+    }
+    if flags & 0x20 != 0 {
+        return Err(
+            "The image data needs a preset dictionary, which is not supported.".to_string(),
+        );
+    }
+    let mut reader = BitReader {
+        data: &data[2..],
+        position: 0,
+    };
+    let mut output = Vec::new();
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => stored_block(&mut reader, &mut output)?,
+            1 => {
+                let mut lengths = [8; 288];
+                lengths[144..256].fill(9);
+                lengths[256..280].fill(7);
+                let (literals, distances) = (Huffman::new(&lengths), Huffman::new(&[5; 30]));
+                compressed_block(&mut reader, &mut output, &literals, &distances, limit)?;
+            }
+            2 => {
+                let (literals, distances) = dynamic_codes(&mut reader)?;
+                compressed_block(&mut reader, &mut output, &literals, &distances, limit)?;
+            }
+            _ => return Err("The compressed image data has an invalid block type.".to_string()),
+        }
+        if output.len() > limit {
+            return Err("The image data is larger than the image size allows.".to_string());
+        }
+        if last {
+            return Ok(output);
+        }
+    }
+}
+
+fn stored_block(reader: &mut BitReader, output: &mut Vec<u8>) -> Result<(), String> {
+    reader.align_to_byte();
+    let length = reader.bits(16)?;
+    if reader.bits(16)? != !length & 0xffff {
+        return Err("The compressed image data has a corrupt stored block.".to_string());
+    }
+    let start = reader.position / 8;
+    let bytes = reader
+        .data
+        .get(start..start + length as usize)
+        .ok_or("The compressed image data ends early.")?;
+    output.extend_from_slice(bytes);
+    reader.position += 8 * length as usize;
+    Ok(())
+}
+
+fn dynamic_codes(reader: &mut BitReader) -> Result<(Huffman, Huffman), String> {
+    let literal_count = reader.bits(5)? as usize + 257;
+    let distance_count = reader.bits(5)? as usize + 1;
+    let code_length_count = reader.bits(4)? as usize + 4;
+    let mut code_lengths = [0; 19];
+    for &symbol in &CODE_LENGTH_ORDER[..code_length_count] {
+        code_lengths[symbol] = reader.bits(3)? as u8;
+    }
+    let code_length_code = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(literal_count + distance_count);
+    while lengths.len() < literal_count + distance_count {
+        let (length, repeat) = match code_length_code.decode(reader)? {
+            symbol @ 0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or("The compressed image data repeats a missing code length.")?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(length, repeat as usize));
+    }
+    if lengths.len() > literal_count + distance_count {
+        return Err("The compressed image data has too many code lengths.".to_string());
+    }
+    let (literals, distances) = lengths.split_at(literal_count);
+    Ok((Huffman::new(literals), Huffman::new(distances)))
+}
+
+fn compressed_block(
+    reader: &mut BitReader,
+    output: &mut Vec<u8>,
+    literals: &Huffman,
+    distances: &Huffman,
+    limit: usize,
+) -> Result<(), String> {
+    loop {
+        let symbol = literals.decode(reader)? as usize;
+        match symbol {
+            0..=255 => output.push(symbol as u8),
+            256 => return Ok(()),
+            _ => {
+                let code = symbol - 257;
+                if code >= LENGTH_BASE.len() {
+                    return Err("The compressed image data has an invalid length.".to_string());
+                }
+                let length =
+                    LENGTH_BASE[code] as usize + reader.bits(LENGTH_EXTRA[code].into())? as usize;
+                let code = distances.decode(reader)? as usize;
+                if code >= DISTANCE_BASE.len() {
+                    return Err("The compressed image data has an invalid distance.".to_string());
+                }
+                let distance = DISTANCE_BASE[code] as usize
+                    + reader.bits(DISTANCE_EXTRA[code].into())? as usize;
+                if distance > output.len() {
+                    return Err("The compressed image data refers back too far.".to_string());
+                }
+                // Copies byte by byte, since the match may overlap what it is copying
+                let start = output.len() - distance;
+                for i in 0..length {
+                    output.push(output[start + i]);
+                }
+            }
+        }
+        if output.len() > limit {
+            return Err("The image data is larger than the image size allows.".to_string());
+        }
+    }
+}
@@ -1,96 +1,816 @@
+#[cfg(any(feature = "wasm", feature = "ffi", feature = "python"))]
+mod api;
+mod binairo;
+mod budget;
+mod candidates;
+mod check;
+mod config;
+mod crossword;
+#[cfg(feature = "ffi")]
+mod ffi;
+mod gattai;
+mod generate;
+mod image;
+mod inflate;
+#[cfg(feature = "server")]
+mod json;
 mod nonogram;
+mod ocr;
+mod play;
+#[cfg(feature = "python")]
+mod python;
+mod rng;
+mod sat;
+mod search;
+#[cfg(feature = "server")]
+mod server;
+mod slitherlink;
+mod starbattle;
 mod sudoku;
+mod symmetry;
+mod tents;
 mod tests;
+#[cfg(feature = "wasm")]
+mod wasm;
+mod watch;
+mod wordsearch;
 
-use nonogram::Nonogram;
+pub use binairo::Binairo;
+pub use budget::{Budget, CancelToken, GiveUp, Outcome, Report, Stats};
+pub use check::{Unit, Violation};
+pub use config::{user_config_path, Config, PROJECT_CONFIG};
+use config::{InputFormat, OutputFormat};
+pub use crossword::{Crossword, BLANK, BLOCK};
+pub use gattai::Gattai;
+pub use generate::{generate_nonogram, generate_sudoku};
+pub use image::GrayImage;
+#[cfg(feature = "server")]
+pub use json::Json;
+pub use nonogram::{build_runs, Nonogram, EMPTY, FILLED, UNKNOWN};
+pub use ocr::{draw_digit, recognize_sudoku, DIGIT_FONT};
+pub use rng::Rng;
+pub use sat::{encode_units, nonogram_variables, solve_cnf, solve_with_sat, Cnf, Encode};
+pub use search::{count_solutions, Backtrack, Search};
+#[cfg(feature = "server")]
+pub use server::{handle_request, serve, Response, ServerConfig};
+pub use slitherlink::Slitherlink;
+pub use starbattle::StarBattle;
 use std::{
     error::Error,
-    fs::{self, File},
-    io::Write,
+    fs,
+    io::{self, BufRead, Read, Write},
     path::Path,
+    time::Duration,
 };
-use sudoku::*;
+pub use sudoku::*;
+pub use symmetry::{find_duplicates, Transform};
+pub use tents::{Tents, TENT, TREE};
+pub use wordsearch::WordSearch;
 
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Default, PartialEq)]
+enum Mode {
+    #[default]
+    Solve,
+    Play,
+    Dedupe,
+    Transform,
+    Candidates,
+    Watch,
+    Check,
+}
+
+/// The algorithm used to solve a puzzle.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+enum Backend {
+    #[default]
+    Search,
+    Sat,
+}
+
+impl Backend {
+    fn from_name(name: &str) -> Option<Backend> {
+        match name.to_lowercase().as_str() {
+            "search" => Some(Backend::Search),
+            "sat" => Some(Backend::Sat),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Default, PartialEq)]
 pub struct Command {
+    mode: Mode,
     puzzle: String,
     filename: String,
+    timeout: Option<Duration>,
+    max_nodes: Option<u64>,
+    stats: bool,
+    backend: Backend,
+    dimacs: Option<String>,
+    remove: bool,
+    seed: Option<u64>,
+    count: Option<usize>,
+    logic_only: bool,
+    output: Option<String>,
+    output_dir: Option<String>,
+    input_format: InputFormat,
+    output_format: OutputFormat,
+    interval: Option<Duration>,
+    original: Option<String>,
 }
 
 impl Command {
-    pub fn build(mut args: impl Iterator<Item = String>) -> Result<Command, &'static str> {
+    pub fn build(args: impl Iterator<Item = String>) -> Result<Command, &'static str> {
+        Command::build_with_config(args, &Config::default())
+    }
+
+    /// Builds a command with defaults taken from a config, which any flags given override.
+    /// When the config names a default puzzle type, the puzzle argument may be left out.
+    pub fn build_with_config(
+        mut args: impl Iterator<Item = String>,
+        config: &Config,
+    ) -> Result<Command, &'static str> {
         args.next();
 
-        let puzzle = match args.next() {
-            Some(arg) => arg.to_lowercase(),
-            None => return Err("At least 1 argument is required to specify puzzle type."),
+        let mut positional: Vec<String> = Vec::new();
+        let (mut timeout, mut max_nodes, mut stats) = (None, None, false);
+        let (mut backend, mut dimacs, mut remove) = (None, None, false);
+        let (mut seed, mut count, mut logic_only) = (None, None, false);
+        let (mut output, mut output_dir) = (None, None);
+        let (mut input_format, mut output_format, mut interval) = (None, None, None);
+        let mut original = None;
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--timeout" => {
+                    let seconds = args
+                        .next()
+                        .and_then(|value| value.parse::<f64>().ok())
+                        .filter(|seconds| *seconds > 0.0)
+                        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                        .ok_or("--timeout requires a positive number of seconds.")?;
+                    timeout = Some(seconds);
+                }
+                "--max-nodes" => {
+                    let nodes = args
+                        .next()
+                        .and_then(|value| value.parse::<u64>().ok())
+                        .filter(|nodes| *nodes > 0)
+                        .ok_or("--max-nodes requires a positive whole number.")?;
+                    max_nodes = Some(nodes);
+                }
+                "--stats" => stats = true,
+                "--remove" => remove = true,
+                "--logic-only" => logic_only = true,
+                "--backend" => {
+                    backend = Some(
+                        args.next()
+                            .and_then(|value| Backend::from_name(&value))
+                            .ok_or("--backend must be either search or sat.")?,
+                    );
+                }
+                "--input-format" => {
+                    input_format = Some(
+                        args.next()
+                            .and_then(|value| InputFormat::from_name(&value))
+                            .ok_or("--input-format must be auto, text or image.")?,
+                    );
+                }
+                "--output-format" => {
+                    output_format = Some(
+                        args.next()
+                            .and_then(|value| OutputFormat::from_name(&value))
+                            .ok_or("--output-format must be either report or grid.")?,
+                    );
+                }
+                "--interval" => {
+                    let seconds = args
+                        .next()
+                        .and_then(|value| value.parse::<f64>().ok())
+                        .filter(|seconds| *seconds > 0.0)
+                        .and_then(|seconds| Duration::try_from_secs_f64(seconds).ok())
+                        .ok_or("--interval requires a positive number of seconds.")?;
+                    interval = Some(seconds);
+                }
+                "--original" => {
+                    original = Some(
+                        args.next()
+                            .ok_or("--original requires the file of the original puzzle.")?,
+                    );
+                }
+                "--output-dir" => {
+                    output_dir = Some(
+                        args.next()
+                            .ok_or("--output-dir requires a directory name.")?,
+                    );
+                }
+                // The config is loaded before the command is built, so only its name is checked
+                "--config" => {
+                    args.next().ok_or("--config requires a config file name.")?;
+                }
+                "--output" => {
+                    output = Some(
+                        args.next()
+                            .ok_or("--output requires a file name, or - for stdout.")?,
+                    );
+                }
+                "--dimacs" => {
+                    dimacs = Some(
+                        args.next()
+                            .ok_or("--dimacs requires an output file name.")?,
+                    );
+                }
+                "--seed" => {
+                    seed = Some(
+                        args.next()
+                            .and_then(|value| value.parse::<u64>().ok())
+                            .ok_or("--seed requires a whole number.")?,
+                    );
+                }
+                "--count" => {
+                    count = Some(
+                        args.next()
+                            .and_then(|value| value.parse::<usize>().ok())
+                            .filter(|count| *count > 0)
+                            .ok_or("--count requires a positive whole number.")?,
+                    );
+                }
+                _ => positional.push(arg),
+            }
+        }
+        let mut positional = positional.into_iter().peekable();
+
+        let mode = match positional.peek().map(|arg| arg.to_lowercase()).as_deref() {
+            Some("play") => Mode::Play,
+            Some("dedupe") => Mode::Dedupe,
+            Some("transform") => Mode::Transform,
+            Some("candidates") => Mode::Candidates,
+            Some("watch") => Mode::Watch,
+            Some("check") => Mode::Check,
+            _ => Mode::Solve,
         };
+        if mode != Mode::Solve {
+            positional.next();
+        }
 
-        let filename = match args.next() {
-            Some(arg) => arg,
-            None => format!("{}.txt", puzzle),
+        // Dedupe, transform and check only work on Sudoku, and watch works out the type of each file,
+        // so their only argument is the file. With a default puzzle type in the config, an
+        // argument that is not a puzzle type is the file.
+        let fixed_puzzle = matches!(
+            mode,
+            Mode::Dedupe | Mode::Transform | Mode::Candidates | Mode::Check | Mode::Watch
+        );
+        let names_puzzle = |arg: &String| {
+            !fixed_puzzle && (config.puzzle.is_none() || PuzzleType::from_name(arg).is_some())
+        };
+        let puzzle = match (&mode, positional.next_if(names_puzzle)) {
+            (Mode::Watch, _) => String::new(),
+            (Mode::Dedupe | Mode::Transform | Mode::Candidates | Mode::Check, _) => {
+                String::from("sudoku")
+            }
+            (_, Some(arg)) => arg.to_lowercase(),
+            (_, None) => config
+                .puzzle
+                .clone()
+                .ok_or("At least 1 argument is required to specify puzzle type.")?,
         };
 
-        Ok(Command { puzzle, filename })
+        let filename = match (&mode, positional.next()) {
+            (_, Some(arg)) => arg,
+            (Mode::Dedupe, None) => String::from("puzzles.txt"),
+            (Mode::Watch, None) => String::from("."),
+            (_, None) => format!("{}.txt", puzzle),
+        };
+
+        Ok(Command {
+            mode,
+            puzzle,
+            filename,
+            timeout: timeout.or(config.timeout),
+            max_nodes: max_nodes.or(config.max_nodes),
+            stats: stats || config.stats.unwrap_or(false),
+            backend: backend.or(config.backend).unwrap_or_default(),
+            dimacs,
+            remove,
+            seed,
+            count,
+            logic_only,
+            output,
+            output_dir: output_dir.or_else(|| config.output_dir.clone()),
+            input_format: input_format.or(config.input_format).unwrap_or_default(),
+            output_format: output_format.or(config.output_format).unwrap_or_default(),
+            interval,
+            original,
+        })
+    }
+
+    // Where a result goes: the --output path if one was given, otherwise the default file name
+    // inside the output directory
+    fn output_path(&self, default: &str) -> String {
+        match (&self.output, &self.output_dir) {
+            (Some(path), _) => path.clone(),
+            (None, Some(dir)) => Path::new(dir).join(default).to_string_lossy().into_owned(),
+            (None, None) => default.to_string(),
+        }
+    }
+
+    fn budget(&self) -> Budget {
+        let mut budget = Budget::unlimited();
+        if let Some(timeout) = self.timeout {
+            budget = budget.with_timeout(timeout);
+        }
+        if let Some(max_nodes) = self.max_nodes {
+            budget = budget.with_max_nodes(max_nodes);
+        }
+        budget
     }
 }
 
-trait Puzzle {
+pub trait Puzzle {
     fn build(contents: &mut String) -> Result<Self, Box<dyn Error>>
     where
         Self: Sized;
-    fn solve(&mut self) -> bool;
+    fn solve_within(&mut self, budget: &Budget) -> Report;
     fn format(&self) -> String;
+
+    fn solve(&mut self) -> bool {
+        self.solve_within(&Budget::unlimited()).outcome == Outcome::Solved
+    }
+
+    // Builds a puzzle from any reader, such as a file, stdin or an in-memory buffer
+    fn read_from(mut reader: impl Read) -> Result<Self, Box<dyn Error>>
+    where
+        Self: Sized,
+    {
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        Self::build(&mut contents)
+    }
+
+    fn write_to(&self, mut writer: impl Write) -> io::Result<()> {
+        writeln!(writer, "{}", self.format())
+    }
 }
 
 #[derive(Debug)]
 enum PuzzleType {
     Sudoku,
     Nonogram,
+    Gattai,
+    WordSearch,
+    Crossword,
+    Slitherlink,
+    StarBattle,
+    Tents,
+    Binairo,
+    Cnf,
 }
 
-fn sudoku_puzzle(contents: &mut String) -> Result<String, Box<dyn Error>> {
-    let mut sudoku = Sudoku::build(contents)?;
-    match sudoku.solve() {
-        true => Ok(sudoku.format()),
-        false => Err(Box::from("The given sudoku could not be solved.")),
+impl PuzzleType {
+    fn from_name(name: &str) -> Option<PuzzleType> {
+        match name.to_lowercase().as_str() {
+            "sudoku" => Some(PuzzleType::Sudoku),
+            "nonogram" => Some(PuzzleType::Nonogram),
+            "gattai" => Some(PuzzleType::Gattai),
+            "wordsearch" => Some(PuzzleType::WordSearch),
+            "crossword" => Some(PuzzleType::Crossword),
+            "slitherlink" => Some(PuzzleType::Slitherlink),
+            "starbattle" => Some(PuzzleType::StarBattle),
+            "tents" => Some(PuzzleType::Tents),
+            "binairo" | "takuzu" => Some(PuzzleType::Binairo),
+            "cnf" | "dimacs" => Some(PuzzleType::Cnf),
+            _ => None,
+        }
+    }
+}
+
+fn report_result(puzzle: &str, solution: String, report: Report) -> Result<String, Box<dyn Error>> {
+    match report.outcome {
+        Outcome::Solved => Ok(solution),
+        Outcome::Unsolvable => Err(Box::from(format!(
+            "The given {} could not be solved.",
+            puzzle
+        ))),
+        Outcome::GaveUp(reason) => Err(Box::from(format!(
+            "Gave up on the given {} ({}) after {} nodes in {:.2?}.",
+            puzzle,
+            reason.describe(),
+            report.stats.nodes,
+            report.stats.elapsed
+        ))),
     }
 }
 
-fn nonogram_puzzle(contents: &mut String) -> Result<String, Box<dyn Error>> {
+fn solve_puzzle<P: Puzzle + Encode>(
+    name: &str,
+    puzzle: &mut P,
+    command: &Command,
+) -> Result<String, Box<dyn Error>> {
+    if let Some(path) = &command.dimacs {
+        fs::write(path, puzzle.encode().to_dimacs())?;
+        eprintln!("CNF encoding written to {}", path);
+    }
+    let budget = command.budget();
+    let report = match command.backend {
+        Backend::Search => puzzle.solve_within(&budget),
+        Backend::Sat => solve_with_sat(puzzle, &budget),
+    };
+    if command.stats {
+        eprintln!("{}", report.stats);
+    }
+    report_result(name, puzzle.format(), report)
+}
+
+// Solves a puzzle that has no SAT encoding, which only the backtracking search can handle
+fn search_puzzle<P: Puzzle>(
+    name: &str,
+    puzzle: &mut P,
+    command: &Command,
+) -> Result<String, Box<dyn Error>> {
+    if command.backend == Backend::Sat || command.dimacs.is_some() {
+        return Err(Box::from(format!(
+            "The {} puzzle can only be solved with the search backend.",
+            name
+        )));
+    }
+    let report = puzzle.solve_within(&command.budget());
+    if command.stats {
+        eprintln!("{}", report.stats);
+    }
+    report_result(name, puzzle.format(), report)
+}
+
+// Solves a DIMACS CNF file with the built-in SAT solver whatever the backend, giving the
+// satisfying assignment as a "v" line like other SAT solvers print
+fn cnf_formula(contents: &str, command: &Command) -> Result<String, Box<dyn Error>> {
+    let cnf = Cnf::from_dimacs(contents)?;
+    let (report, model) = solve_cnf(&cnf, &command.budget());
+    if command.stats {
+        eprintln!("{}", report.stats);
+    }
+    let mut line = vec!["v".to_string()];
+    for (var, &value) in model.unwrap_or_default().iter().enumerate().skip(1) {
+        line.push(match value {
+            true => var.to_string(),
+            false => format!("-{}", var),
+        });
+    }
+    line.push("0".to_string());
+    let solution = line.join(" ");
+    report_result("CNF formula", solution, report)
+}
+
+fn sudoku_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
+    let mut sudoku = Sudoku::build(contents)?;
+    solve_puzzle("sudoku", &mut sudoku, command)
+}
+
+fn gattai_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
+    let mut gattai = Gattai::build(contents)?;
+    solve_puzzle("gattai", &mut gattai, command)
+}
+
+fn word_search_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
+    let mut word_search = WordSearch::build(contents)?;
+    search_puzzle("word search", &mut word_search, command)
+}
+
+fn crossword_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
+    let mut crossword = Crossword::build(contents)?;
+    search_puzzle("crossword", &mut crossword, command)
+}
+
+fn slitherlink_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
+    let mut slitherlink = Slitherlink::build(contents)?;
+    search_puzzle("slitherlink", &mut slitherlink, command)
+}
+
+fn star_battle_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
+    let mut star_battle = StarBattle::build(contents)?;
+    search_puzzle("star battle", &mut star_battle, command)
+}
+
+fn tents_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
+    let mut tents = Tents::build(contents)?;
+    search_puzzle("tents", &mut tents, command)
+}
+
+fn binairo_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
+    let mut binairo = Binairo::build(contents)?;
+    search_puzzle("binairo", &mut binairo, command)
+}
+
+fn nonogram_puzzle(contents: &mut String, command: &Command) -> Result<String, Box<dyn Error>> {
     let mut nonogram = Nonogram::build(contents)?;
-    println!("Warning: Nonogram solving algorithm is poorly optimized. Large puzzles may take a while to solve.");
-    match nonogram.solve() {
-        true => Ok(nonogram.format()),
-        false => Err(Box::from("The given nonogram could not be solved.")),
+    if command.logic_only {
+        return nonogram_logic(&mut nonogram, command);
     }
+    if command.backend == Backend::Search {
+        eprintln!("Warning: Nonogram solving algorithm is poorly optimized. Large puzzles may take a while to solve.");
+    }
+    solve_puzzle("nonogram", &mut nonogram, command)
 }
 
-pub fn run(command: Command) -> Result<(), Box<dyn Error>> {
-    let puzzle: PuzzleType = match command.puzzle.as_str() {
-        "sudoku" => PuzzleType::Sudoku,
-        "nonogram" => PuzzleType::Nonogram,
-        _ => return Err(Box::from("The specified puzzle is not supported.")),
+// Runs line logic alone. Getting stuck is not an error here, since the partial grid is
+// exactly what a designer checking for guess-free solving wants to see.
+fn nonogram_logic(nonogram: &mut Nonogram, command: &Command) -> Result<String, Box<dyn Error>> {
+    let report = nonogram.solve_by_logic(&command.budget());
+    if command.stats {
+        eprintln!("{}", report.stats);
+    }
+    match report.outcome {
+        Outcome::Solved => eprintln!("The nonogram can be solved with line logic alone."),
+        Outcome::GaveUp(GiveUp::NeedsGuess) => eprintln!(
+            "Line logic alone cannot finish the nonogram. Undecided cells are marked with {}.",
+            UNKNOWN
+        ),
+        _ => return report_result("nonogram", nonogram.format(), report),
+    }
+    Ok(nonogram.format())
+}
+
+// A puzzle from a collection file, with its line number and original text
+type CollectionEntry<'a> = (usize, &'a str, Sudoku);
+
+// Reads a collection with one Sudoku per line, where empty cells may be written as 0 or '.'
+fn read_collection(contents: &str) -> Result<Vec<CollectionEntry<'_>>, Box<dyn Error>> {
+    let mut puzzles = Vec::new();
+    for (number, line) in contents.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let sudoku = Sudoku::build(&mut line.replace('.', "0"))
+            .map_err(|_| format!("Line {} is not an 81-digit Sudoku.", number + 1))?;
+        puzzles.push((number + 1, line, sudoku));
+    }
+    Ok(puzzles)
+}
+
+fn dedupe_collection(contents: &str) -> Result<(String, Vec<String>), Box<dyn Error>> {
+    let puzzles = read_collection(contents)?;
+    let boards: Vec<Sudoku> = puzzles
+        .iter()
+        .map(|(_, _, sudoku)| sudoku.clone())
+        .collect();
+    let mut report = String::new();
+    let mut unique = Vec::new();
+    for ((number, line, _), duplicate) in puzzles.iter().zip(find_duplicates(&boards)) {
+        match duplicate {
+            Some(original) => report.push_str(&format!(
+                "Line {} is equivalent to line {}\n",
+                number, puzzles[original].0
+            )),
+            None => unique.push(line.to_string()),
+        }
+    }
+    report.push_str(&format!(
+        "{} puzzles read, {} duplicates found.",
+        puzzles.len(),
+        puzzles.len() - unique.len()
+    ));
+    Ok((report, unique))
+}
+
+// Writes a Sudoku as a single 81-digit line, the format used by collection files
+fn collection_line(sudoku: &Sudoku) -> String {
+    sudoku
+        .board
+        .iter()
+        .flatten()
+        .map(|digit| digit.to_string())
+        .collect()
+}
+
+// Random equivalent variants of a puzzle. Each is a relabelling of the original, so a
+// puzzle with a unique solution keeps exactly one.
+fn transform_sudoku(sudoku: &Sudoku, seed: u64, count: usize) -> Vec<Sudoku> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|_| Transform::random(&mut rng).apply(sudoku))
+        .collect()
+}
+
+// The file name that stands for stdin when reading and stdout when writing
+const STANDARD_STREAM: &str = "-";
+
+fn read_input(filename: &str, mut input: impl Read) -> io::Result<String> {
+    match filename {
+        STANDARD_STREAM => {
+            let mut contents = String::new();
+            input.read_to_string(&mut contents)?;
+            Ok(contents)
+        }
+        _ => fs::read_to_string(filename),
+    }
+}
+
+// Puzzles in files with these extensions are read from a picture of the grid
+const IMAGE_EXTENSIONS: [&str; 2] = ["png", "pgm"];
+
+fn is_image_path(path: &str) -> bool {
+    path.rsplit_once('.')
+        .is_some_and(|(_, extension)| IMAGE_EXTENSIONS.contains(&extension.to_lowercase().as_str()))
+}
+
+// Recognizes the Sudoku in an image file and returns it as a line of digits. The grid is
+// echoed so that a misread digit can be spotted before trusting the solution.
+fn read_sudoku_image(path: &str, mut input: impl Read) -> Result<String, Box<dyn Error>> {
+    let bytes = match path {
+        STANDARD_STREAM => {
+            let mut bytes = Vec::new();
+            input.read_to_end(&mut bytes)?;
+            bytes
+        }
+        _ => fs::read(path)?,
     };
+    let image = GrayImage::decode(&bytes)?;
+    let line = collection_line(&recognize_sudoku(&image)?);
+    let rows: Vec<&str> = (0..9).map(|row| &line[row * 9..row * 9 + 9]).collect();
+    eprintln!("Recognized grid:\n{}", rows.join("\n"));
+    Ok(line)
+}
 
-    println!(
+fn write_output(path: &str, contents: &str, mut output: impl Write) -> io::Result<()> {
+    match path {
+        STANDARD_STREAM => {
+            output.write_all(contents.as_bytes())?;
+            output.flush()
+        }
+        _ => {
+            if let Some(dir) = Path::new(path).parent() {
+                fs::create_dir_all(dir)?;
+            }
+            fs::write(path, contents)
+        }
+    }
+}
+
+pub fn run(command: Command) -> Result<(), Box<dyn Error>> {
+    run_with(command, io::stdin().lock(), io::stdout())
+}
+
+/// Runs a command with the given streams standing in for stdin and stdout. A file name of
+/// "-" reads the puzzle from `input`, and an output of "-" writes the result to `output`, so
+/// nothing touches the filesystem. Progress messages always go to stderr.
+pub fn run_with(
+    command: Command,
+    mut input: impl BufRead,
+    output: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    if command.mode == Mode::Dedupe {
+        eprintln!("Looking for duplicates in: {}", command.filename);
+        let contents = read_input(&command.filename, &mut input)?;
+        let (report, unique) = dedupe_collection(&contents)?;
+        eprintln!("{}", report);
+        if command.remove {
+            let path = command.output_path("deduped.txt");
+            eprintln!("Writing unique puzzles to {}", path);
+            write_output(&path, &(unique.join("\n") + "\n"), output)?;
+        }
+        return Ok(());
+    }
+
+    if command.mode == Mode::Watch {
+        let interval = command.interval.unwrap_or(Duration::from_secs(2));
+        return watch::watch(&command.filename, &command, interval);
+    }
+
+    if command.mode == Mode::Check {
+        eprintln!("Checking grid: {}", command.filename);
+        let mut contents = read_input(&command.filename, &mut input)?;
+        let grid = Sudoku::build(&mut contents)?;
+        let violations = match &command.original {
+            Some(path) if path == STANDARD_STREAM && command.filename == STANDARD_STREAM => {
+                return Err(Box::from(
+                    "The grid and the original puzzle cannot both be read from stdin.",
+                ))
+            }
+            Some(path) => grid.check_against(
+                &Sudoku::build(&mut read_input(path, &mut input)?)?,
+                &command.budget(),
+            )?,
+            None => grid.check(),
+        };
+        let mut report: Vec<String> = violations.iter().map(Violation::to_string).collect();
+        let empty = grid
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&digit| digit == 0)
+            .count();
+        if violations.is_empty() {
+            report.push(match empty {
+                0 => "The grid is complete and correct.".to_string(),
+                _ => format!("No mistakes found, with {} cells still empty.", empty),
+            });
+        }
+        // The report is printed unless it is sent somewhere else
+        let path = match (&command.output, &command.output_dir) {
+            (None, None) => STANDARD_STREAM.to_string(),
+            _ => command.output_path("check.txt"),
+        };
+        write_output(&path, &(report.join("\n") + "\n"), output)?;
+        return match violations.len() {
+            0 => Ok(()),
+            count => Err(Box::from(format!("{} problems found.", count))),
+        };
+    }
+
+    if command.mode == Mode::Candidates {
+        let mut contents = read_input(&command.filename, &mut input)?;
+        let mut sudoku = Sudoku::build(&mut contents)?;
+        sudoku.eliminate_candidates();
+        let path = command.output_path("candidates.txt");
+        eprintln!("Writing pencil marks for {} to {}", command.filename, path);
+        write_output(&path, &(sudoku.format_candidates() + "\n"), output)?;
+        return Ok(());
+    }
+
+    if command.mode == Mode::Transform {
+        let mut contents = read_input(&command.filename, &mut input)?;
+        let sudoku = Sudoku::build(&mut contents)?;
+        let seed = command.seed.unwrap_or_else(Rng::seed_from_time);
+        let count = command.count.unwrap_or(1);
+        eprintln!("Transforming {} with seed {}", command.filename, seed);
+        let lines: Vec<String> = transform_sudoku(&sudoku, seed, count)
+            .iter()
+            .map(collection_line)
+            .collect();
+        let path = command.output_path("transformed.txt");
+        eprintln!("Writing {} puzzles to {}", lines.len(), path);
+        write_output(&path, &(lines.join("\n") + "\n"), output)?;
+        return Ok(());
+    }
+
+    let puzzle =
+        PuzzleType::from_name(&command.puzzle).ok_or("The specified puzzle is not supported.")?;
+    if command.logic_only && !matches!(puzzle, PuzzleType::Nonogram) {
+        return Err(Box::from("--logic-only is only supported for nonograms."));
+    }
+
+    if command.mode == Mode::Play {
+        // Commands are read from stdin, so it cannot also carry the puzzle
+        if command.filename == STANDARD_STREAM {
+            return Err(Box::from(
+                "Play mode needs the puzzle in a file, not stdin.",
+            ));
+        }
+        let contents = fs::read_to_string(&command.filename)?;
+        let save_path = match command.filename.ends_with(".progress") {
+            true => command.filename.clone(),
+            false => format!("{}.progress", command.filename),
+        };
+        return play::play(&puzzle, &contents, save_path, input, output);
+    }
+
+    eprintln!(
         "Solving puzzle: {}\nLooking for file: {}",
         command.puzzle[0..1].to_uppercase() + &command.puzzle[1..],
         command.filename
     );
-    let mut contents = fs::read_to_string(command.filename.clone())?;
-    println!("...Solving...");
+    let is_image = match command.input_format {
+        InputFormat::Auto => is_image_path(&command.filename),
+        InputFormat::Text => false,
+        InputFormat::Image => true,
+    };
+    let mut contents = match is_image {
+        true if matches!(puzzle, PuzzleType::Sudoku) => {
+            read_sudoku_image(&command.filename, &mut input)?
+        }
+        true => return Err(Box::from("Only Sudoku puzzles can be read from an image.")),
+        false => read_input(&command.filename, &mut input)?,
+    };
+    eprintln!("...Solving...");
 
     let solution = match puzzle {
-        PuzzleType::Sudoku => sudoku_puzzle(&mut contents)?,
-        PuzzleType::Nonogram => nonogram_puzzle(&mut contents)?,
+        PuzzleType::Sudoku => sudoku_puzzle(&mut contents, &command)?,
+        PuzzleType::Nonogram => nonogram_puzzle(&mut contents, &command)?,
+        PuzzleType::Gattai => gattai_puzzle(&mut contents, &command)?,
+        PuzzleType::WordSearch => word_search_puzzle(&mut contents, &command)?,
+        PuzzleType::Crossword => crossword_puzzle(&mut contents, &command)?,
+        PuzzleType::Slitherlink => slitherlink_puzzle(&mut contents, &command)?,
+        PuzzleType::StarBattle => star_battle_puzzle(&mut contents, &command)?,
+        PuzzleType::Tents => tents_puzzle(&mut contents, &command)?,
+        PuzzleType::Binairo => binairo_puzzle(&mut contents, &command)?,
+        PuzzleType::Cnf => cnf_formula(&contents, &command)?,
     };
 
-    println!("{:?} puzzle solved. Writing to solution.txt", puzzle);
-    let path = Path::new("solution.txt");
-    let mut file = File::create(path)?;
-    file.write_all(format!("Solution to puzzle: {}\n", command.filename).as_bytes())?;
-    file.write_all(solution.as_bytes())?;
+    let path = command.output_path("solution.txt");
+    let header = match solution.contains(UNKNOWN) {
+        true => {
+            eprintln!("Writing the partial grid to {}", path);
+            "Partial solution"
+        }
+        false => {
+            eprintln!("{:?} puzzle solved. Writing to {}", puzzle, path);
+            "Solution"
+        }
+    };
+    // Output meant for another program gets the bare grid
+    let contents = match (path.as_str(), command.output_format) {
+        (STANDARD_STREAM, _) | (_, OutputFormat::Grid) => solution + "\n",
+        _ => format!("{} to puzzle: {}\n{}", header, command.filename, solution),
+    };
+    write_output(&path, &contents, output)?;
 
     Ok(())
 }
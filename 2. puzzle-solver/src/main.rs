@@ -2,12 +2,21 @@ use puzzle_solver::*;
 use std::{env, process};
 
 fn main() {
-    let command = Command::build(env::args()).unwrap_or_else(|err| {
+    let args: Vec<String> = env::args().collect();
+    let config_path = args
+        .iter()
+        .position(|arg| arg == "--config")
+        .and_then(|index| args.get(index + 1));
+    let config = Config::load(config_path.map(String::as_str)).unwrap_or_else(|err| {
         eprintln!("Application Error: {err}");
-        process::exit(0);
+        process::exit(1);
+    });
+    let command = Command::build_with_config(args.into_iter(), &config).unwrap_or_else(|err| {
+        eprintln!("Application Error: {err}");
+        process::exit(1);
     });
     if let Err(err) = run(command) {
         eprintln!("Application Error: {err}");
-        process::exit(0);
+        process::exit(1);
     }
 }
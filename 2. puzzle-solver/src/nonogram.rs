@@ -1,8 +1,16 @@
-use crate::Puzzle;
+use crate::{
+    budget::{Budget, GiveUp, Report, Tracker},
+    check::Unit,
+    search::{Backtrack, Search},
+    Puzzle,
+};
 
-const EMPTY: char = '\u{25A1}';
-const FILLED: char = '\u{25A0}';
+pub const EMPTY: char = '\u{25A1}';
+pub const FILLED: char = '\u{25A0}';
+// Marks cells that line logic could not decide
+pub const UNKNOWN: char = '?';
 
+#[derive(Debug, Clone)]
 pub struct Nonogram {
     columns: Vec<Vec<u32>>,
     rows: Vec<Vec<u32>>,
@@ -62,33 +70,231 @@ impl Nonogram {
         self.safe_row(row, col) && self.safe_col(row, col)
     }
 
-    fn fill_board(&mut self, mut row: usize, mut col: usize) -> bool {
-        let (width, height) = (self.columns.len(), self.rows.len());
-        if col == width {
-            if row == height - 1 {
-                return true;
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.columns.len()
+    }
+
+    pub fn row_clues(&self, row: usize) -> &[u32] {
+        &self.rows[row]
+    }
+
+    pub fn column_clues(&self, col: usize) -> &[u32] {
+        &self.columns[col]
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> char {
+        self.board[row][col]
+    }
+
+    pub fn set_cell(&mut self, row: usize, col: usize, cell: char) {
+        self.board[row][col] = cell;
+    }
+
+    // Checking a line as if it were only partially filled catches runs that are already too long or too many
+    pub fn has_conflict(&self, row: usize, col: usize) -> bool {
+        !self.safe_row(row, 0) || !self.safe_col(0, col)
+    }
+
+    /// Solves using line logic only, never guessing. Each row and column is checked against
+    /// every placement of its clues that fits the cells known so far, and cells that agree in
+    /// all of them are fixed, until nothing changes. Gives up with [`GiveUp::NeedsGuess`] when
+    /// the grid is left incomplete, with the undecided cells set to [`UNKNOWN`].
+    pub fn solve_by_logic(&mut self, budget: &Budget) -> Report {
+        let mut tracker = Tracker::new(budget);
+        let result = self.deduce(&mut tracker);
+        tracker.finish(result)
+    }
+
+    fn deduce(&mut self, tracker: &mut Tracker) -> Result<bool, GiveUp> {
+        let (width, height) = (self.width(), self.height());
+        self.board = vec![vec![UNKNOWN; width]; height];
+        // Lines are numbered with the rows first, then the columns
+        let mut dirty = vec![true; height + width];
+        while let Some(line) = dirty.iter().position(|&dirty| dirty) {
+            dirty[line] = false;
+            tracker.visit(0)?;
+            let (clues, cells): (&[u32], Vec<char>) = match line < height {
+                true => (&self.rows[line], self.board[line].clone()),
+                false => (
+                    &self.columns[line - height],
+                    self.board.iter().map(|row| row[line - height]).collect(),
+                ),
+            };
+            let Some(deduced) = solve_line(clues, &cells) else {
+                return Ok(false);
+            };
+            for (index, cell) in deduced.into_iter().enumerate() {
+                if cell == cells[index] {
+                    continue;
+                }
+                tracker.propagate();
+                let (row, col) = match line < height {
+                    true => (line, index),
+                    false => (index, line - height),
+                };
+                self.board[row][col] = cell;
+                // The crossing line has new information to work with
+                match line < height {
+                    true => dirty[height + col] = true,
+                    false => dirty[row] = true,
+                }
             }
-            row += 1;
-            col = 0;
         }
-        self.board[row][col] = FILLED;
-        if self.safe_space(row, col) && self.fill_board(row, col + 1) {
-            return true;
+        match self.board.iter().flatten().any(|&cell| cell == UNKNOWN) {
+            true => Err(GiveUp::NeedsGuess),
+            false => Ok(true),
         }
-        self.board[row][col] = EMPTY;
-        if self.safe_space(row, col) && self.fill_board(row, col + 1) {
-            return true;
+    }
+
+    /// The puzzle whose clues describe the given picture, with every cell left empty. Fails
+    /// for an empty picture or one whose rows differ in length.
+    pub fn from_solution(picture: &[Vec<bool>]) -> Result<Nonogram, Box<dyn std::error::Error>> {
+        let (height, width) = (picture.len(), picture.first().map_or(0, Vec::len));
+        if width == 0 {
+            return Err(Box::from("A nonogram needs at least one row and column."));
+        }
+        if picture.iter().any(|row| row.len() != width) {
+            return Err(Box::from(
+                "Every row of a nonogram picture must be the same length.",
+            ));
         }
-        false
+        let rows = picture
+            .iter()
+            .map(|row| line_runs(row.iter().copied()))
+            .collect();
+        let columns = (0..width)
+            .map(|col| line_runs(picture.iter().map(|row| row[col])))
+            .collect();
+        Ok(Nonogram {
+            columns,
+            rows,
+            board: vec![vec![EMPTY; width]; height],
+        })
+    }
+
+    /// The clues written in the input file format, so that `build` reads them back.
+    pub fn format_clues(&self) -> String {
+        let line = |clues: &Vec<Vec<u32>>| {
+            clues
+                .iter()
+                .map(|runs| match runs.is_empty() {
+                    true => "0".to_string(),
+                    false => runs
+                        .iter()
+                        .map(u32::to_string)
+                        .collect::<Vec<String>>()
+                        .join(","),
+                })
+                .collect::<Vec<String>>()
+                .join(" ")
+        };
+        format!("{}\n{}", line(&self.columns), line(&self.rows))
+    }
+
+    /// Fills the board from a grid with one line per row, where filled cells are written as
+    /// the filled square, # or 1 and empty ones as the empty square, . or 0. Spaces are ignored.
+    pub fn read_grid(&mut self, grid: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let rows: Vec<Vec<char>> = grid
+            .lines()
+            .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+            .filter(|row: &Vec<char>| !row.is_empty())
+            .collect();
+        if rows.len() != self.height() || rows.iter().any(|row| row.len() != self.width()) {
+            return Err(Box::from(format!(
+                "The grid should have {} rows of {} cells.",
+                self.height(),
+                self.width()
+            )));
+        }
+        for (row, cells) in rows.iter().enumerate() {
+            for (col, &cell) in cells.iter().enumerate() {
+                self.board[row][col] = match cell {
+                    FILLED | '#' | '1' => FILLED,
+                    EMPTY | '.' | '0' => EMPTY,
+                    _ => return Err(Box::from(format!("\"{}\" is not a grid cell.", cell))),
+                };
+            }
+        }
+        Ok(())
+    }
+
+    /// The rows and columns whose filled cells do not match their clues, counted from 0.
+    pub fn mismatched_lines(&self) -> Vec<(Unit, usize)> {
+        let rows = (0..self.height())
+            .filter(|&row| {
+                line_runs(self.board[row].iter().map(|&cell| cell == FILLED)) != self.rows[row]
+            })
+            .map(|row| (Unit::Row, row));
+        let columns = (0..self.width())
+            .filter(|&col| {
+                line_runs(self.board.iter().map(|row| row[col] == FILLED)) != self.columns[col]
+            })
+            .map(|col| (Unit::Column, col));
+        rows.chain(columns).collect()
+    }
+
+    pub fn is_complete(&self) -> bool {
+        let (width, height) = (self.width(), self.height());
+        (0..height).all(|row| self.safe_row(row, width - 1))
+            && (0..width).all(|col| self.safe_col(height - 1, col))
+    }
+}
+
+impl Backtrack for Nonogram {
+    type Value = char;
+
+    fn cells(&self) -> usize {
+        self.width() * self.height()
+    }
+
+    fn options(&self, _index: usize) -> Option<Vec<char>> {
+        Some(vec![FILLED, EMPTY])
+    }
+
+    fn place(&mut self, index: usize, cell: char) -> bool {
+        let (row, col) = (index / self.width(), index % self.width());
+        self.board[row][col] = cell;
+        self.safe_space(row, col)
+    }
+
+    fn clear(&mut self, index: usize) {
+        let width = self.width();
+        self.board[index / width][index % width] = EMPTY;
     }
 }
 
 impl Puzzle for Nonogram {
     fn build(contents: &mut String) -> Result<Self, Box<dyn std::error::Error>> {
-        let row_col_split: Vec<&str> = contents.split('\n').collect();
-        let columns = build_runs(row_col_split[0]);
-        let rows = build_runs(row_col_split[1]);
-        let board = vec![vec![EMPTY; rows.len()]; columns.len()];
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let (Some(column_line), Some(row_line)) = (lines.next(), lines.next()) else {
+            return Err(Box::from(
+                "Failed to read puzzle. For Nonogram, ensure there is a line of column clues followed by a line of row clues.",
+            ));
+        };
+        let columns = build_runs(column_line)?;
+        let rows = build_runs(row_line)?;
+        // Every clue has to fit across the lines it runs through
+        for (lines, length, name) in [
+            (&columns, rows.len(), "Column"),
+            (&rows, columns.len(), "Row"),
+        ] {
+            for (index, runs) in lines.iter().enumerate() {
+                let needed = runs.iter().map(|&run| u64::from(run) + 1).sum::<u64>();
+                if needed > length as u64 + 1 {
+                    return Err(Box::from(format!(
+                        "{} {} clues do not fit in {} cells.",
+                        name,
+                        index + 1,
+                        length
+                    )));
+                }
+            }
+        }
+        let board = vec![vec![EMPTY; columns.len()]; rows.len()];
         Ok(Nonogram {
             columns,
             rows,
@@ -96,8 +302,8 @@ impl Puzzle for Nonogram {
         })
     }
 
-    fn solve(&mut self) -> bool {
-        self.fill_board(0, 0)
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        Search::new().run(self, budget)
     }
 
     fn format(&self) -> String {
@@ -109,12 +315,113 @@ impl Puzzle for Nonogram {
     }
 }
 
-fn build_runs(split: &str) -> Vec<Vec<u32>> {
-    let str_runs: Vec<&str> = split.split_whitespace().collect();
+// Every cell that is the same in all placements of the clues consistent with the known cells
+// is fixed, and the rest are left UNKNOWN. Returns None when no placement fits.
+fn solve_line(clues: &[u32], cells: &[char]) -> Option<Vec<char>> {
+    let clues: Vec<usize> = clues
+        .iter()
+        .filter(|&&clue| clue > 0)
+        .map(|&clue| clue as usize)
+        .collect();
+    let (length, count) = (cells.len(), clues.len());
+    // Whether a run of the given clue can start at a cell, and where the line continues after it
+    let run_fits = |start: usize, clue: usize| {
+        let end = start + clue;
+        end <= length
+            && cells[start..end].iter().all(|&cell| cell != EMPTY)
+            && cells.get(end) != Some(&FILLED)
+    };
+    let after_run = |start: usize, clue: usize| (start + clue + 1).min(length);
+
+    // fits[i][j]: the cells from i onwards can hold exactly the clues from j onwards, given
+    // that cell i is not joined to a run before it
+    let mut fits = vec![vec![false; count + 1]; length + 1];
+    fits[length][count] = true;
+    for i in (0..length).rev() {
+        for j in 0..=count {
+            fits[i][j] = (cells[i] != FILLED && fits[i + 1][j])
+                || (j < count && run_fits(i, clues[j]) && fits[after_run(i, clues[j])][j + 1]);
+        }
+    }
+    if !fits[0][0] {
+        return None;
+    }
+
+    // Walk forwards through every reachable state that can still be completed, recording
+    // which cells can be filled and which can be empty
+    let (mut can_fill, mut can_empty) = (vec![false; length], vec![false; length]);
+    let mut reached = vec![vec![false; count + 1]; length + 1];
+    reached[0][0] = true;
+    for i in 0..length {
+        for j in 0..=count {
+            if !reached[i][j] {
+                continue;
+            }
+            if cells[i] != FILLED && fits[i + 1][j] {
+                can_empty[i] = true;
+                reached[i + 1][j] = true;
+            }
+            if j < count && run_fits(i, clues[j]) && fits[after_run(i, clues[j])][j + 1] {
+                let end = i + clues[j];
+                can_fill[i..end].iter_mut().for_each(|cell| *cell = true);
+                if end < length {
+                    can_empty[end] = true;
+                }
+                reached[after_run(i, clues[j])][j + 1] = true;
+            }
+        }
+    }
+    Some(
+        can_fill
+            .into_iter()
+            .zip(can_empty)
+            .map(|cell| match cell {
+                (true, false) => FILLED,
+                (false, true) => EMPTY,
+                _ => UNKNOWN,
+            })
+            .collect(),
+    )
+}
+
+// The lengths of the runs of filled cells along a line
+fn line_runs(cells: impl Iterator<Item = bool>) -> Vec<u32> {
+    let mut runs = Vec::new();
+    let mut previous = false;
+    for filled in cells {
+        match (filled, previous) {
+            (true, true) => *runs.last_mut().unwrap() += 1,
+            (true, false) => runs.push(1),
+            _ => {}
+        }
+        previous = filled;
+    }
+    runs
+}
+
+/// Reads a line of clues, one whitespace-separated group per row or column with the runs in
+/// each group separated by commas. A group of 0 stands for a line with nothing filled.
+pub fn build_runs(split: &str) -> Result<Vec<Vec<u32>>, Box<dyn std::error::Error>> {
     let mut num_runs: Vec<Vec<u32>> = Vec::new();
-    for run in str_runs {
-        let comma_split: String = run.split(',').collect();
-        num_runs.push(comma_split.chars().filter_map(|c| c.to_digit(10)).collect());
+    for group in split.split_whitespace() {
+        let mut runs = Vec::new();
+        for run in group.split(',') {
+            let run: u32 = run.parse().map_err(|_| {
+                format!(
+                    "Failed to read clue \"{}\". Clues must be whole numbers.",
+                    group
+                )
+            })?;
+            if run > 0 {
+                runs.push(run);
+            }
+        }
+        num_runs.push(runs);
+    }
+    match num_runs.is_empty() {
+        true => Err(Box::from(
+            "Failed to read puzzle. A line of clues is empty.",
+        )),
+        false => Ok(num_runs),
     }
-    num_runs
 }
@@ -0,0 +1,266 @@
+use std::error::Error;
+
+use crate::{
+    image::GrayImage,
+    sudoku::{Sudoku, SUDOKU_SIZE},
+};
+
+const SIZE: usize = SUDOKU_SIZE as usize;
+
+/// The bundled digit templates, as a 5x7 pixel font for the digits 1 to 9.
+pub const DIGIT_FONT: [[&str; 7]; 9] = [
+    [
+        "..#..", ".##..", "..#..", "..#..", "..#..", "..#..", ".###.",
+    ],
+    [
+        ".###.", "#...#", "....#", "...#.", "..#..", ".#...", "#####",
+    ],
+    [
+        "#####", "...#.", "..#..", "...#.", "....#", "#...#", ".###.",
+    ],
+    [
+        "...#.", "..##.", ".#.#.", "#..#.", "#####", "...#.", "...#.",
+    ],
+    [
+        "#####", "#....", "####.", "....#", "....#", "#...#", ".###.",
+    ],
+    [
+        "..##.", ".#...", "#....", "####.", "#...#", "#...#", ".###.",
+    ],
+    [
+        "#####", "....#", "...#.", "..#..", ".#...", ".#...", ".#...",
+    ],
+    [
+        ".###.", "#...#", "#...#", ".###.", "#...#", "#...#", ".###.",
+    ],
+    [
+        ".###.", "#...#", "#...#", ".####", "....#", "...#.", ".##..",
+    ],
+];
+
+// Digits are compared after being scaled to this many samples across and down
+const SAMPLE_WIDTH: usize = 12;
+const SAMPLE_HEIGHT: usize = 16;
+// How much of each cell to ignore around its edges, so grid lines are not read as ink
+const CELL_MARGIN: f64 = 0.15;
+// The share of a cell's inside that must be dark before it counts as holding a digit
+const MIN_INK: f64 = 0.02;
+// Rows and columns whose longest dark run is this close to the longest one are grid lines
+const LINE_LENGTH: f64 = 0.8;
+
+type Samples = [f64; SAMPLE_WIDTH * SAMPLE_HEIGHT];
+
+/// Draws a digit from [`DIGIT_FONT`] with its top left corner at (x, y), each font pixel
+/// becoming a square of `scale` image pixels.
+pub fn draw_digit(image: &mut GrayImage, digit: u32, x: usize, y: usize, scale: usize) {
+    for (row, line) in DIGIT_FONT[digit as usize - 1].iter().enumerate() {
+        for (col, pixel) in line.chars().enumerate() {
+            if pixel != '#' {
+                continue;
+            }
+            for dy in 0..scale {
+                for dx in 0..scale {
+                    image.set(x + col * scale + dx, y + row * scale + dy, 0);
+                }
+            }
+        }
+    }
+}
+
+// Picks the gray level that best splits the image into ink and paper (Otsu's method)
+fn threshold(image: &GrayImage) -> u8 {
+    let mut histogram = [0usize; 256];
+    for &pixel in &image.pixels {
+        histogram[pixel as usize] += 1;
+    }
+    let total = image.pixels.len() as f64;
+    let sum: f64 = (0..256)
+        .map(|level| level as f64 * histogram[level] as f64)
+        .sum();
+    let (mut best, mut best_variance) = (128, 0.0);
+    let (mut weight, mut dark_sum) = (0.0, 0.0);
+    for (level, &count) in histogram.iter().enumerate() {
+        weight += count as f64;
+        dark_sum += level as f64 * count as f64;
+        if weight == 0.0 || weight == total {
+            continue;
+        }
+        let dark_mean = dark_sum / weight;
+        let light_mean = (sum - dark_sum) / (total - weight);
+        let variance = weight * (total - weight) * (dark_mean - light_mean).powi(2);
+        if variance > best_variance {
+            (best, best_variance) = (level as u8, variance);
+        }
+    }
+    best
+}
+
+// Finds grid lines along one direction from the longest dark run in each row (or column),
+// returning the middle of each group of neighbouring line rows
+fn find_lines(runs: &[usize]) -> Vec<f64> {
+    let longest = runs.iter().copied().max().unwrap_or(0);
+    if longest == 0 {
+        return Vec::new();
+    }
+    let mut lines: Vec<(usize, usize)> = Vec::new();
+    for (position, &run) in runs.iter().enumerate() {
+        if (run as f64) < longest as f64 * LINE_LENGTH {
+            continue;
+        }
+        match lines.last_mut() {
+            Some((_, end)) if *end + 1 == position => *end = position,
+            _ => lines.push((position, position)),
+        }
+    }
+    lines
+        .iter()
+        .map(|&(start, end)| (start + end) as f64 / 2.0)
+        .collect()
+}
+
+fn longest_run(dark: impl Iterator<Item = bool>) -> usize {
+    let (mut longest, mut current) = (0, 0);
+    for is_dark in dark {
+        current = if is_dark { current + 1 } else { 0 };
+        longest = longest.max(current);
+    }
+    longest
+}
+
+// The 10 lines bounding the cells along one direction. When some inner lines are too faint
+// to find, the cells are spread evenly between the outermost lines.
+fn cell_bounds(lines: &[f64]) -> Result<Vec<f64>, Box<dyn Error>> {
+    match lines {
+        lines if lines.len() == SIZE + 1 => Ok(lines.to_vec()),
+        [first, .., last] if last - first >= SIZE as f64 => Ok((0..=SIZE)
+            .map(|i| first + (last - first) * i as f64 / SIZE as f64)
+            .collect()),
+        _ => Err(Box::from(
+            "Could not find the Sudoku grid lines in the image.",
+        )),
+    }
+}
+
+// Scales the ink inside a box to a fixed size, keeping its shape by fitting the height and
+// centring it across, and records how much of each sample is dark
+fn sample(
+    dark: &dyn Fn(usize, usize) -> bool,
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+) -> Samples {
+    let (width, height) = ((right - left) as f64, (bottom - top) as f64);
+    let scale = SAMPLE_HEIGHT as f64 / height;
+    let offset = (SAMPLE_WIDTH as f64 - width * scale).max(0.0) / 2.0;
+    let mut samples = [0.0; SAMPLE_WIDTH * SAMPLE_HEIGHT];
+    for y in top..bottom {
+        for x in left..right {
+            if !dark(x, y) {
+                continue;
+            }
+            let sx = ((x - left) as f64 + 0.5) * scale + offset;
+            let sy = ((y - top) as f64 + 0.5) * scale;
+            let (sx, sy) = (sx as usize, sy as usize);
+            if sx < SAMPLE_WIDTH && sy < SAMPLE_HEIGHT {
+                samples[sy * SAMPLE_WIDTH + sx] += 1.0;
+            }
+        }
+    }
+    let total: f64 = samples.iter().sum();
+    if total > 0.0 {
+        // Makes the result independent of how large the digit was printed
+        for value in samples.iter_mut() {
+            *value /= total;
+        }
+    }
+    samples
+}
+
+// The samples of every digit in the bundled font, drawn large enough to sample smoothly
+fn templates() -> Vec<Samples> {
+    const SCALE: usize = 8;
+    (1..=SUDOKU_SIZE)
+        .map(|digit| {
+            let mut image = GrayImage::new(5 * SCALE, 7 * SCALE);
+            draw_digit(&mut image, digit, 0, 0, SCALE);
+            let dark = |x: usize, y: usize| image.get(x, y) < 128;
+            let (left, top, right, bottom) =
+                ink_bounds(&dark, 0, 0, image.width, image.height).expect("every digit has ink");
+            sample(&dark, left, top, right, bottom)
+        })
+        .collect()
+}
+
+// The smallest box holding all the dark pixels in an area
+fn ink_bounds(
+    dark: &dyn Fn(usize, usize) -> bool,
+    left: usize,
+    top: usize,
+    right: usize,
+    bottom: usize,
+) -> Option<(usize, usize, usize, usize)> {
+    let mut bounds: Option<(usize, usize, usize, usize)> = None;
+    for y in top..bottom {
+        for x in left..right {
+            if dark(x, y) {
+                bounds = Some(match bounds {
+                    None => (x, y, x + 1, y + 1),
+                    Some((l, t, r, b)) => (l.min(x), t.min(y), r.max(x + 1), b.max(y + 1)),
+                });
+            }
+        }
+    }
+    bounds
+}
+
+/// Reads a Sudoku from a clean, upright image of the grid. The grid lines are found first,
+/// then each cell with enough ink in it is matched against the bundled digit templates.
+/// Cells without ink are left empty.
+pub fn recognize_sudoku(image: &GrayImage) -> Result<Sudoku, Box<dyn Error>> {
+    let cutoff = threshold(image);
+    let dark = |x: usize, y: usize| image.get(x, y) <= cutoff;
+    let row_runs: Vec<usize> = (0..image.height)
+        .map(|y| longest_run((0..image.width).map(|x| dark(x, y))))
+        .collect();
+    let col_runs: Vec<usize> = (0..image.width)
+        .map(|x| longest_run((0..image.height).map(|y| dark(x, y))))
+        .collect();
+    let rows = cell_bounds(&find_lines(&row_runs))?;
+    let cols = cell_bounds(&find_lines(&col_runs))?;
+
+    let templates = templates();
+    let mut board = vec![vec![0; SIZE]; SIZE];
+    for row in 0..SIZE {
+        for col in 0..SIZE {
+            let (height, width) = (rows[row + 1] - rows[row], cols[col + 1] - cols[col]);
+            let top = (rows[row] + height * CELL_MARGIN).round() as usize;
+            let bottom = (rows[row + 1] - height * CELL_MARGIN).round() as usize;
+            let left = (cols[col] + width * CELL_MARGIN).round() as usize;
+            let right = (cols[col + 1] - width * CELL_MARGIN).round() as usize;
+            let ink = (top..bottom)
+                .flat_map(|y| (left..right).map(move |x| (x, y)))
+                .filter(|&(x, y)| dark(x, y))
+                .count();
+            if (ink as f64) < ((bottom - top) * (right - left)) as f64 * MIN_INK {
+                continue;
+            }
+            let Some((l, t, r, b)) = ink_bounds(&dark, left, top, right, bottom) else {
+                continue;
+            };
+            let samples = sample(&dark, l, t, r, b);
+            let difference = |template: &Samples| -> f64 {
+                template
+                    .iter()
+                    .zip(&samples)
+                    .map(|(a, b)| (a - b).powi(2))
+                    .sum()
+            };
+            let best = (0..templates.len())
+                .min_by(|&a, &b| difference(&templates[a]).total_cmp(&difference(&templates[b])))
+                .unwrap_or(0);
+            board[row][col] = best as u32 + 1;
+        }
+    }
+    Ok(Sudoku::from_board(board))
+}
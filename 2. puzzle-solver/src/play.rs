@@ -0,0 +1,490 @@
+use std::{
+    error::Error,
+    fs,
+    io::{BufRead, Write},
+    time::Duration,
+};
+
+use crate::{
+    nonogram::{Nonogram, EMPTY, FILLED},
+    sudoku::{Sudoku, SUDOKU_SIZE},
+    Budget, Outcome, Puzzle, PuzzleType,
+};
+
+// Separates the original puzzle from the player's board in a saved game
+const PROGRESS_MARKER: &str = "progress";
+const HINT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const RESET: &str = "\x1b[0m";
+const BOLD: &str = "\x1b[1m";
+const RED: &str = "\x1b[31m";
+const REVERSE: &str = "\x1b[7m";
+
+const HELP: &str = "Commands (several can be chained on one line, ex. \"dd5\"):
+  w/a/s/d  move the cursor up/left/down/right
+  1-9      place a digit (Sudoku)      0 or c  clear the cell (Sudoku)
+  f        fill the cell (Nonogram)    x or c  empty the cell (Nonogram)
+  u        undo                        r       redo
+  h        reveal the solution for the cursor cell
+  save     save progress (optionally followed by a file name)
+  ?        show this help              q       quit";
+
+/// A puzzle board that can be edited one cell at a time by a player.
+trait Playable {
+    fn size(&self) -> (usize, usize);
+    fn cell(&self, row: usize, col: usize) -> char;
+    fn set_cell(&mut self, row: usize, col: usize, cell: char);
+    // Translates a key press into the cell it should produce, if the key edits cells at all
+    fn edit(&self, key: char) -> Option<char>;
+    fn is_locked(&self, row: usize, col: usize) -> bool;
+    fn has_conflict(&self, row: usize, col: usize) -> bool;
+    fn is_complete(&self) -> bool;
+    // The solver's value for a cell, computed from the original puzzle
+    fn answer(&mut self, row: usize, col: usize) -> Option<char>;
+    fn render_row(&self, row: usize, cells: Vec<String>) -> String;
+    fn save(&self) -> String;
+}
+
+struct SudokuGame {
+    givens: Sudoku,
+    current: Sudoku,
+    solution: Option<Sudoku>,
+}
+
+impl SudokuGame {
+    fn build(puzzle: &mut String, progress: Option<&mut String>) -> Result<Self, Box<dyn Error>> {
+        let givens = Sudoku::build(puzzle)?;
+        let mut current = givens.clone();
+        if let Some(progress) = progress {
+            let saved = Sudoku::build(progress)?;
+            let size = SUDOKU_SIZE as usize;
+            for (row, col) in (0..size).flat_map(|row| (0..size).map(move |col| (row, col))) {
+                if givens.board[row][col] == 0 {
+                    current.board[row][col] = saved.board[row][col];
+                }
+            }
+        }
+        Ok(SudokuGame {
+            givens,
+            current,
+            solution: None,
+        })
+    }
+}
+
+impl Playable for SudokuGame {
+    fn size(&self) -> (usize, usize) {
+        (SUDOKU_SIZE as usize, SUDOKU_SIZE as usize)
+    }
+
+    fn cell(&self, row: usize, col: usize) -> char {
+        match self.current.board[row][col] {
+            0 => '.',
+            digit => char::from_digit(digit, 10).unwrap_or('?'),
+        }
+    }
+
+    fn set_cell(&mut self, row: usize, col: usize, cell: char) {
+        self.current.board[row][col] = cell.to_digit(10).unwrap_or(0);
+    }
+
+    fn edit(&self, key: char) -> Option<char> {
+        match key {
+            '1'..='9' => Some(key),
+            '0' | 'c' => Some('.'),
+            _ => None,
+        }
+    }
+
+    fn is_locked(&self, row: usize, col: usize) -> bool {
+        self.givens.board[row][col] != 0
+    }
+
+    fn has_conflict(&self, row: usize, col: usize) -> bool {
+        self.current.has_conflict(row, col)
+    }
+
+    fn is_complete(&self) -> bool {
+        let size = SUDOKU_SIZE as usize;
+        (0..size).all(|row| {
+            (0..size).all(|col| self.current.board[row][col] != 0 && !self.has_conflict(row, col))
+        })
+    }
+
+    fn answer(&mut self, row: usize, col: usize) -> Option<char> {
+        if self.solution.is_none() {
+            let mut solved = self.givens.clone();
+            let budget = Budget::unlimited().with_timeout(HINT_TIMEOUT);
+            if solved.solve_within(&budget).outcome == Outcome::Solved {
+                self.solution = Some(solved);
+            }
+        }
+        let digit = self.solution.as_ref()?.board[row][col];
+        char::from_digit(digit, 10)
+    }
+
+    fn render_row(&self, row: usize, cells: Vec<String>) -> String {
+        let mut line = String::new();
+        if row.is_multiple_of(3) {
+            line.push_str("+-------+-------+-------+\n");
+        }
+        for (col, cell) in cells.iter().enumerate() {
+            if col.is_multiple_of(3) {
+                line.push_str("| ");
+            }
+            line.push_str(cell);
+            line.push(' ');
+        }
+        line.push('|');
+        if row == SUDOKU_SIZE as usize - 1 {
+            line.push_str("\n+-------+-------+-------+");
+        }
+        line
+    }
+
+    fn save(&self) -> String {
+        format!(
+            "{}\n{}\n{}",
+            self.givens.format(),
+            PROGRESS_MARKER,
+            self.current.format()
+        )
+    }
+}
+
+struct NonogramGame {
+    clues: String,
+    current: Nonogram,
+    solution: Option<Nonogram>,
+}
+
+impl NonogramGame {
+    fn build(puzzle: &mut String, progress: Option<&mut String>) -> Result<Self, Box<dyn Error>> {
+        let clues = puzzle.trim().to_string();
+        let mut current = Nonogram::build(puzzle)?;
+        if let Some(progress) = progress {
+            for (row, line) in progress.trim().lines().take(current.height()).enumerate() {
+                for (col, cell) in line.chars().take(current.width()).enumerate() {
+                    if cell == FILLED {
+                        current.set_cell(row, col, FILLED);
+                    }
+                }
+            }
+        }
+        Ok(NonogramGame {
+            clues,
+            current,
+            solution: None,
+        })
+    }
+}
+
+impl Playable for NonogramGame {
+    fn size(&self) -> (usize, usize) {
+        (self.current.height(), self.current.width())
+    }
+
+    fn cell(&self, row: usize, col: usize) -> char {
+        self.current.cell(row, col)
+    }
+
+    fn set_cell(&mut self, row: usize, col: usize, cell: char) {
+        self.current.set_cell(row, col, cell);
+    }
+
+    fn edit(&self, key: char) -> Option<char> {
+        match key {
+            'f' => Some(FILLED),
+            'x' | 'c' => Some(EMPTY),
+            _ => None,
+        }
+    }
+
+    fn is_locked(&self, _row: usize, _col: usize) -> bool {
+        false
+    }
+
+    fn has_conflict(&self, row: usize, col: usize) -> bool {
+        self.current.has_conflict(row, col)
+    }
+
+    fn is_complete(&self) -> bool {
+        self.current.is_complete()
+    }
+
+    fn answer(&mut self, row: usize, col: usize) -> Option<char> {
+        if self.solution.is_none() {
+            let mut solved = Nonogram::build(&mut self.clues.clone()).ok()?;
+            let budget = Budget::unlimited().with_timeout(HINT_TIMEOUT);
+            if solved.solve_within(&budget).outcome == Outcome::Solved {
+                self.solution = Some(solved);
+            }
+        }
+        Some(self.solution.as_ref()?.cell(row, col))
+    }
+
+    fn render_row(&self, row: usize, cells: Vec<String>) -> String {
+        let clues: Vec<String> = self
+            .current
+            .row_clues(row)
+            .iter()
+            .map(|clue| clue.to_string())
+            .collect();
+        let mut line = format!("{}  {}", cells.join(""), clues.join(","));
+        if row == self.current.height() - 1 {
+            let columns: Vec<String> = (0..self.current.width())
+                .map(|col| {
+                    let clues: Vec<String> = self
+                        .current
+                        .column_clues(col)
+                        .iter()
+                        .map(|clue| clue.to_string())
+                        .collect();
+                    clues.join(",")
+                })
+                .collect();
+            line.push_str(&format!("\nColumns: {}", columns.join(" ")));
+        }
+        line
+    }
+
+    fn save(&self) -> String {
+        format!(
+            "{}\n{}\n{}",
+            self.clues,
+            PROGRESS_MARKER,
+            self.current.format()
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Edit {
+    row: usize,
+    col: usize,
+    before: char,
+    after: char,
+}
+
+/// An interactive game: the board being played plus the cursor and edit history.
+struct Session<G: Playable> {
+    game: G,
+    cursor: (usize, usize),
+    undo: Vec<Edit>,
+    redo: Vec<Edit>,
+    save_path: String,
+    message: String,
+}
+
+impl<G: Playable> Session<G> {
+    fn new(game: G, save_path: String) -> Session<G> {
+        Session {
+            game,
+            cursor: (0, 0),
+            undo: Vec::new(),
+            redo: Vec::new(),
+            save_path,
+            message: String::from("Enter ? for a list of commands."),
+        }
+    }
+
+    fn move_cursor(&mut self, key: char) {
+        let (height, width) = self.game.size();
+        let (row, col) = self.cursor;
+        self.cursor = match key {
+            'w' => (row.saturating_sub(1), col),
+            's' => ((row + 1).min(height - 1), col),
+            'a' => (row, col.saturating_sub(1)),
+            'd' => (row, (col + 1).min(width - 1)),
+            _ => (row, col),
+        };
+    }
+
+    fn place(&mut self, cell: char) {
+        let (row, col) = self.cursor;
+        if self.game.is_locked(row, col) {
+            self.message = String::from("That cell is part of the original puzzle.");
+            return;
+        }
+        let before = self.game.cell(row, col);
+        if before == cell {
+            return;
+        }
+        self.game.set_cell(row, col, cell);
+        self.undo.push(Edit {
+            row,
+            col,
+            before,
+            after: cell,
+        });
+        self.redo.clear();
+    }
+
+    fn undo(&mut self) {
+        match self.undo.pop() {
+            Some(edit) => {
+                self.game.set_cell(edit.row, edit.col, edit.before);
+                self.cursor = (edit.row, edit.col);
+                self.redo.push(edit);
+            }
+            None => self.message = String::from("Nothing to undo."),
+        }
+    }
+
+    fn redo(&mut self) {
+        match self.redo.pop() {
+            Some(edit) => {
+                self.game.set_cell(edit.row, edit.col, edit.after);
+                self.cursor = (edit.row, edit.col);
+                self.undo.push(edit);
+            }
+            None => self.message = String::from("Nothing to redo."),
+        }
+    }
+
+    fn hint(&mut self) {
+        let (row, col) = self.cursor;
+        let answer = match self.game.answer(row, col) {
+            Some(answer) => answer,
+            None => {
+                self.message = String::from("The solver could not find a solution to give a hint.");
+                return;
+            }
+        };
+        if self.game.cell(row, col) == answer {
+            self.message = String::from("That cell is already correct.");
+        } else {
+            self.place(answer);
+        }
+    }
+
+    fn save(&mut self, path: &str) -> Result<(), Box<dyn Error>> {
+        if !path.is_empty() {
+            self.save_path = path.to_string();
+        }
+        fs::write(&self.save_path, self.game.save())?;
+        self.message = format!("Progress saved to {}.", self.save_path);
+        Ok(())
+    }
+
+    // Returns false once the player asks to quit
+    fn handle(&mut self, line: &str) -> Result<bool, Box<dyn Error>> {
+        let line = line.trim();
+        if let Some(path) = line.strip_prefix("save") {
+            self.save(path.trim())?;
+            return Ok(true);
+        }
+        for key in line.chars().filter(|key| !key.is_whitespace()) {
+            match key {
+                'q' => return Ok(false),
+                'w' | 'a' | 's' | 'd' => self.move_cursor(key),
+                'u' => self.undo(),
+                'r' => self.redo(),
+                'h' => self.hint(),
+                '?' => self.message = HELP.to_string(),
+                _ => match self.game.edit(key) {
+                    Some(cell) => self.place(cell),
+                    None => self.message = format!("Unknown command '{}'.", key),
+                },
+            }
+        }
+        Ok(true)
+    }
+
+    fn render(&self) -> String {
+        let (height, width) = self.game.size();
+        let mut screen = String::new();
+        for row in 0..height {
+            let cells: Vec<String> = (0..width)
+                .map(|col| {
+                    let mut style = String::new();
+                    if self.game.is_locked(row, col) {
+                        style.push_str(BOLD);
+                    }
+                    if self.game.has_conflict(row, col) {
+                        style.push_str(RED);
+                    }
+                    if self.cursor == (row, col) {
+                        style.push_str(REVERSE);
+                    }
+                    match style.is_empty() {
+                        true => self.game.cell(row, col).to_string(),
+                        false => format!("{}{}{}", style, self.game.cell(row, col), RESET),
+                    }
+                })
+                .collect();
+            screen.push_str(&self.game.render_row(row, cells));
+            screen.push('\n');
+        }
+        screen.push_str(&format!(
+            "Row {}, column {}. ",
+            self.cursor.0 + 1,
+            self.cursor.1 + 1
+        ));
+        if self.game.is_complete() {
+            screen.push_str("Puzzle complete!\n");
+        } else {
+            screen.push('\n');
+        }
+        if !self.message.is_empty() {
+            screen.push_str(&self.message);
+            screen.push('\n');
+        }
+        screen
+    }
+
+    fn play(&mut self, input: impl BufRead, mut output: impl Write) -> Result<(), Box<dyn Error>> {
+        write!(output, "{}> ", self.render())?;
+        output.flush()?;
+        for line in input.lines() {
+            self.message.clear();
+            if !self.handle(&line?)? {
+                break;
+            }
+            write!(output, "{}> ", self.render())?;
+            output.flush()?;
+        }
+        writeln!(output)?;
+        Ok(())
+    }
+}
+
+/// Starts an interactive game from puzzle file contents, which may include previously saved progress.
+pub fn play(
+    puzzle: &PuzzleType,
+    contents: &str,
+    save_path: String,
+    input: impl BufRead,
+    output: impl Write,
+) -> Result<(), Box<dyn Error>> {
+    let (mut original, mut progress) = split_progress(contents);
+    match puzzle {
+        PuzzleType::Sudoku => {
+            let game = SudokuGame::build(&mut original, progress.as_mut())?;
+            Session::new(game, save_path).play(input, output)
+        }
+        PuzzleType::Nonogram => {
+            let game = NonogramGame::build(&mut original, progress.as_mut())?;
+            Session::new(game, save_path).play(input, output)
+        }
+        PuzzleType::Gattai => Err(Box::from("Play mode does not support gattai puzzles yet.")),
+        PuzzleType::WordSearch | PuzzleType::Crossword => {
+            Err(Box::from("Play mode does not support word puzzles yet."))
+        }
+        PuzzleType::Slitherlink
+        | PuzzleType::StarBattle
+        | PuzzleType::Tents
+        | PuzzleType::Binairo => Err(Box::from(format!(
+            "Play mode does not support {:?} puzzles yet.",
+            puzzle
+        ))),
+        PuzzleType::Cnf => Err(Box::from("Play mode needs a puzzle, not a CNF formula.")),
+    }
+}
+
+fn split_progress(contents: &str) -> (String, Option<String>) {
+    match contents.split_once(&format!("\n{}\n", PROGRESS_MARKER)) {
+        Some((original, progress)) => (original.to_string(), Some(progress.to_string())),
+        None => (contents.to_string(), None),
+    }
+}
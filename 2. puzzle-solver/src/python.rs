@@ -0,0 +1,130 @@
+// A Python extension module with the same solvers, built with maturin (see pyproject.toml).
+// Errors are raised as ValueError, and the GIL is released while a puzzle is being solved.
+use std::{error::Error, time::Duration};
+
+use pyo3::{exceptions::PyValueError, prelude::*};
+
+use crate::{api, Budget, Rng};
+
+fn py_error(err: Box<dyn Error>) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+fn budget(timeout: Option<f64>) -> PyResult<Budget> {
+    match timeout {
+        None => Ok(Budget::unlimited()),
+        Some(seconds) => match Duration::try_from_secs_f64(seconds) {
+            Ok(timeout) if seconds > 0.0 => Ok(Budget::unlimited().with_timeout(timeout)),
+            _ => Err(PyValueError::new_err(
+                "timeout must be a positive number of seconds.",
+            )),
+        },
+    }
+}
+
+/// Solves a Sudoku and returns the solution as a string of 81 digits.
+#[pyfunction]
+#[pyo3(signature = (input, timeout = None))]
+fn solve_sudoku(py: Python, input: &str, timeout: Option<f64>) -> PyResult<String> {
+    let budget = budget(timeout)?;
+    py.allow_threads(|| api::solve_sudoku(input, &budget).map_err(|err| err.to_string()))
+        .map_err(PyValueError::new_err)
+}
+
+/// Solves a Nonogram and returns the picture, one line per row with # for filled cells.
+#[pyfunction]
+#[pyo3(signature = (input, timeout = None))]
+fn solve_nonogram(py: Python, input: &str, timeout: Option<f64>) -> PyResult<String> {
+    let budget = budget(timeout)?;
+    py.allow_threads(|| api::solve_nonogram(input, &budget).map_err(|err| err.to_string()))
+        .map_err(PyValueError::new_err)
+}
+
+/// Counts the solutions of a Sudoku, stopping at limit. A count of 1 means it is unique.
+#[pyfunction]
+#[pyo3(signature = (input, limit = 2, timeout = None))]
+fn count_sudoku_solutions(
+    py: Python,
+    input: &str,
+    limit: usize,
+    timeout: Option<f64>,
+) -> PyResult<usize> {
+    let budget = budget(timeout)?;
+    py.allow_threads(|| {
+        api::count_sudoku_solutions(input, limit, &budget).map_err(|err| err.to_string())
+    })
+    .map_err(PyValueError::new_err)
+}
+
+/// Counts the solutions of a Nonogram, stopping at limit. A count of 1 means it is unique.
+#[pyfunction]
+#[pyo3(signature = (input, limit = 2, timeout = None))]
+fn count_nonogram_solutions(
+    py: Python,
+    input: &str,
+    limit: usize,
+    timeout: Option<f64>,
+) -> PyResult<usize> {
+    let budget = budget(timeout)?;
+    py.allow_threads(|| {
+        api::count_nonogram_solutions(input, limit, &budget).map_err(|err| err.to_string())
+    })
+    .map_err(PyValueError::new_err)
+}
+
+/// Lists the mistakes in a full or partial Sudoku grid, also comparing it with the solution
+/// of original when one is given. The timeout only applies to solving original.
+#[pyfunction]
+#[pyo3(signature = (grid, original = None, timeout = None))]
+fn check_sudoku(
+    py: Python,
+    grid: &str,
+    original: Option<&str>,
+    timeout: Option<f64>,
+) -> PyResult<Vec<String>> {
+    let budget = budget(timeout)?;
+    py.allow_threads(|| api::check_sudoku(grid, original, &budget).map_err(|err| err.to_string()))
+        .map_err(PyValueError::new_err)
+}
+
+/// Lists the rows and columns of a Nonogram picture that do not match the clues.
+#[pyfunction]
+fn check_nonogram(clues: &str, grid: &str) -> PyResult<Vec<String>> {
+    api::check_nonogram(clues, grid).map_err(py_error)
+}
+
+/// Makes a Sudoku with a unique solution as 81 digits, with 0 for empty cells. A seed gives
+/// the same puzzle every time.
+#[pyfunction]
+#[pyo3(signature = (seed = None))]
+fn generate_sudoku(py: Python, seed: Option<u64>) -> String {
+    let seed = seed.unwrap_or_else(Rng::seed_from_time);
+    py.allow_threads(|| api::generate_sudoku(seed))
+}
+
+/// Makes a Nonogram with a unique solution and returns its two lines of clues.
+#[pyfunction]
+#[pyo3(signature = (width, height, seed = None))]
+fn generate_nonogram(
+    py: Python,
+    width: usize,
+    height: usize,
+    seed: Option<u64>,
+) -> PyResult<String> {
+    let seed = seed.unwrap_or_else(Rng::seed_from_time);
+    py.allow_threads(|| api::generate_nonogram(width, height, seed).map_err(|err| err.to_string()))
+        .map_err(PyValueError::new_err)
+}
+
+#[pymodule]
+fn puzzle_solver(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_function(wrap_pyfunction!(solve_sudoku, module)?)?;
+    module.add_function(wrap_pyfunction!(solve_nonogram, module)?)?;
+    module.add_function(wrap_pyfunction!(count_sudoku_solutions, module)?)?;
+    module.add_function(wrap_pyfunction!(count_nonogram_solutions, module)?)?;
+    module.add_function(wrap_pyfunction!(check_sudoku, module)?)?;
+    module.add_function(wrap_pyfunction!(check_nonogram, module)?)?;
+    module.add_function(wrap_pyfunction!(generate_sudoku, module)?)?;
+    module.add_function(wrap_pyfunction!(generate_nonogram, module)?)?;
+    Ok(())
+}
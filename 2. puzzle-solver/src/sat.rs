@@ -0,0 +1,592 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, GiveUp, Report, Tracker},
+    gattai::Gattai,
+    nonogram::{Nonogram, EMPTY, FILLED},
+    sudoku::{Sudoku, SUDOKU_SIZE},
+};
+
+/// A formula in conjunctive normal form, using DIMACS numbering: variables start at 1 and a
+/// negative literal is the negation of its variable.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Cnf {
+    pub variables: usize,
+    pub clauses: Vec<Vec<i32>>,
+}
+
+impl Cnf {
+    pub fn new() -> Cnf {
+        Cnf::default()
+    }
+
+    pub fn variable(&mut self) -> i32 {
+        self.variables += 1;
+        self.variables as i32
+    }
+
+    pub fn add(&mut self, clause: Vec<i32>) {
+        self.clauses.push(clause);
+    }
+
+    pub fn at_most_one(&mut self, literals: &[i32]) {
+        for (i, first) in literals.iter().enumerate() {
+            for second in &literals[i + 1..] {
+                self.add(vec![-first, -second]);
+            }
+        }
+    }
+
+    pub fn exactly_one(&mut self, literals: &[i32]) {
+        self.add(literals.to_vec());
+        self.at_most_one(literals);
+    }
+
+    pub fn to_dimacs(&self) -> String {
+        let mut dimacs = format!("p cnf {} {}\n", self.variables, self.clauses.len());
+        for clause in &self.clauses {
+            let literals: Vec<String> = clause.iter().map(|lit| lit.to_string()).collect();
+            dimacs.push_str(&literals.join(" "));
+            dimacs.push_str(" 0\n");
+        }
+        dimacs
+    }
+
+    pub fn from_dimacs(contents: &str) -> Result<Cnf, Box<dyn Error>> {
+        let mut cnf = Cnf::new();
+        let mut clause: Vec<i32> = Vec::new();
+        let mut header = false;
+        for line in contents.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('c') || line.starts_with('%') {
+                continue;
+            }
+            if let Some(problem) = line.strip_prefix('p') {
+                let fields: Vec<&str> = problem.split_whitespace().collect();
+                match fields.as_slice() {
+                    ["cnf", variables, _] => cnf.variables = variables.parse()?,
+                    _ => return Err(Box::from("Malformed DIMACS problem line.")),
+                }
+                header = true;
+                continue;
+            }
+            if !header {
+                return Err(Box::from("DIMACS clauses must follow a 'p cnf' line."));
+            }
+            for literal in line.split_whitespace() {
+                let literal: i32 = literal.parse()?;
+                if literal == 0 {
+                    cnf.add(std::mem::take(&mut clause));
+                } else if literal.unsigned_abs() as usize > cnf.variables {
+                    return Err(Box::from(
+                        "DIMACS literal is outside the declared variables.",
+                    ));
+                } else {
+                    clause.push(literal);
+                }
+            }
+        }
+        if !clause.is_empty() {
+            cnf.add(clause);
+        }
+        Ok(cnf)
+    }
+}
+
+/// A puzzle that can be translated to CNF and read back from a satisfying assignment.
+pub trait Encode {
+    fn encode(&self) -> Cnf;
+    // The model is indexed by variable, so model[0] is unused
+    fn decode(&mut self, model: &[bool]);
+}
+
+/// Encodes a grid of cells that each hold one of `digits` values, where no value may repeat
+/// within a unit. Variants such as diagonal Sudoku only need to add their extra units.
+/// The variable for placing `digit` in `cell` is `cell * digits + digit`.
+pub fn encode_units(cells: &[u32], digits: u32, units: &[Vec<usize>]) -> Cnf {
+    let digit_count = digits as usize;
+    let variable = |cell: usize, digit: u32| (cell * digit_count + digit as usize) as i32;
+    let mut cnf = Cnf::new();
+    cnf.variables = cells.len() * digit_count;
+    for (cell, &given) in cells.iter().enumerate() {
+        let literals: Vec<i32> = (1..=digits).map(|digit| variable(cell, digit)).collect();
+        cnf.exactly_one(&literals);
+        if given != 0 {
+            cnf.add(vec![variable(cell, given)]);
+        }
+    }
+    for unit in units {
+        for digit in 1..=digits {
+            let literals: Vec<i32> = unit.iter().map(|&cell| variable(cell, digit)).collect();
+            if unit.len() == digit_count {
+                cnf.add(literals.clone());
+            }
+            cnf.at_most_one(&literals);
+        }
+    }
+    cnf
+}
+
+fn sudoku_units() -> Vec<Vec<usize>> {
+    let size = SUDOKU_SIZE as usize;
+    let mut units = Vec::new();
+    for i in 0..size {
+        units.push((0..size).map(|col| i * size + col).collect());
+        units.push((0..size).map(|row| row * size + i).collect());
+        let (start_row, start_col) = (i / 3 * 3, i % 3 * 3);
+        units.push(
+            (0..size)
+                .map(|j| (start_row + j / 3) * size + start_col + j % 3)
+                .collect(),
+        );
+    }
+    units
+}
+
+impl Encode for Sudoku {
+    fn encode(&self) -> Cnf {
+        let cells: Vec<u32> = self.board.iter().flatten().copied().collect();
+        let mut cnf = encode_units(&cells, SUDOKU_SIZE, &sudoku_units());
+        // Digits already ruled out by pencil marks
+        for (cell, _) in cells.iter().enumerate().filter(|(_, &given)| given == 0) {
+            let (row, col) = (cell / SUDOKU_SIZE as usize, cell % SUDOKU_SIZE as usize);
+            let allowed = self.candidates(row, col);
+            for digit in (1..=SUDOKU_SIZE).filter(|digit| !allowed.contains(digit)) {
+                cnf.add(vec![-((cell * SUDOKU_SIZE as usize) as i32 + digit as i32)]);
+            }
+        }
+        cnf
+    }
+
+    fn decode(&mut self, model: &[bool]) {
+        let size = SUDOKU_SIZE as usize;
+        for (index, chunk) in model[1..].chunks(size).take(size * size).enumerate() {
+            if let Some(digit) = chunk.iter().position(|&set| set) {
+                self.board[index / size][index % size] = digit as u32 + 1;
+            }
+        }
+    }
+}
+
+impl Encode for Gattai {
+    fn encode(&self) -> Cnf {
+        encode_units(&self.values, SUDOKU_SIZE, &self.units)
+    }
+
+    fn decode(&mut self, model: &[bool]) {
+        let size = SUDOKU_SIZE as usize;
+        let cells = self.values.len();
+        for (index, chunk) in model[1..].chunks(size).take(cells).enumerate() {
+            if let Some(digit) = chunk.iter().position(|&set| set) {
+                self.values[index] = digit as u32 + 1;
+            }
+        }
+    }
+}
+
+// The runs of a line and how many positions each of them can start at. Every run can slide
+// by the same amount, since the runs before and after it take up a fixed amount of room.
+fn line_shape(clues: &[u32], length: usize) -> (Vec<usize>, usize) {
+    let runs: Vec<usize> = clues
+        .iter()
+        .filter(|&&run| run > 0)
+        .map(|&run| run as usize)
+        .collect();
+    let needed: usize = runs.iter().map(|run| run + 1).sum();
+    (runs, (length + 2).saturating_sub(needed))
+}
+
+// The variables `encode_line` adds for a line, besides its cells
+fn line_variables(clues: &[u32], length: usize) -> usize {
+    let (runs, positions) = line_shape(clues, length);
+    match positions {
+        0 => 0,
+        _ => runs.iter().map(|run| 2 * positions + run).sum(),
+    }
+}
+
+/// The number of variables in a Nonogram's encoding, worked out from its clues without
+/// building it, so callers can turn down formulas too large to hold in memory.
+pub fn nonogram_variables(nonogram: &Nonogram) -> usize {
+    let (width, height) = (nonogram.width(), nonogram.height());
+    let rows = (0..height).map(|row| line_variables(nonogram.row_clues(row), width));
+    let columns = (0..width).map(|col| line_variables(nonogram.column_clues(col), height));
+    rows.chain(columns)
+        .fold(width * height, |total, variables| {
+            total.saturating_add(variables)
+        })
+}
+
+// Encodes one line of a Nonogram with, for each run, a ladder of variables saying the run
+// starts at least so many cells after its earliest start. This keeps the formula linear in the
+// number of places each run could go.
+fn encode_line(cnf: &mut Cnf, clues: &[u32], cells: &[i32]) {
+    let (runs, positions) = line_shape(clues, cells.len());
+    if !runs.is_empty() && positions == 0 {
+        // The runs do not fit in the line
+        cnf.add(Vec::new());
+        return;
+    }
+    let ladders: Vec<Vec<i32>> = runs
+        .iter()
+        .map(|_| (0..=positions).map(|_| cnf.variable()).collect())
+        .collect();
+    for (index, ladder) in ladders.iter().enumerate() {
+        cnf.add(vec![ladder[0]]);
+        cnf.add(vec![-ladder[positions]]);
+        for offset in 0..positions {
+            cnf.add(vec![-ladder[offset + 1], ladder[offset]]);
+            // Each run starts at least one cell after the one before it ends
+            if let Some(next) = ladders.get(index + 1) {
+                cnf.add(vec![-ladder[offset], next[offset]]);
+            }
+        }
+    }
+
+    let mut covering = vec![Vec::new(); cells.len()];
+    let mut earliest = 0;
+    for (ladder, &run) in ladders.iter().zip(&runs) {
+        for position in earliest..earliest + positions - 1 + run {
+            // The run covers the cell when its offset lies between first and last
+            let first = (position + 1).saturating_sub(earliest + run);
+            let last = (position - earliest).min(positions - 1);
+            let covered = cnf.variable();
+            cnf.add(vec![-covered, ladder[first]]);
+            cnf.add(vec![-covered, -ladder[last + 1]]);
+            cnf.add(vec![covered, -ladder[first], ladder[last + 1]]);
+            cnf.add(vec![-covered, cells[position]]);
+            covering[position].push(covered);
+        }
+        earliest += run + 1;
+    }
+    // A filled cell must be covered by some run
+    for (&cell, mut clause) in cells.iter().zip(covering) {
+        clause.push(-cell);
+        cnf.add(clause);
+    }
+}
+
+impl Encode for Nonogram {
+    fn encode(&self) -> Cnf {
+        let (width, height) = (self.width(), self.height());
+        let mut cnf = Cnf::new();
+        cnf.variables = width * height;
+        let cell = |row: usize, col: usize| (row * width + col + 1) as i32;
+        let mut lines: Vec<(&[u32], Vec<i32>)> = Vec::new();
+        for row in 0..height {
+            lines.push((
+                self.row_clues(row),
+                (0..width).map(|col| cell(row, col)).collect(),
+            ));
+        }
+        for col in 0..width {
+            lines.push((
+                self.column_clues(col),
+                (0..height).map(|row| cell(row, col)).collect(),
+            ));
+        }
+        for (clues, cells) in lines {
+            encode_line(&mut cnf, clues, &cells);
+        }
+        cnf
+    }
+
+    fn decode(&mut self, model: &[bool]) {
+        let width = self.width();
+        for index in 0..width * self.height() {
+            let cell = if model[index + 1] { FILLED } else { EMPTY };
+            self.set_cell(index / width, index % width, cell);
+        }
+    }
+}
+
+fn code(literal: i32) -> usize {
+    2 * (literal.unsigned_abs() as usize - 1) + usize::from(literal < 0)
+}
+
+fn var(literal: i32) -> usize {
+    literal.unsigned_abs() as usize
+}
+
+const ACTIVITY_DECAY: f64 = 0.95;
+
+/// CDCL solver: two watched literals per clause, first-UIP clause learning with
+/// non-chronological backjumping, and activity-ordered branching with phase saving.
+struct Solver {
+    clauses: Vec<Vec<i32>>,
+    watches: Vec<Vec<usize>>,
+    values: Vec<Option<bool>>,
+    levels: Vec<usize>,
+    reasons: Vec<Option<usize>>,
+    activity: Vec<f64>,
+    bump: f64,
+    phases: Vec<bool>,
+    trail: Vec<i32>,
+    // Trail length at the start of each decision level
+    level_starts: Vec<usize>,
+    head: usize,
+}
+
+impl Solver {
+    fn new(variables: usize) -> Solver {
+        Solver {
+            clauses: Vec::new(),
+            watches: vec![Vec::new(); 2 * variables],
+            values: vec![None; variables + 1],
+            levels: vec![0; variables + 1],
+            reasons: vec![None; variables + 1],
+            activity: vec![0.0; variables + 1],
+            bump: 1.0,
+            phases: vec![true; variables + 1],
+            trail: Vec::new(),
+            level_starts: Vec::new(),
+            head: 0,
+        }
+    }
+
+    fn value(&self, literal: i32) -> Option<bool> {
+        let value = self.values[var(literal)]?;
+        Some(value == (literal > 0))
+    }
+
+    fn assign(&mut self, literal: i32, reason: Option<usize>) {
+        let var = var(literal);
+        self.values[var] = Some(literal > 0);
+        self.levels[var] = self.level_starts.len();
+        self.reasons[var] = reason;
+        self.trail.push(literal);
+    }
+
+    // Watches the first two literals of a clause, which must not already be false
+    fn attach(&mut self, clause: Vec<i32>) -> usize {
+        let index = self.clauses.len();
+        self.watches[code(clause[0])].push(index);
+        self.watches[code(clause[1])].push(index);
+        self.clauses.push(clause);
+        index
+    }
+
+    // Adds the clauses, returning false if they are trivially contradictory
+    fn load(&mut self, cnf: &Cnf) -> bool {
+        for clause in &cnf.clauses {
+            let mut clause = clause.clone();
+            clause.sort_unstable();
+            clause.dedup();
+            if clause.windows(2).any(|pair| pair[0] == -pair[1]) {
+                continue;
+            }
+            for literal in &clause {
+                self.activity[var(*literal)] += 1.0;
+            }
+            match clause.len() {
+                0 => return false,
+                1 => match self.value(clause[0]) {
+                    Some(false) => return false,
+                    Some(true) => {}
+                    None => self.assign(clause[0], None),
+                },
+                _ => {
+                    self.attach(clause);
+                }
+            }
+        }
+        true
+    }
+
+    // Returns the index of a conflicting clause, if any
+    fn propagate(&mut self, tracker: &mut Tracker) -> Option<usize> {
+        while self.head < self.trail.len() {
+            let falsified = -self.trail[self.head];
+            self.head += 1;
+            let watching = std::mem::take(&mut self.watches[code(falsified)]);
+            let mut kept = Vec::with_capacity(watching.len());
+            let mut conflict = None;
+            for (position, &index) in watching.iter().enumerate() {
+                if conflict.is_some() {
+                    kept.extend_from_slice(&watching[position..]);
+                    break;
+                }
+                // Keep the falsified watch in second place so the first is the one that may be implied
+                if self.clauses[index][0] == falsified {
+                    self.clauses[index].swap(0, 1);
+                }
+                let other = self.clauses[index][0];
+                if self.value(other) == Some(true) {
+                    kept.push(index);
+                    continue;
+                }
+                let replacement = (2..self.clauses[index].len())
+                    .find(|&k| self.value(self.clauses[index][k]) != Some(false));
+                if let Some(k) = replacement {
+                    self.clauses[index].swap(1, k);
+                    self.watches[code(self.clauses[index][1])].push(index);
+                    continue;
+                }
+                kept.push(index);
+                match self.value(other) {
+                    Some(false) => conflict = Some(index),
+                    _ => {
+                        tracker.propagate();
+                        self.assign(other, Some(index));
+                    }
+                }
+            }
+            self.watches[code(falsified)] = kept;
+            if conflict.is_some() {
+                return conflict;
+            }
+        }
+        None
+    }
+
+    fn bump(&mut self, var: usize) {
+        self.activity[var] += self.bump;
+        if self.activity[var] > 1e100 {
+            for activity in self.activity.iter_mut() {
+                *activity *= 1e-100;
+            }
+            self.bump *= 1e-100;
+        }
+    }
+
+    // Derives a first-UIP clause from a conflict, returning it with the level to jump back to
+    fn analyze(&mut self, conflict: usize) -> (Vec<i32>, usize) {
+        let level = self.level_starts.len();
+        let mut seen = vec![false; self.values.len()];
+        let mut learnt = vec![0];
+        let mut pending = 0;
+        let mut clause = conflict;
+        let mut skip_first = false;
+        let mut position = self.trail.len();
+        let implied = loop {
+            for k in usize::from(skip_first)..self.clauses[clause].len() {
+                let literal = self.clauses[clause][k];
+                let var = var(literal);
+                if seen[var] || self.levels[var] == 0 {
+                    continue;
+                }
+                seen[var] = true;
+                self.bump(var);
+                if self.levels[var] == level {
+                    pending += 1;
+                } else {
+                    learnt.push(literal);
+                }
+            }
+            loop {
+                position -= 1;
+                if seen[var(self.trail[position])] {
+                    break;
+                }
+            }
+            let literal = self.trail[position];
+            seen[var(literal)] = false;
+            pending -= 1;
+            if pending == 0 {
+                break literal;
+            }
+            clause = self.reasons[var(literal)].expect("implied literals have reasons");
+            skip_first = true;
+        };
+        learnt[0] = -implied;
+        self.bump /= ACTIVITY_DECAY;
+
+        // The literal from the highest remaining level becomes the second watch
+        let mut jump = 0;
+        for k in 1..learnt.len() {
+            if self.levels[var(learnt[k])] > jump {
+                jump = self.levels[var(learnt[k])];
+                learnt.swap(1, k);
+            }
+        }
+        (learnt, jump)
+    }
+
+    fn backjump(&mut self, level: usize) {
+        if level >= self.level_starts.len() {
+            return;
+        }
+        let start = self.level_starts[level];
+        for literal in self.trail.drain(start..) {
+            let var = var(literal);
+            self.phases[var] = literal > 0;
+            self.values[var] = None;
+            self.reasons[var] = None;
+        }
+        self.level_starts.truncate(level);
+        self.head = start;
+    }
+
+    fn decide(&self) -> Option<usize> {
+        let mut best: Option<usize> = None;
+        for var in 1..self.values.len() {
+            if self.values[var].is_none()
+                && best.is_none_or(|best| self.activity[var] > self.activity[best])
+            {
+                best = Some(var);
+            }
+        }
+        best
+    }
+
+    fn search(&mut self, tracker: &mut Tracker) -> Result<bool, GiveUp> {
+        loop {
+            if let Some(conflict) = self.propagate(tracker) {
+                if self.level_starts.is_empty() {
+                    return Ok(false);
+                }
+                tracker.backtrack();
+                let (learnt, jump) = self.analyze(conflict);
+                self.backjump(jump);
+                if learnt.len() == 1 {
+                    self.assign(learnt[0], None);
+                } else {
+                    let implied = learnt[0];
+                    let reason = self.attach(learnt);
+                    self.assign(implied, Some(reason));
+                }
+                continue;
+            }
+            let Some(var) = self.decide() else {
+                return Ok(true);
+            };
+            tracker.visit(self.level_starts.len() + 1)?;
+            self.level_starts.push(self.trail.len());
+            let literal = if self.phases[var] {
+                var as i32
+            } else {
+                -(var as i32)
+            };
+            self.assign(literal, None);
+        }
+    }
+}
+
+/// Solves a formula, returning the satisfying assignment (indexed by variable) if one was found.
+pub fn solve_cnf(cnf: &Cnf, budget: &Budget) -> (Report, Option<Vec<bool>>) {
+    let mut tracker = Tracker::new(budget);
+    let mut solver = Solver::new(cnf.variables);
+    let result = match solver.load(cnf) {
+        true => solver.search(&mut tracker),
+        false => Ok(false),
+    };
+    let model = match result {
+        Ok(true) => Some(
+            solver
+                .values
+                .iter()
+                .map(|value| value.unwrap_or(false))
+                .collect(),
+        ),
+        _ => None,
+    };
+    (tracker.finish(result), model)
+}
+
+/// Solves a puzzle by encoding it as CNF and decoding the model back into its board.
+pub fn solve_with_sat<P: Encode>(puzzle: &mut P, budget: &Budget) -> Report {
+    let (report, model) = solve_cnf(&puzzle.encode(), budget);
+    if let Some(model) = model {
+        puzzle.decode(&model);
+    }
+    report
+}
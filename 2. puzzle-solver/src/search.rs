@@ -0,0 +1,155 @@
+use crate::budget::{Budget, GiveUp, Outcome, Report, Stats, Tracker};
+
+/// A puzzle whose cells can be filled in a fixed order by the backtracking [`Search`].
+pub trait Backtrack {
+    type Value: Copy;
+
+    // Number of cells the search assigns, numbered from 0 in the order they are filled
+    fn cells(&self) -> usize;
+    // The values to try for a cell in order, or None when the cell is already fixed
+    fn options(&self, index: usize) -> Option<Vec<Self::Value>>;
+    // Writes a value into a cell and returns whether the board is still consistent
+    fn place(&mut self, index: usize, value: Self::Value) -> bool;
+    fn clear(&mut self, index: usize);
+}
+
+#[derive(Debug, Clone)]
+struct Frame<V> {
+    index: usize,
+    options: Option<Vec<V>>,
+    next: usize,
+    placed: bool,
+}
+
+/// Iterative backtracking search that keeps its decisions on an explicit stack instead of
+/// recursing once per cell.
+///
+/// A search that gives up because of its budget can be resumed by calling [`Search::run`]
+/// again with the same puzzle. Cloning a paused search together with its puzzle snapshots it,
+/// and running a search again after it has found a solution continues on to the next one.
+#[derive(Debug, Clone)]
+pub struct Search<V> {
+    stack: Vec<Frame<V>>,
+    descend: bool,
+    exhausted: bool,
+    stats: Stats,
+}
+
+impl<V: Copy> Default for Search<V> {
+    fn default() -> Self {
+        Search::new()
+    }
+}
+
+impl<V: Copy> Search<V> {
+    pub fn new() -> Search<V> {
+        Search {
+            stack: Vec::new(),
+            descend: true,
+            exhausted: false,
+            stats: Stats::default(),
+        }
+    }
+
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    // Statistics accumulated over every run of this search
+    pub fn stats(&self) -> &Stats {
+        &self.stats
+    }
+
+    pub fn run<P: Backtrack<Value = V>>(&mut self, puzzle: &mut P, budget: &Budget) -> Report {
+        let mut tracker = Tracker::resume(budget, self.stats.clone());
+        let result = self.search(puzzle, &mut tracker);
+        let report = tracker.finish(result);
+        self.stats = report.stats.clone();
+        report
+    }
+
+    fn search<P: Backtrack<Value = V>>(
+        &mut self,
+        puzzle: &mut P,
+        tracker: &mut Tracker,
+    ) -> Result<bool, GiveUp> {
+        if self.exhausted {
+            return Ok(false);
+        }
+        loop {
+            if self.descend {
+                let index = self.stack.len();
+                if index == puzzle.cells() {
+                    // The next run backs out of this solution and looks for another
+                    self.descend = false;
+                    return Ok(true);
+                }
+                tracker.visit(index + 1)?;
+                self.stack.push(Frame {
+                    index,
+                    options: puzzle.options(index),
+                    next: 0,
+                    placed: false,
+                });
+            }
+            self.descend = self.advance(puzzle, tracker);
+            if !self.descend && self.stack.is_empty() {
+                self.exhausted = true;
+                return Ok(false);
+            }
+        }
+    }
+
+    // Moves the top frame on to its next value, popping it once every value has failed.
+    // Returns whether the search can descend to the following cell.
+    fn advance<P: Backtrack<Value = V>>(&mut self, puzzle: &mut P, tracker: &mut Tracker) -> bool {
+        let Some(frame) = self.stack.last_mut() else {
+            return false;
+        };
+        let Some(options) = &frame.options else {
+            // Fixed cells are passed through once on the way down and popped on the way back
+            let first_visit = !frame.placed;
+            frame.placed = true;
+            if !first_visit {
+                self.stack.pop();
+            }
+            return first_visit;
+        };
+        if frame.placed {
+            puzzle.clear(frame.index);
+            tracker.backtrack();
+            frame.placed = false;
+        }
+        while frame.next < options.len() {
+            let value = options[frame.next];
+            frame.next += 1;
+            tracker.propagate();
+            if puzzle.place(frame.index, value) {
+                frame.placed = true;
+                return true;
+            }
+        }
+        puzzle.clear(frame.index);
+        self.stack.pop();
+        false
+    }
+}
+
+/// Counts the solutions of a puzzle, stopping as soon as `limit` have been found, so a limit of
+/// 2 tells whether a puzzle has exactly one. The budget applies to the search for each solution.
+pub fn count_solutions<P: Backtrack>(
+    puzzle: &mut P,
+    limit: usize,
+    budget: &Budget,
+) -> Result<usize, GiveUp> {
+    let mut search = Search::new();
+    let mut count = 0;
+    while count < limit {
+        match search.run(puzzle, budget).outcome {
+            Outcome::Solved => count += 1,
+            Outcome::Unsolvable => break,
+            Outcome::GaveUp(reason) => return Err(reason),
+        }
+    }
+    Ok(count)
+}
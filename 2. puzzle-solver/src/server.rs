@@ -0,0 +1,394 @@
+use std::{
+    io::{self, BufRead, BufReader, ErrorKind, Read, Write},
+    net::{TcpListener, TcpStream},
+    panic::{self, AssertUnwindSafe},
+    sync::{mpsc, Arc, Mutex},
+    thread,
+    time::Duration,
+};
+
+use crate::{
+    budget::{Budget, Outcome, Report, Stats, Tracker},
+    json::Json,
+    nonogram::{Nonogram, FILLED},
+    sat::{nonogram_variables, solve_cnf, Encode},
+    search::{Backtrack, Search},
+    sudoku::Sudoku,
+    Backend, Puzzle,
+};
+
+// Room allowed for the request line and headers on top of the body limit
+const MAX_HEADER_BYTES: usize = 8 * 1024;
+
+/// Limits applied to every request the solving service handles.
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub max_request_bytes: usize,
+    // Longest a solve may run, including the check for a second solution
+    pub solve_timeout: Duration,
+    // Longest the server waits for a client to finish sending its request
+    pub read_timeout: Duration,
+    // Largest formula the sat backend may build for a nonogram, in variables
+    pub max_sat_variables: usize,
+    // Connections handled at once, with the rest waiting their turn
+    pub workers: usize,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            max_request_bytes: 64 * 1024,
+            solve_timeout: Duration::from_secs(10),
+            read_timeout: Duration::from_secs(5),
+            max_sat_variables: 500_000,
+            workers: 8,
+        }
+    }
+}
+
+/// An HTTP status code with the JSON body sent back for it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    pub body: Json,
+}
+
+impl Response {
+    fn error(status: u16, message: &str) -> Response {
+        Response {
+            status,
+            body: Json::object(vec![("error", Json::String(message.to_string()))]),
+        }
+    }
+}
+
+/// Serves the HTTP API on a listener until it fails, handling connections on a fixed pool of
+/// worker threads. `POST /solve/sudoku` and `POST /solve/nonogram` take the puzzle either as
+/// plain text in the usual file format or as a JSON object, and `GET /health` reports that the
+/// server is up.
+pub fn serve(listener: TcpListener, config: ServerConfig) -> io::Result<()> {
+    let workers = config.workers.max(1);
+    // Accepted connections queue up for the workers, and accepting waits while the queue is full
+    let (sender, receiver) = mpsc::sync_channel::<TcpStream>(workers);
+    let receiver = Arc::new(Mutex::new(receiver));
+    for _ in 0..workers {
+        let receiver = Arc::clone(&receiver);
+        let config = config.clone();
+        thread::spawn(move || loop {
+            let next = receiver.lock().map(|receiver| receiver.recv());
+            let Ok(Ok(stream)) = next else {
+                return;
+            };
+            // A request that panics should not take its worker down with it
+            let _ = panic::catch_unwind(AssertUnwindSafe(|| handle_connection(stream, &config)));
+        });
+    }
+    for stream in listener.incoming() {
+        // A client that hangs up before being accepted should not stop the server
+        let Ok(stream) = stream else {
+            continue;
+        };
+        if sender.send(stream).is_err() {
+            return Err(io::Error::other("The worker threads have stopped."));
+        }
+    }
+    Ok(())
+}
+
+fn handle_connection(mut stream: TcpStream, config: &ServerConfig) -> io::Result<()> {
+    stream.set_read_timeout(Some(config.read_timeout))?;
+    let response = match read_request(&stream, config) {
+        Ok((method, path, content_type, body)) => {
+            handle_request(&method, &path, content_type.as_deref(), &body, config)
+        }
+        Err(response) => response,
+    };
+    let body = response.body.to_string();
+    write!(
+        stream,
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\
+         Access-Control-Allow-Origin: *\r\nAccess-Control-Allow-Headers: Content-Type\r\n\
+         Connection: close\r\n\r\n{}",
+        response.status,
+        reason_phrase(response.status),
+        body.len(),
+        body
+    )?;
+    stream.flush()
+}
+
+// The method, path, content type and body of a request
+type Request = (String, String, Option<String>, String);
+
+fn read_request(stream: &TcpStream, config: &ServerConfig) -> Result<Request, Response> {
+    let limit = config.max_request_bytes.saturating_add(MAX_HEADER_BYTES) as u64;
+    let mut reader = BufReader::new(stream.take(limit));
+    let read_error = |err: io::Error| match err.kind() {
+        ErrorKind::WouldBlock | ErrorKind::TimedOut => {
+            Response::error(408, "Timed out waiting for the request.")
+        }
+        _ => Response::error(400, "Could not read the request."),
+    };
+
+    let mut line = String::new();
+    reader.read_line(&mut line).map_err(read_error)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Err(Response::error(400, "Malformed request line."));
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let (mut content_length, mut content_type) = (None, None);
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).map_err(read_error)? == 0 {
+            return Err(Response::error(400, "The request headers were cut off."));
+        }
+        let header = line.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        let Some((name, value)) = header.split_once(':') else {
+            return Err(Response::error(400, "Malformed request header."));
+        };
+        match name.trim().to_lowercase().as_str() {
+            "content-length" => {
+                let length = value
+                    .trim()
+                    .parse::<usize>()
+                    .map_err(|_| Response::error(400, "Content-Length must be a whole number."))?;
+                content_length = Some(length);
+            }
+            "content-type" => content_type = Some(value.trim().to_lowercase()),
+            _ => {}
+        }
+    }
+
+    let length = content_length.unwrap_or(0);
+    if length > config.max_request_bytes {
+        return Err(Response::error(413, "The request body is too large."));
+    }
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body).map_err(read_error)?;
+    let body =
+        String::from_utf8(body).map_err(|_| Response::error(400, "The body must be UTF-8."))?;
+    Ok((method, path, content_type, body))
+}
+
+/// Answers a single request. This is everything the server does apart from reading and
+/// writing the connection, so it can be called directly without any networking.
+pub fn handle_request(
+    method: &str,
+    path: &str,
+    content_type: Option<&str>,
+    body: &str,
+    config: &ServerConfig,
+) -> Response {
+    let puzzle = match path.strip_prefix("/solve/") {
+        Some(puzzle @ ("sudoku" | "nonogram")) => puzzle,
+        Some(_) => return Response::error(404, "The specified puzzle is not supported."),
+        None if path == "/health" && method == "GET" => {
+            return Response {
+                status: 200,
+                body: Json::object(vec![("status", Json::String("ok".to_string()))]),
+            }
+        }
+        None => return Response::error(404, "Not found."),
+    };
+    match method {
+        "POST" => {}
+        // Browsers check with OPTIONS before sending JSON to another origin
+        "OPTIONS" => {
+            return Response {
+                status: 200,
+                body: Json::Null,
+            }
+        }
+        _ => return Response::error(405, "Puzzles must be sent with POST."),
+    }
+    if body.len() > config.max_request_bytes {
+        return Response::error(413, "The request body is too large.");
+    }
+
+    let (mut contents, backend, budget) = match read_options(content_type, body, config) {
+        Ok(options) => options,
+        Err(message) => return Response::error(400, &message),
+    };
+    let result = match puzzle {
+        "sudoku" => Sudoku::build(&mut contents).map(|mut sudoku| {
+            // The search assumes the givens do not already clash
+            let (report, unique) = match sudoku.is_valid() {
+                true => solve(&mut sudoku, &backend, &budget),
+                false => (Tracker::new(&budget).finish(Ok(false)), None),
+            };
+            let grid = sudoku
+                .board
+                .iter()
+                .map(|row| Json::Array(row.iter().map(|&d| Json::Number(d.into())).collect()))
+                .collect();
+            solved_response(report, unique, grid)
+        }),
+        _ => Nonogram::build(&mut contents).map(|mut nonogram| {
+            // Building the formula for a huge nonogram could use up the server's memory
+            if backend == Backend::Sat && nonogram_variables(&nonogram) > config.max_sat_variables {
+                return Response::error(413, "The nonogram is too large for the sat backend.");
+            }
+            let (report, unique) = solve(&mut nonogram, &backend, &budget);
+            let grid = (0..nonogram.height())
+                .map(|row| {
+                    let cells = (0..nonogram.width())
+                        .map(|col| Json::Bool(nonogram.cell(row, col) == FILLED));
+                    Json::Array(cells.collect())
+                })
+                .collect();
+            solved_response(report, unique, grid)
+        }),
+    };
+    result.unwrap_or_else(|err| Response::error(400, &err.to_string()))
+}
+
+// The puzzle text, backend and budget for a request. Plain text bodies are the puzzle itself,
+// while JSON bodies hold it under "input" and may also pick a backend and a shorter timeout.
+fn read_options(
+    content_type: Option<&str>,
+    body: &str,
+    config: &ServerConfig,
+) -> Result<(String, Backend, Budget), String> {
+    let mut timeout = config.solve_timeout;
+    if !content_type.is_some_and(|kind| kind.starts_with("application/json")) {
+        return Ok((
+            body.to_string(),
+            Backend::Search,
+            Budget::unlimited().with_timeout(timeout),
+        ));
+    }
+    let json = Json::parse(body)?;
+    let input = json
+        .get("input")
+        .and_then(Json::as_str)
+        .ok_or("JSON requests need the puzzle as an \"input\" string.")?;
+    let backend = match json.get("backend").map(|backend| backend.as_str()) {
+        None | Some(Some("search")) => Backend::Search,
+        Some(Some("sat")) => Backend::Sat,
+        _ => return Err("\"backend\" must be either search or sat.".to_string()),
+    };
+    if let Some(seconds) = json.get("timeout") {
+        let seconds = seconds
+            .as_f64()
+            .filter(|seconds| *seconds > 0.0)
+            .ok_or("\"timeout\" must be a positive number of seconds.")?;
+        // Clients may ask for less time than the server allows, but never more
+        timeout = timeout.min(Duration::from_secs_f64(seconds.min(u32::MAX.into())));
+    }
+    Ok((
+        input.to_string(),
+        backend,
+        Budget::unlimited().with_timeout(timeout),
+    ))
+}
+
+fn solved_response(report: Report, unique: Option<bool>, grid: Vec<Json>) -> Response {
+    let (outcome, reason) = match report.outcome {
+        Outcome::Solved => ("solved", Json::Null),
+        Outcome::Unsolvable => ("unsolvable", Json::Null),
+        Outcome::GaveUp(reason) => ("gave up", Json::String(reason.describe().to_string())),
+    };
+    let solution = match report.outcome {
+        Outcome::Solved => Json::Array(grid),
+        _ => Json::Null,
+    };
+    let stats = &report.stats;
+    Response {
+        status: 200,
+        body: Json::object(vec![
+            ("outcome", Json::String(outcome.to_string())),
+            ("reason", reason),
+            ("solution", solution),
+            ("unique", unique.map_or(Json::Null, Json::Bool)),
+            (
+                "stats",
+                Json::object(vec![
+                    ("nodes", Json::Number(stats.nodes as f64)),
+                    ("backtracks", Json::Number(stats.backtracks as f64)),
+                    ("propagations", Json::Number(stats.propagations as f64)),
+                    ("max_depth", Json::Number(stats.max_depth as f64)),
+                    (
+                        "elapsed_ms",
+                        Json::Number(stats.elapsed.as_secs_f64() * 1000.0),
+                    ),
+                ]),
+            ),
+        ]),
+    }
+}
+
+// Solves the puzzle, then looks for a second solution with whatever time is left. Whether the
+// solution is unique is None when the puzzle is unsolved or the second search gave up.
+fn solve<P: Backtrack + Encode + Clone>(
+    puzzle: &mut P,
+    backend: &Backend,
+    budget: &Budget,
+) -> (Report, Option<bool>) {
+    let remaining = |stats: &Stats| {
+        let timeout = budget.timeout.unwrap_or(Duration::MAX);
+        Budget::unlimited().with_timeout(timeout.saturating_sub(stats.elapsed))
+    };
+    let (report, second) = match backend {
+        Backend::Search => {
+            let mut search = Search::new();
+            let report = search.run(puzzle, budget);
+            if report.outcome != Outcome::Solved {
+                return (report, None);
+            }
+            let solution = puzzle.clone();
+            let second = search.run(puzzle, &remaining(&report.stats));
+            *puzzle = solution;
+            // The search keeps counting across both runs
+            let report = Report {
+                stats: second.stats.clone(),
+                ..report
+            };
+            (report, second)
+        }
+        Backend::Sat => {
+            let mut cnf = puzzle.encode();
+            let (report, model) = solve_cnf(&cnf, budget);
+            let Some(model) = model else {
+                return (report, None);
+            };
+            puzzle.decode(&model);
+            // Rule out this exact solution and ask for any other
+            let blocking = (1..model.len())
+                .filter(|&variable| model[variable])
+                .map(|variable| -(variable as i32))
+                .collect();
+            cnf.add(blocking);
+            let (second, _) = solve_cnf(&cnf, &remaining(&report.stats));
+            let mut stats = report.stats.clone();
+            stats.nodes += second.stats.nodes;
+            stats.backtracks += second.stats.backtracks;
+            stats.propagations += second.stats.propagations;
+            stats.max_depth = stats.max_depth.max(second.stats.max_depth);
+            stats.elapsed += second.stats.elapsed;
+            (Report { stats, ..report }, second)
+        }
+    };
+    let unique = match second.outcome {
+        Outcome::Solved => Some(false),
+        Outcome::Unsolvable => Some(true),
+        Outcome::GaveUp(_) => None,
+    };
+    (report, unique)
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        408 => "Request Timeout",
+        413 => "Payload Too Large",
+        _ => "Internal Server Error",
+    }
+}
@@ -0,0 +1,290 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, Report},
+    search::{Backtrack, Search},
+    Puzzle,
+};
+
+/// A Slitherlink grid. The solution is a single closed loop drawn along the edges between
+/// cells, where every numbered cell has exactly that many of its four edges on the loop.
+///
+/// Edges are numbered a row of the grid at a time: the horizontal edges along the top of row
+/// `r`, then the vertical edges down its sides, so the search works down the grid in order.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Slitherlink {
+    height: usize,
+    width: usize,
+    clues: Vec<Option<u8>>,
+    // Whether each edge is on the loop, or None while undecided
+    edges: Vec<Option<bool>>,
+    // The two corners joined by each edge
+    ends: Vec<[usize; 2]>,
+    // The one or two cells on either side of each edge
+    sides: Vec<Vec<usize>>,
+    // The edges meeting at each corner, and the four edges around each cell
+    corner_edges: Vec<Vec<usize>>,
+    cell_edges: Vec<[usize; 4]>,
+    on: usize,
+    // The edge that closed the loop, after which no more edges can be added
+    closed_by: Option<usize>,
+}
+
+impl Slitherlink {
+    /// An empty grid of the given size with clues by row, None for cells without a number.
+    pub fn new(height: usize, width: usize, clues: Vec<Option<u8>>) -> Slitherlink {
+        let stride = 2 * width + 1;
+        let horizontal = |row: usize, col: usize| row * stride + col;
+        let vertical = |row: usize, col: usize| row * stride + width + col;
+        let corner = |row: usize, col: usize| row * (width + 1) + col;
+        let count = height * stride + width;
+
+        let mut ends = vec![[0; 2]; count];
+        let mut sides = vec![Vec::new(); count];
+        let mut corner_edges = vec![Vec::new(); (height + 1) * (width + 1)];
+        for row in 0..=height {
+            for col in 0..width {
+                let edge = horizontal(row, col);
+                ends[edge] = [corner(row, col), corner(row, col + 1)];
+                sides[edge].extend(
+                    [row.checked_sub(1), (row < height).then_some(row)]
+                        .into_iter()
+                        .flatten()
+                        .map(|r| r * width + col),
+                );
+            }
+        }
+        for row in 0..height {
+            for col in 0..=width {
+                let edge = vertical(row, col);
+                ends[edge] = [corner(row, col), corner(row + 1, col)];
+                sides[edge].extend(
+                    [col.checked_sub(1), (col < width).then_some(col)]
+                        .into_iter()
+                        .flatten()
+                        .map(|c| row * width + c),
+                );
+            }
+        }
+        for (edge, pair) in ends.iter().enumerate() {
+            for &end in pair {
+                corner_edges[end].push(edge);
+            }
+        }
+        let cell_edges = (0..height * width)
+            .map(|cell| {
+                let (row, col) = (cell / width, cell % width);
+                [
+                    horizontal(row, col),
+                    horizontal(row + 1, col),
+                    vertical(row, col),
+                    vertical(row, col + 1),
+                ]
+            })
+            .collect();
+
+        Slitherlink {
+            height,
+            width,
+            clues,
+            edges: vec![None; count],
+            ends,
+            sides,
+            corner_edges,
+            cell_edges,
+            on: 0,
+            closed_by: None,
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn clue(&self, row: usize, col: usize) -> Option<u8> {
+        self.clues[row * self.width + col]
+    }
+
+    /// Whether the edge along the top of a cell is on the loop. Row `height` is the bottom edge
+    /// of the grid.
+    pub fn top(&self, row: usize, col: usize) -> bool {
+        self.edges[row * (2 * self.width + 1) + col] == Some(true)
+    }
+
+    /// Whether the edge down the left side of a cell is on the loop. Column `width` is the
+    /// right edge of the grid.
+    pub fn left(&self, row: usize, col: usize) -> bool {
+        self.edges[row * (2 * self.width + 1) + self.width + col] == Some(true)
+    }
+
+    // The number of edges that are on and still undecided out of a group
+    fn tally(&self, edges: &[usize]) -> (usize, usize) {
+        edges
+            .iter()
+            .fold((0, 0), |(on, open), &edge| match self.edges[edge] {
+                Some(true) => (on + 1, open),
+                Some(false) => (on, open),
+                None => (on, open + 1),
+            })
+    }
+
+    fn cell_allows(&self, cell: usize) -> bool {
+        let Some(clue) = self.clues[cell] else {
+            return true;
+        };
+        let (on, open) = self.tally(&self.cell_edges[cell]);
+        on <= clue as usize && on + open >= clue as usize
+    }
+
+    // A corner of the loop has exactly two edges, and every other corner has none
+    fn corner_allows(&self, corner: usize) -> bool {
+        match self.tally(&self.corner_edges[corner]) {
+            (on, _) if on > 2 => false,
+            (1, 0) => false,
+            _ => true,
+        }
+    }
+
+    // Follows the path through an edge that was just added. Returns the length of the loop if
+    // the path comes back around to where it started.
+    fn loop_through(&self, edge: usize) -> Option<usize> {
+        let [start, mut corner] = self.ends[edge];
+        let (mut previous, mut length) = (edge, 1);
+        while corner != start {
+            previous = *self.corner_edges[corner]
+                .iter()
+                .find(|&&next| next != previous && self.edges[next] == Some(true))?;
+            let [a, b] = self.ends[previous];
+            corner = if a == corner { b } else { a };
+            length += 1;
+        }
+        Some(length)
+    }
+}
+
+impl Backtrack for Slitherlink {
+    type Value = bool;
+
+    fn cells(&self) -> usize {
+        self.edges.len()
+    }
+
+    fn options(&self, _index: usize) -> Option<Vec<bool>> {
+        Some(vec![false, true])
+    }
+
+    fn place(&mut self, index: usize, value: bool) -> bool {
+        if self.edges[index] == Some(true) {
+            self.on -= 1;
+        }
+        self.edges[index] = Some(value);
+        if value {
+            self.on += 1;
+        }
+        let local = self.sides[index].iter().all(|&cell| self.cell_allows(cell))
+            && self.ends[index]
+                .iter()
+                .all(|&corner| self.corner_allows(corner));
+        if !local {
+            return false;
+        }
+        if value {
+            // Once the loop is closed it has to be the only one
+            if self.closed_by.is_some() {
+                return false;
+            }
+            if let Some(length) = self.loop_through(index) {
+                if length != self.on {
+                    return false;
+                }
+                self.closed_by = Some(index);
+            }
+        }
+        // With every edge decided, a grid without a loop has no edges at all
+        index + 1 < self.edges.len() || self.closed_by.is_some()
+    }
+
+    fn clear(&mut self, index: usize) {
+        if self.edges[index] == Some(true) {
+            self.on -= 1;
+        }
+        self.edges[index] = None;
+        if self.closed_by == Some(index) {
+            self.closed_by = None;
+        }
+    }
+}
+
+impl Puzzle for Slitherlink {
+    // One row of clues per line, with a number from 0 to 4 for each clue and . for cells
+    // without one. Spaces between cells are optional.
+    fn build(contents: &mut String) -> Result<Self, Box<dyn Error>> {
+        let mut rows = Vec::new();
+        for (row, line) in contents
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+        {
+            let cells = line
+                .chars()
+                .filter(|c| !c.is_whitespace())
+                .map(|c| match c {
+                    '.' | '_' => Ok(None),
+                    '0'..='4' => Ok(Some(c as u8 - b'0')),
+                    c => Err(format!(
+                        "Row {} has an invalid clue \"{}\". Clues must be 0 to 4, or . for none.",
+                        row + 1,
+                        c
+                    )),
+                })
+                .collect::<Result<Vec<Option<u8>>, String>>()?;
+            rows.push(cells);
+        }
+        let width = rows.first().map_or(0, Vec::len);
+        if width == 0 {
+            return Err(Box::from("Failed to read puzzle. The grid is empty."));
+        }
+        if rows.iter().any(|row| row.len() != width) {
+            return Err(Box::from(
+                "Failed to read puzzle. Every row must have the same number of cells.",
+            ));
+        }
+        Ok(Slitherlink::new(rows.len(), width, rows.concat()))
+    }
+
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        Search::new().run(self, budget)
+    }
+
+    // Draws the loop with + at the corners, --- and | for edges on the loop and the clues in
+    // the middle of their cells
+    fn format(&self) -> String {
+        let mut lines = Vec::new();
+        for row in 0..=self.height {
+            let mut border = String::from("+");
+            for col in 0..self.width {
+                border += if self.top(row, col) { "---+" } else { "   +" };
+            }
+            lines.push(border);
+            if row == self.height {
+                break;
+            }
+            let mut cells = String::new();
+            for col in 0..=self.width {
+                cells.push(if self.left(row, col) { '|' } else { ' ' });
+                if col < self.width {
+                    match self.clue(row, col) {
+                        Some(clue) => cells += &format!(" {} ", clue),
+                        None => cells += "   ",
+                    }
+                }
+            }
+            lines.push(cells.trim_end().to_string());
+        }
+        lines.join("\n")
+    }
+}
@@ -0,0 +1,157 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, Report},
+    search::{Backtrack, Search},
+    Puzzle,
+};
+
+/// A Star Battle grid, split into as many regions as it has rows. Every row, column and region
+/// holds the same number of stars, and no two stars touch, not even diagonally.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StarBattle {
+    size: usize,
+    stars: usize,
+    // The region of each cell, numbered from 0, by row
+    regions: Vec<usize>,
+    // Whether each cell holds a star, or None while undecided
+    cells: Vec<Option<bool>>,
+    // The cells of every row, column and region
+    units: Vec<Vec<usize>>,
+}
+
+impl StarBattle {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn region(&self, row: usize, col: usize) -> usize {
+        self.regions[row * self.size + col]
+    }
+
+    pub fn is_star(&self, row: usize, col: usize) -> bool {
+        self.cells[row * self.size + col] == Some(true)
+    }
+
+    // A unit can still end up with exactly the right number of stars
+    fn unit_allows(&self, unit: &[usize]) -> bool {
+        let stars = unit
+            .iter()
+            .filter(|&&cell| self.cells[cell] == Some(true))
+            .count();
+        let open = unit
+            .iter()
+            .filter(|&&cell| self.cells[cell].is_none())
+            .count();
+        stars <= self.stars && stars + open >= self.stars
+    }
+
+    fn touches_star(&self, index: usize) -> bool {
+        let (row, col) = (index / self.size, index % self.size);
+        (row.saturating_sub(1)..=(row + 1).min(self.size - 1)).any(|r| {
+            (col.saturating_sub(1)..=(col + 1).min(self.size - 1))
+                .any(|c| (r, c) != (row, col) && self.is_star(r, c))
+        })
+    }
+}
+
+impl Backtrack for StarBattle {
+    type Value = bool;
+
+    fn cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    fn options(&self, _index: usize) -> Option<Vec<bool>> {
+        Some(vec![false, true])
+    }
+
+    fn place(&mut self, index: usize, star: bool) -> bool {
+        self.cells[index] = Some(star);
+        if star && self.touches_star(index) {
+            return false;
+        }
+        let (row, col) = (index / self.size, index % self.size);
+        [row, self.size + col, 2 * self.size + self.regions[index]]
+            .iter()
+            .all(|&unit| self.unit_allows(&self.units[unit]))
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.cells[index] = None;
+    }
+}
+
+impl Puzzle for StarBattle {
+    // The first line gives the number of stars in each row, column and region. The grid follows
+    // with any letter or symbol for each cell, where cells sharing a symbol form a region.
+    fn build(contents: &mut String) -> Result<Self, Box<dyn Error>> {
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let stars = lines
+            .next()
+            .and_then(|line| line.trim().parse::<usize>().ok())
+            .filter(|&stars| stars > 0)
+            .ok_or("Failed to read puzzle. For Star Battle, the first line should be the number of stars in each row.")?;
+        let rows: Vec<Vec<char>> = lines
+            .map(|line| line.chars().filter(|c| !c.is_whitespace()).collect())
+            .collect();
+        let size = rows.len();
+        if size == 0 || rows.iter().any(|row| row.len() != size) {
+            return Err(Box::from(
+                "Failed to read puzzle. The Star Battle grid must be square.",
+            ));
+        }
+        let mut symbols: Vec<char> = Vec::new();
+        let regions: Vec<usize> = rows
+            .concat()
+            .into_iter()
+            .map(|symbol| match symbols.iter().position(|&s| s == symbol) {
+                Some(region) => region,
+                None => {
+                    symbols.push(symbol);
+                    symbols.len() - 1
+                }
+            })
+            .collect();
+        if symbols.len() != size {
+            return Err(Box::from(format!(
+                "Failed to read puzzle. A {0}x{0} grid needs {0} regions but {1} were found.",
+                size,
+                symbols.len()
+            )));
+        }
+        let mut units: Vec<Vec<usize>> = (0..size)
+            .map(|row| (0..size).map(|col| row * size + col).collect())
+            .collect();
+        units.extend((0..size).map(|col| (0..size).map(|row| row * size + col).collect()));
+        units.extend((0..size).map(|region| {
+            (0..size * size)
+                .filter(|&cell| regions[cell] == region)
+                .collect()
+        }));
+        Ok(StarBattle {
+            size,
+            stars,
+            regions,
+            cells: vec![None; size * size],
+            units,
+        })
+    }
+
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        Search::new().run(self, budget)
+    }
+
+    // Stars are drawn as * and every other cell as .
+    fn format(&self) -> String {
+        (0..self.size)
+            .map(|row| {
+                (0..self.size)
+                    .map(|col| if self.is_star(row, col) { "*" } else { "." })
+                    .collect::<Vec<&str>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
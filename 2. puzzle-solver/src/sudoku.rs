@@ -1,34 +1,48 @@
 use std::error::Error;
 
-use crate::Puzzle;
+use crate::{
+    budget::{Budget, Report, Tracker},
+    candidates::is_candidate_grid,
+    search::{Backtrack, Search},
+    Puzzle,
+};
 
 pub const SUDOKU_SIZE: u32 = 9;
+// Candidate mask with every digit from 1 to 9 still possible
+pub(crate) const ALL_CANDIDATES: u16 = 0b11_1111_1110;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct Sudoku {
     pub board: Vec<Vec<u32>>,
+    // Pencil marks for each cell read by row, with bit d set while digit d is still possible.
+    // Only empty cells use them.
+    pub(crate) candidates: Vec<u16>,
 }
 
 impl Sudoku {
-    fn safe_row(&self, row: usize, col: usize, candidate: u32) -> bool {
+    /// A Sudoku with the given digits and no pencil marks, so every empty cell may hold any digit.
+    pub fn from_board(board: Vec<Vec<u32>>) -> Sudoku {
+        Sudoku {
+            candidates: vec![ALL_CANDIDATES; board.len() * board.len()],
+            board,
+        }
+    }
+
+    pub(crate) fn safe_row(&self, row: usize, col: usize, candidate: u32) -> bool {
         let intersecting_val = self.board[row].iter().position(|&x| x == candidate);
         (Option::is_none(&intersecting_val)) || (intersecting_val == Some(col))
     }
 
-    fn safe_col(&self, row: usize, col: usize, candidate: u32) -> bool {
-        self.board
-            .iter()
-            .filter(|row| row[col] == candidate)
-            .count()
-            == 0
-            || self.board[row][col] == candidate
+    pub(crate) fn safe_col(&self, row: usize, col: usize, candidate: u32) -> bool {
+        let intersecting_val = self.board.iter().position(|line| line[col] == candidate);
+        (Option::is_none(&intersecting_val)) || (intersecting_val == Some(row))
     }
 
-    fn safe_matrix(&self, row: usize, col: usize, candidate: u32) -> bool {
+    pub(crate) fn safe_matrix(&self, row: usize, col: usize, candidate: u32) -> bool {
         let (start_row, start_col) = (row - row % 3, col - col % 3);
         for r in start_row..(start_row + 3) {
             for c in start_col..(start_col + 3) {
-                if self.board[r][c] == candidate && r != row && c != col {
+                if self.board[r][c] == candidate && (r, c) != (row, col) {
                     return false;
                 }
             }
@@ -43,7 +57,18 @@ impl Sudoku {
         row_safe && col_safe && matrix_safe
     }
 
-    fn is_valid(&self) -> bool {
+    /// Whether the digit at the given cell clashes with another digit in its row, column or box.
+    pub fn has_conflict(&self, row: usize, col: usize) -> bool {
+        let digit = self.board[row][col];
+        if digit == 0 {
+            return false;
+        }
+        let mut others = self.clone();
+        others.board[row][col] = 0;
+        !others.safe_space(row, col, digit)
+    }
+
+    pub(crate) fn is_valid(&self) -> bool {
         for row in 0..SUDOKU_SIZE as usize {
             for col in 0..SUDOKU_SIZE as usize {
                 let filled = self.board[row][col];
@@ -54,45 +79,56 @@ impl Sudoku {
         }
         true
     }
+}
 
-    // Backtracking Algorithm
-    fn fill_board(&mut self, mut row: usize, mut col: usize) -> bool {
-        let max = SUDOKU_SIZE as usize;
-        if col == max {
-            if row == max - 1 {
-                return true;
-            }
-            row += 1;
-            col = 0;
-        }
-        if self.board[row][col] > 0 {
-            return self.fill_board(row, col + 1);
+impl Backtrack for Sudoku {
+    type Value = u32;
+
+    fn cells(&self) -> usize {
+        SUDOKU_SIZE.pow(2) as usize
+    }
+
+    fn options(&self, index: usize) -> Option<Vec<u32>> {
+        let size = SUDOKU_SIZE as usize;
+        match self.board[index / size][index % size] {
+            0 => Some(
+                (1..=SUDOKU_SIZE)
+                    .filter(|digit| self.candidates[index] & 1 << digit != 0)
+                    .collect(),
+            ),
+            _ => None,
         }
-        for n in 1..=max {
-            let candidate = n as u32;
-            if self.safe_space(row, col, candidate) {
-                self.board[row][col] = candidate;
-                if self.fill_board(row, col + 1) {
-                    return true;
-                }
-            }
-            self.board[row][col] = 0;
+    }
+
+    fn place(&mut self, index: usize, candidate: u32) -> bool {
+        let (row, col) = (index / SUDOKU_SIZE as usize, index % SUDOKU_SIZE as usize);
+        let safe = self.safe_space(row, col, candidate);
+        if safe {
+            self.board[row][col] = candidate;
         }
-        false
+        safe
+    }
+
+    fn clear(&mut self, index: usize) {
+        let size = SUDOKU_SIZE as usize;
+        self.board[index / size][index % size] = 0;
     }
 }
 
 impl Puzzle for Sudoku {
     fn build(contents: &mut String) -> Result<Self, Box<dyn Error>> {
+        if is_candidate_grid(contents) {
+            return Sudoku::from_candidates(contents);
+        }
         contents.retain(|c| !c.is_whitespace());
         let digits: Vec<u32> = contents.chars().filter_map(|c| c.to_digit(10)).collect();
         if digits.len() == SUDOKU_SIZE.pow(2) as usize {
-            Ok(Sudoku {
-                board: digits
+            Ok(Sudoku::from_board(
+                digits
                     .chunks(9)
                     .map(|x| x.to_vec())
                     .collect::<Vec<Vec<u32>>>(),
-            })
+            ))
         } else {
             Err(Box::from(
                 "Failed to read puzzle. For Sudoku, ensure there are 81 digits total.",
@@ -100,8 +136,11 @@ impl Puzzle for Sudoku {
         }
     }
 
-    fn solve(&mut self) -> bool {
-        self.is_valid() && self.fill_board(0, 0)
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        match self.is_valid() {
+            true => Search::new().run(self, budget),
+            false => Tracker::new(budget).finish(Ok(false)),
+        }
     }
 
     fn format(&self) -> String {
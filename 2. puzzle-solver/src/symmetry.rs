@@ -0,0 +1,298 @@
+use std::{cmp::Ordering, collections::HashMap};
+
+use crate::{
+    rng::Rng,
+    sudoku::{Sudoku, SUDOKU_SIZE},
+};
+
+const SIZE: usize = SUDOKU_SIZE as usize;
+
+/// A relabelling of a Sudoku grid that maps valid grids to valid grids: an optional
+/// transposition, then a reordering of rows and columns, then a digit permutation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Transform {
+    pub transpose: bool,
+    // Row r of the result is taken from row rows[r] of the original
+    pub rows: [usize; SIZE],
+    pub cols: [usize; SIZE],
+    // digits[d] replaces digit d, and digits[0] must stay 0
+    pub digits: [u32; SIZE + 1],
+}
+
+impl Transform {
+    pub fn identity() -> Transform {
+        Transform {
+            transpose: false,
+            rows: std::array::from_fn(|i| i),
+            cols: std::array::from_fn(|i| i),
+            digits: std::array::from_fn(|i| i as u32),
+        }
+    }
+
+    pub fn transposition() -> Transform {
+        Transform {
+            transpose: true,
+            ..Transform::identity()
+        }
+    }
+
+    // A quarter turn clockwise
+    pub fn rotation() -> Transform {
+        Transform {
+            transpose: true,
+            cols: std::array::from_fn(|i| SIZE - 1 - i),
+            ..Transform::identity()
+        }
+    }
+
+    // Mirrors the grid left to right
+    pub fn reflection() -> Transform {
+        Transform {
+            cols: std::array::from_fn(|i| SIZE - 1 - i),
+            ..Transform::identity()
+        }
+    }
+
+    /// A random transform mixing every kind of symmetry: digit relabelling, band and stack
+    /// swaps, row and column swaps within them, rotation, reflection and transposition.
+    pub fn random(rng: &mut Rng) -> Transform {
+        let mut digits: [u32; SIZE + 1] = std::array::from_fn(|i| i as u32);
+        rng.shuffle(&mut digits[1..]);
+        let mut transform = Transform {
+            transpose: rng.chance(),
+            rows: random_line_order(rng),
+            cols: random_line_order(rng),
+            digits,
+        };
+        for _ in 0..rng.below(4) {
+            transform = transform.then(&Transform::rotation());
+        }
+        if rng.chance() {
+            transform = transform.then(&Transform::reflection());
+        }
+        transform
+    }
+
+    // Only band/stack swaps and swaps within a band or stack keep every box intact
+    pub fn is_valid(&self) -> bool {
+        let keeps_boxes = |order: &[usize; SIZE]| {
+            let mut seen = [false; SIZE];
+            order.chunks(3).all(|band| {
+                band.iter().all(|&line| {
+                    line < SIZE
+                        && line / 3 == band[0] / 3
+                        && !std::mem::replace(&mut seen[line], true)
+                })
+            })
+        };
+        let mut digits = self.digits;
+        digits.sort_unstable();
+        keeps_boxes(&self.rows)
+            && keeps_boxes(&self.cols)
+            && self.digits[0] == 0
+            && digits == std::array::from_fn(|i| i as u32)
+    }
+
+    // The transform that applies this one followed by `next`
+    pub fn then(&self, next: &Transform) -> Transform {
+        let (rows, cols) = match next.transpose {
+            true => (
+                std::array::from_fn(|i| self.cols[next.rows[i]]),
+                std::array::from_fn(|i| self.rows[next.cols[i]]),
+            ),
+            false => (
+                std::array::from_fn(|i| self.rows[next.rows[i]]),
+                std::array::from_fn(|i| self.cols[next.cols[i]]),
+            ),
+        };
+        Transform {
+            transpose: self.transpose != next.transpose,
+            rows,
+            cols,
+            digits: std::array::from_fn(|i| next.digits[self.digits[i] as usize]),
+        }
+    }
+
+    pub fn apply(&self, sudoku: &Sudoku) -> Sudoku {
+        let board = (0..SIZE)
+            .map(|row| {
+                (0..SIZE)
+                    .map(|col| {
+                        let (r, c) = (self.rows[row], self.cols[col]);
+                        let digit = match self.transpose {
+                            true => sudoku.board[c][r],
+                            false => sudoku.board[r][c],
+                        };
+                        self.digits[digit as usize]
+                    })
+                    .collect()
+            })
+            .collect();
+        let mut transformed = Sudoku::from_board(board);
+        for (index, mask) in transformed.candidates.iter_mut().enumerate() {
+            let (r, c) = (self.rows[index / SIZE], self.cols[index % SIZE]);
+            let original = match self.transpose {
+                true => sudoku.candidates[c * SIZE + r],
+                false => sudoku.candidates[r * SIZE + c],
+            };
+            *mask = (1..=SIZE)
+                .filter(|&digit| original & 1 << digit != 0)
+                .fold(0, |mask, digit| mask | 1 << self.digits[digit]);
+        }
+        transformed
+    }
+}
+
+fn random_line_order(rng: &mut Rng) -> [usize; SIZE] {
+    let mut bands = [0, 1, 2];
+    rng.shuffle(&mut bands);
+    let mut order = [0; SIZE];
+    for (position, band) in bands.iter().enumerate() {
+        let mut lines = [band * 3, band * 3 + 1, band * 3 + 2];
+        rng.shuffle(&mut lines);
+        order[position * 3..position * 3 + 3].copy_from_slice(&lines);
+    }
+    order
+}
+
+// Every ordering of the 9 lines that only permutes bands and the lines within each band
+fn line_orders() -> Vec<[usize; SIZE]> {
+    let triples = [
+        [0, 1, 2],
+        [0, 2, 1],
+        [1, 0, 2],
+        [1, 2, 0],
+        [2, 0, 1],
+        [2, 1, 0],
+    ];
+    let mut orders = Vec::with_capacity(1296);
+    for bands in triples {
+        for first in triples {
+            for second in triples {
+                for third in triples {
+                    let within = [first, second, third];
+                    orders.push(std::array::from_fn(|i| {
+                        bands[i / 3] * 3 + within[i / 3][i % 3]
+                    }));
+                }
+            }
+        }
+    }
+    orders
+}
+
+impl Sudoku {
+    /// The smallest grid, read row by row, among every grid equivalent to this one under
+    /// transposition, band/stack and row/column permutations and digit relabelling. Digits
+    /// are relabelled in order of first appearance, so two puzzles are equivalent exactly when
+    /// their canonical forms are equal. Pencil marks are not part of the canonical form.
+    pub fn canonical(&self) -> Sudoku {
+        let cells: Vec<u8> = self
+            .board
+            .iter()
+            .flatten()
+            .map(|&digit| digit as u8)
+            .collect();
+        let cell = |transpose: bool, r: usize, c: usize| match transpose {
+            true => cells[c * SIZE + r],
+            false => cells[r * SIZE + c],
+        };
+        let orders = line_orders();
+
+        // The first row only depends on which line leads and the column order, so only the
+        // combinations giving the smallest first row need their remaining rows explored
+        let mut best_first = [u8::MAX; SIZE];
+        let mut leaders = Vec::new();
+        for transpose in [false, true] {
+            for first in 0..SIZE {
+                for cols in &orders {
+                    let mut row = [0u8; SIZE];
+                    let line = cols.iter().map(|&col| cell(transpose, first, col));
+                    relabel(line, &mut row);
+                    match row.cmp(&best_first) {
+                        Ordering::Less => {
+                            best_first = row;
+                            leaders.clear();
+                            leaders.push((transpose, first, cols));
+                        }
+                        Ordering::Equal => leaders.push((transpose, first, cols)),
+                        Ordering::Greater => {}
+                    }
+                }
+            }
+        }
+
+        let mut best = [u8::MAX; SIZE * SIZE];
+        let mut candidate = [0u8; SIZE * SIZE];
+        for (transpose, first, cols) in leaders {
+            for rows in orders.iter().filter(|rows| rows[0] == first) {
+                let grid = (0..SIZE * SIZE)
+                    .map(|index| cell(transpose, rows[index / SIZE], cols[index % SIZE]));
+                if relabel_below(grid, &best, &mut candidate) {
+                    best = candidate;
+                }
+            }
+        }
+        Sudoku::from_board(
+            best.chunks(SIZE)
+                .map(|row| row.iter().map(|&digit| u32::from(digit)).collect())
+                .collect(),
+        )
+    }
+}
+
+// Numbers digits in order of first appearance, leaving empty cells as 0
+fn relabel(digits: impl Iterator<Item = u8>, out: &mut [u8]) {
+    let mut labels = [0u8; SIZE + 1];
+    for (index, digit) in digits.enumerate() {
+        out[index] = label(digit, &mut labels);
+    }
+}
+
+fn label(digit: u8, labels: &mut [u8; SIZE + 1]) -> u8 {
+    if digit != 0 && labels[digit as usize] == 0 {
+        labels[digit as usize] = labels[0] + 1;
+        // The unused slot for 0 tracks the last label handed out
+        labels[0] += 1;
+    }
+    match digit {
+        0 => 0,
+        _ => labels[digit as usize],
+    }
+}
+
+// Relabels a grid into `out`, returning whether it is smaller than `best`. Gives up as soon
+// as the grid is known to be larger.
+fn relabel_below(digits: impl Iterator<Item = u8>, best: &[u8], out: &mut [u8]) -> bool {
+    let mut labels = [0u8; SIZE + 1];
+    let mut ordering = Ordering::Equal;
+    for (index, digit) in digits.enumerate() {
+        out[index] = label(digit, &mut labels);
+        if ordering == Ordering::Equal {
+            ordering = out[index].cmp(&best[index]);
+            if ordering == Ordering::Greater {
+                return false;
+            }
+        }
+    }
+    ordering == Ordering::Less
+}
+
+/// For each puzzle, the index of the first earlier puzzle it is equivalent to.
+pub fn find_duplicates(puzzles: &[Sudoku]) -> Vec<Option<usize>> {
+    let mut first_seen: HashMap<Sudoku, usize> = HashMap::new();
+    puzzles
+        .iter()
+        .enumerate()
+        .map(|(index, puzzle)| {
+            let canonical = puzzle.canonical();
+            match first_seen.get(&canonical) {
+                Some(&original) => Some(original),
+                None => {
+                    first_seen.insert(canonical, index);
+                    None
+                }
+            }
+        })
+        .collect()
+}
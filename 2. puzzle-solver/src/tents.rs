@@ -0,0 +1,266 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, Report, Tracker},
+    search::{Backtrack, Search},
+    Puzzle,
+};
+
+/// A tree in a Tents grid.
+pub const TREE: char = 'T';
+/// A tent in a solved Tents grid.
+pub const TENT: char = 'A';
+
+/// A Tents grid. Every tree gets its own tent in a cell next to it (not diagonally), tents
+/// never touch each other, even diagonally, and the number of tents in each row and column
+/// matches its count.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tents {
+    height: usize,
+    width: usize,
+    row_counts: Vec<usize>,
+    col_counts: Vec<usize>,
+    trees: Vec<bool>,
+    // Whether each cell holds a tent, or None while undecided. Trees and cells with no tree
+    // beside them start out empty.
+    tents: Vec<Option<bool>>,
+}
+
+impl Tents {
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn cell(&self, row: usize, col: usize) -> char {
+        let index = row * self.width + col;
+        match (self.trees[index], self.tents[index]) {
+            (true, _) => TREE,
+            (_, Some(true)) => TENT,
+            _ => '.',
+        }
+    }
+
+    // The cells beside a cell, not counting diagonals
+    fn beside(&self, index: usize) -> Vec<usize> {
+        let (row, col, width) = (index / self.width, index % self.width, self.width);
+        let mut cells = Vec::with_capacity(4);
+        if row > 0 {
+            cells.push(index - width);
+        }
+        if row + 1 < self.height {
+            cells.push(index + width);
+        }
+        if col > 0 {
+            cells.push(index - 1);
+        }
+        if col + 1 < width {
+            cells.push(index + 1);
+        }
+        cells
+    }
+
+    fn touches_tent(&self, index: usize) -> bool {
+        let (row, col) = (index / self.width, index % self.width);
+        (row.saturating_sub(1)..=(row + 1).min(self.height - 1)).any(|r| {
+            (col.saturating_sub(1)..=(col + 1).min(self.width - 1))
+                .any(|c| (r, c) != (row, col) && self.tents[r * self.width + c] == Some(true))
+        })
+    }
+
+    // A line can still end up with exactly its count of tents
+    fn line_allows(&self, cells: impl Iterator<Item = usize>, count: usize) -> bool {
+        let (tents, open) = cells.fold((0, 0), |(tents, open), cell| match self.tents[cell] {
+            Some(true) => (tents + 1, open),
+            Some(false) => (tents, open),
+            None => (tents, open + 1),
+        });
+        tents <= count && tents + open >= count
+    }
+
+    // A tree still has a cell beside it where its tent could go
+    fn tree_allows(&self, tree: usize) -> bool {
+        self.beside(tree)
+            .iter()
+            .any(|&cell| self.tents[cell] != Some(false))
+    }
+
+    // Checks a grid with every cell decided, including lines that the search never touched
+    fn is_solved(&self) -> bool {
+        let width = self.width;
+        (0..self.height)
+            .all(|row| self.line_allows(row * width..(row + 1) * width, self.row_counts[row]))
+            && (0..width).all(|col| {
+                self.line_allows(
+                    (0..self.height).map(|r| r * width + col),
+                    self.col_counts[col],
+                )
+            })
+            && self.trees_paired()
+    }
+
+    // Pairs every tree with a different tent beside it, using augmenting paths
+    fn trees_paired(&self) -> bool {
+        let trees: Vec<usize> = (0..self.trees.len()).filter(|&i| self.trees[i]).collect();
+        let tents = self
+            .tents
+            .iter()
+            .filter(|&&tent| tent == Some(true))
+            .count();
+        if tents != trees.len() {
+            return false;
+        }
+        let mut partner: Vec<Option<usize>> = vec![None; self.tents.len()];
+        trees.iter().all(|&tree| {
+            let mut seen = vec![false; self.tents.len()];
+            self.pair(tree, &mut partner, &mut seen)
+        })
+    }
+
+    fn pair(&self, tree: usize, partner: &mut Vec<Option<usize>>, seen: &mut Vec<bool>) -> bool {
+        for tent in self.beside(tree) {
+            if self.tents[tent] != Some(true) || seen[tent] {
+                continue;
+            }
+            seen[tent] = true;
+            let free = match partner[tent] {
+                None => true,
+                Some(other) => self.pair(other, partner, seen),
+            };
+            if free {
+                partner[tent] = Some(tree);
+                return true;
+            }
+        }
+        false
+    }
+}
+
+impl Backtrack for Tents {
+    type Value = bool;
+
+    fn cells(&self) -> usize {
+        self.tents.len()
+    }
+
+    fn options(&self, index: usize) -> Option<Vec<bool>> {
+        match self.tents[index] {
+            None => Some(vec![false, true]),
+            Some(_) => None,
+        }
+    }
+
+    fn place(&mut self, index: usize, tent: bool) -> bool {
+        self.tents[index] = Some(tent);
+        if tent && self.touches_tent(index) {
+            return false;
+        }
+        let (row, col, width) = (index / self.width, index % self.width, self.width);
+        let lines = self.line_allows(row * width..(row + 1) * width, self.row_counts[row])
+            && self.line_allows(
+                (0..self.height).map(|r| r * width + col),
+                self.col_counts[col],
+            );
+        let trees = self
+            .beside(index)
+            .iter()
+            .all(|&cell| !self.trees[cell] || self.tree_allows(cell));
+        if !lines || !trees {
+            return false;
+        }
+        match self.tents.contains(&None) {
+            true => true,
+            false => self.is_solved(),
+        }
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.tents[index] = None;
+    }
+}
+
+impl Puzzle for Tents {
+    // The first line lists the tent count of every column. Each following line is a row: its
+    // tent count, then its cells with T for trees and . for open ground.
+    fn build(contents: &mut String) -> Result<Self, Box<dyn Error>> {
+        let counts = |text: &str| {
+            text.split(|c: char| c == ',' || c.is_whitespace())
+                .filter(|count| !count.is_empty())
+                .map(|count| count.parse::<usize>().ok())
+                .collect::<Option<Vec<usize>>>()
+        };
+        let mut lines = contents.lines().filter(|line| !line.trim().is_empty());
+        let col_counts = lines.next().and_then(counts).ok_or(
+            "Failed to read puzzle. For Tents, the first line should list the tent count of each column.",
+        )?;
+        let (mut row_counts, mut cells, mut row_lengths) = (Vec::new(), Vec::new(), Vec::new());
+        for (row, line) in lines.enumerate() {
+            let line = line.trim();
+            let (count, row_cells) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            row_counts.push(count.parse::<usize>().map_err(|_| {
+                format!(
+                    "Failed to read puzzle. Row {} should start with its tent count.",
+                    row + 1
+                )
+            })?);
+            for c in row_cells.chars().filter(|c| !c.is_whitespace()) {
+                match c.to_ascii_uppercase() {
+                    TREE => cells.push(true),
+                    '.' | '_' => cells.push(false),
+                    c => {
+                        return Err(Box::from(format!(
+                        "Row {} has an invalid cell \"{}\". Use T for trees and . for open ground.",
+                        row + 1,
+                        c
+                    )))
+                    }
+                }
+            }
+            row_lengths.push(cells.len() - row_lengths.iter().sum::<usize>());
+        }
+        let (height, width) = (row_counts.len(), col_counts.len());
+        if height == 0 || width == 0 || row_lengths.iter().any(|&length| length != width) {
+            return Err(Box::from(format!(
+                "Failed to read puzzle. The column counts give {} columns, but the rows do not all have that many cells.",
+                width
+            )));
+        }
+        let mut tents = Tents {
+            height,
+            width,
+            row_counts,
+            col_counts,
+            tents: vec![None; cells.len()],
+            trees: cells,
+        };
+        for index in 0..tents.tents.len() {
+            if tents.trees[index] || !tents.beside(index).iter().any(|&cell| tents.trees[cell]) {
+                tents.tents[index] = Some(false);
+            }
+        }
+        Ok(tents)
+    }
+
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        // With nothing left to decide the search would accept the grid without checking it
+        match self.tents.contains(&None) {
+            true => Search::new().run(self, budget),
+            false => Tracker::new(budget).finish(Ok(self.is_solved())),
+        }
+    }
+
+    fn format(&self) -> String {
+        (0..self.height)
+            .map(|row| {
+                (0..self.width)
+                    .map(|col| self.cell(row, col).to_string())
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+}
@@ -1,11 +1,106 @@
 #[cfg(test)]
+#[allow(clippy::module_inception)]
 mod tests {
 
-    use std::vec;
+    use std::{env, fs, thread, time::Duration, vec};
 
-    use crate::{run, sudoku::Sudoku, sudoku_puzzle, Command, Puzzle};
+    use crate::{
+        build_runs,
+        config::OutputFormat,
+        count_solutions, dedupe_collection, draw_digit, generate_nonogram, generate_sudoku,
+        inflate::zlib_decompress,
+        nonogram::Nonogram,
+        nonogram_puzzle, nonogram_variables,
+        play::play,
+        recognize_sudoku, run, run_with, solve_cnf, solve_with_sat,
+        sudoku::Sudoku,
+        sudoku_puzzle, transform_sudoku,
+        watch::{detect_puzzle, Watcher},
+        Backend, Backtrack, Binairo, Budget, CancelToken, Cnf, Command, Config, Crossword, Encode,
+        Gattai, GiveUp, GrayImage, Mode, Outcome, Puzzle, PuzzleType, Rng, Search, Slitherlink,
+        StarBattle, Tents, Transform, Unit, Violation, WordSearch, FILLED, UNKNOWN,
+    };
+
+    const SOLVABLE_SUDOKU: &str = "600 079 032 000 060 500 209 008 700
+                                   900 305 001 850 000 300 473 001 250
+                                   042 680 900 000 013 427 090 200 600";
+
+    // A single long line of bits where each cell must differ from the one before it
+    struct Alternating {
+        bits: Vec<Option<bool>>,
+    }
+
+    impl Backtrack for Alternating {
+        type Value = bool;
+
+        fn cells(&self) -> usize {
+            self.bits.len()
+        }
+
+        fn options(&self, _index: usize) -> Option<Vec<bool>> {
+            Some(vec![false, true])
+        }
+
+        fn place(&mut self, index: usize, value: bool) -> bool {
+            self.bits[index] = Some(value);
+            index == 0 || self.bits[index - 1] != Some(value)
+        }
+
+        fn clear(&mut self, index: usize) {
+            self.bits[index] = None;
+        }
+    }
+
+    fn play_lines(puzzle: PuzzleType, contents: &str, save_path: &str, input: &str) -> String {
+        let mut output: Vec<u8> = Vec::new();
+        play(
+            &puzzle,
+            contents,
+            save_path.to_string(),
+            input.as_bytes(),
+            &mut output,
+        )
+        .unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    // A random solved Sudoku, made by shuffling the first solution of an empty grid
+    fn random_solution(rng: &mut Rng) -> Sudoku {
+        let mut sudoku = Sudoku::build(&mut "0".repeat(81)).unwrap();
+        assert!(sudoku.solve());
+        Transform::random(rng).apply(&sudoku)
+    }
+
+    // Checks every row, column and box independently of the solver's own checks
+    fn satisfies_constraints(sudoku: &Sudoku) -> bool {
+        let complete = |cells: Vec<u32>| {
+            let mut digits = cells.clone();
+            digits.sort_unstable();
+            digits == (1..=9).collect::<Vec<u32>>()
+        };
+        (0..9).all(|i| {
+            complete(sudoku.board[i].clone())
+                && complete((0..9).map(|row| sudoku.board[row][i]).collect())
+                && complete(
+                    (0..9)
+                        .map(|j| sudoku.board[i / 3 * 3 + j / 3][i % 3 * 3 + j % 3])
+                        .collect(),
+                )
+        })
+    }
+
+    fn nonogram_grid(nonogram: &Nonogram) -> Vec<Vec<bool>> {
+        (0..nonogram.height())
+            .map(|row| {
+                (0..nonogram.width())
+                    .map(|col| nonogram.cell(row, col) == FILLED)
+                    .collect()
+            })
+            .collect()
+    }
 
     #[test]
+    #[allow(clippy::zero_repeat_side_effects)]
     fn test_empty_command() {
         let args = [String::new(); 0];
         let command = Command::build(args.into_iter());
@@ -19,15 +114,18 @@ mod tests {
         let result = run(Command {
             puzzle: "foobar".to_string(),
             filename: "foobar.txt".to_string(),
+            ..Default::default()
         })
         .unwrap_err();
         assert_eq!(result.to_string(), "The specified puzzle is not supported.")
     }
     #[test]
+    #[allow(clippy::assertions_on_constants)]
     fn test_file_not_found() {
         match run(Command {
             puzzle: "sudoku".to_string(),
             filename: "".to_string(),
+            ..Default::default()
         }) {
             Ok(_) => assert!(false),
             Err(_) => assert!(true),
@@ -45,7 +143,7 @@ mod tests {
     #[test]
     fn test_unsolvable_sudoku() {
         let mut contents = String::from_utf8(vec![b'3'; 81]).unwrap();
-        let status = sudoku_puzzle(&mut contents).unwrap_err();
+        let status = sudoku_puzzle(&mut contents, &Command::default()).unwrap_err();
         assert_eq!(status.to_string(), "The given sudoku could not be solved.")
     }
     #[test]
@@ -70,4 +168,1692 @@ mod tests {
         assert!(sudoku.solve());
         assert_eq!(sudoku.board, solution)
     }
+    #[test]
+    fn test_budget_flags() {
+        let args = [
+            "puzzle-solver",
+            "sudoku",
+            "--timeout",
+            "1.5",
+            "--max-nodes",
+            "500",
+            "--stats",
+        ];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(
+            command,
+            Command {
+                mode: Mode::Solve,
+                puzzle: "sudoku".to_string(),
+                filename: "sudoku.txt".to_string(),
+                timeout: Some(Duration::from_millis(1500)),
+                max_nodes: Some(500),
+                stats: true,
+                ..Default::default()
+            }
+        )
+    }
+    #[test]
+    fn test_bad_budget_flag() {
+        let args = ["puzzle-solver", "sudoku", "--max-nodes", "lots"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            command,
+            Err("--max-nodes requires a positive whole number.")
+        )
+    }
+    #[test]
+    fn test_interval_too_large() {
+        let args = ["puzzle-solver", "watch", "--interval", "1e30"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            command,
+            Err("--interval requires a positive number of seconds.")
+        )
+    }
+    #[test]
+    fn test_timeout_too_large() {
+        let args = ["puzzle-solver", "sudoku", "--timeout", "1e30"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(
+            command,
+            Err("--timeout requires a positive number of seconds.")
+        )
+    }
+    #[test]
+    fn test_node_limit() {
+        let mut contents = "0".repeat(81);
+        let mut sudoku = Sudoku::build(&mut contents).unwrap();
+        let report = sudoku.solve_within(&Budget::unlimited().with_max_nodes(10));
+        assert_eq!(report.outcome, Outcome::GaveUp(GiveUp::NodeLimit));
+        assert_eq!(report.stats.nodes, 10)
+    }
+    #[test]
+    fn test_node_limit_message() {
+        let mut contents = "0".repeat(81);
+        let command = Command {
+            max_nodes: Some(10),
+            ..Default::default()
+        };
+        let status = sudoku_puzzle(&mut contents, &command).unwrap_err();
+        assert!(status
+            .to_string()
+            .starts_with("Gave up on the given sudoku (node limit reached) after 10 nodes"))
+    }
+    #[test]
+    fn test_timeout() {
+        let mut contents = "0".repeat(81);
+        let mut sudoku = Sudoku::build(&mut contents).unwrap();
+        let report = sudoku.solve_within(&Budget::unlimited().with_timeout(Duration::ZERO));
+        assert_eq!(report.outcome, Outcome::GaveUp(GiveUp::Timeout))
+    }
+    #[test]
+    fn test_cancelled_solve() {
+        // The last row needs a 9 in its final cell, which the top row already blocks. The
+        // contradiction is only found after exhausting every way to fill the rows in between.
+        let mut contents = String::from("000000009") + &"0".repeat(63) + "123456780";
+        let mut sudoku = Sudoku::build(&mut contents).unwrap();
+        let cancel = CancelToken::new();
+        let budget = Budget::unlimited().with_cancel(cancel.clone());
+        let canceller = thread::spawn(move || {
+            thread::sleep(Duration::from_millis(50));
+            cancel.cancel();
+        });
+        let report = sudoku.solve_within(&budget);
+        canceller.join().unwrap();
+        assert_eq!(report.outcome, Outcome::GaveUp(GiveUp::Cancelled))
+    }
+    #[test]
+    fn test_sudoku_stats() {
+        let mut sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let report = sudoku.solve_within(&Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        assert_eq!(report.stats.max_depth, 81);
+        assert!(report.stats.nodes > 81);
+        assert!(report.stats.propagations >= report.stats.nodes - 81);
+        assert!(report.stats.backtracks < report.stats.nodes)
+    }
+    #[test]
+    fn test_solvable_nonogram() {
+        let mut contents = String::from(
+        "5,4 1,1,3 2,2 1,2 1,2 9 3 4,1 2,1 2,3,1\n1,1 2,5 1,1,5 8 1,1 2,1 1,2,3 2,1,1 4,1 4,1,1",
+    );
+        let mut nonogram = Nonogram::build(&mut contents).unwrap();
+        let report = nonogram.solve_within(&Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        assert!(report.stats.backtracks > 0);
+        assert_eq!(
+            nonogram.format().lines().next(),
+            Some(
+                "\u{25A0}\u{25A1}\u{25A1}\u{25A1}\u{25A1}\u{25A1}\u{25A1}\u{25A0}\u{25A1}\u{25A1}"
+            )
+        )
+    }
+    #[test]
+    fn test_play_command() {
+        let args = ["puzzle-solver", "play", "nonogram"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(command.mode, Mode::Play);
+        assert_eq!(command.filename, "nonogram.txt")
+    }
+    #[test]
+    fn test_play_conflict_and_undo() {
+        // The 6 clashes with the given 6 at the start of the row
+        let output = play_lines(PuzzleType::Sudoku, SOLVABLE_SUDOKU, "", "d6\n");
+        let last_screen = output.rsplit("> ").nth(1).unwrap();
+        assert!(last_screen.contains("\x1b[31m\x1b[7m6"));
+        let output = play_lines(PuzzleType::Sudoku, SOLVABLE_SUDOKU, "", "d6u\n");
+        let last_screen = output.rsplit("> ").nth(1).unwrap();
+        assert!(!last_screen.contains("\x1b[31m"));
+        let output = play_lines(PuzzleType::Sudoku, SOLVABLE_SUDOKU, "", "d6ur\n");
+        let last_screen = output.rsplit("> ").nth(1).unwrap();
+        assert!(last_screen.contains("\x1b[31m\x1b[7m6"))
+    }
+    #[test]
+    fn test_play_locked_cell() {
+        let output = play_lines(PuzzleType::Sudoku, SOLVABLE_SUDOKU, "", "5\n");
+        assert!(output.contains("That cell is part of the original puzzle."))
+    }
+    #[test]
+    fn test_play_hint_and_save() {
+        let path = env::temp_dir().join("puzzle_solver_play_hint.progress");
+        let path = path.to_string_lossy().to_string();
+        play_lines(PuzzleType::Sudoku, SOLVABLE_SUDOKU, &path, "dh\nsave\nq\n");
+        let saved = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        let (_, progress) = saved.split_once("\nprogress\n").unwrap();
+        assert!(progress.starts_with("[6, 8, 0,"));
+
+        // Loading the saved game restores the hinted digit without making it a given
+        let output = play_lines(PuzzleType::Sudoku, &saved, "", "dc\n");
+        let last_screen = output.rsplit("> ").nth(1).unwrap();
+        assert!(
+            last_screen.starts_with("+-------+-------+-------+\n| \x1b[1m6\x1b[0m \x1b[7m.\x1b[0m")
+        )
+    }
+    #[test]
+    fn test_play_nonogram_complete() {
+        let solution = ["\u{25A0}\u{25A0}", "\u{25A0}\u{25A1}"];
+        let contents = format!("2 1\n2 1\nprogress\n{}", solution.join("\n"));
+        let output = play_lines(PuzzleType::Nonogram, &contents, "", "q\n");
+        assert!(output.contains("Puzzle complete!"));
+        let output = play_lines(PuzzleType::Nonogram, "2 1\n2 1", "", "sfdf\n");
+        let last_screen = output.rsplit("> ").nth(1).unwrap();
+        assert!(last_screen.contains("\x1b[31m"));
+        assert!(!last_screen.contains("Puzzle complete!"))
+    }
+    #[test]
+    fn test_deep_search() {
+        // Far deeper than a recursive solver could go without overflowing the stack
+        let mut puzzle = Alternating {
+            bits: vec![None; 1_000_000],
+        };
+        let report = Search::new().run(&mut puzzle, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        assert_eq!(report.stats.max_depth, 1_000_000);
+        assert_eq!(puzzle.bits[999_999], Some(true))
+    }
+    #[test]
+    fn test_pause_and_resume() {
+        let mut contents = SOLVABLE_SUDOKU.to_string();
+        let mut expected = Sudoku::build(&mut contents.clone()).unwrap();
+        let full_report = expected.solve_within(&Budget::unlimited());
+
+        let mut sudoku = Sudoku::build(&mut contents).unwrap();
+        let mut search = Search::new();
+        let budget = Budget::unlimited().with_max_nodes(100);
+        let mut pauses = 0;
+        while search.run(&mut sudoku, &budget).outcome == Outcome::GaveUp(GiveUp::NodeLimit) {
+            pauses += 1;
+        }
+        assert!(pauses > 1);
+        assert_eq!(sudoku, expected);
+        assert_eq!(search.stats().nodes, full_report.stats.nodes);
+        assert_eq!(search.stats().backtracks, full_report.stats.backtracks)
+    }
+    #[test]
+    fn test_search_snapshot() {
+        let mut sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let mut search = Search::new();
+        let budget = Budget::unlimited().with_max_nodes(200);
+        search.run(&mut sudoku, &budget);
+        let (mut snapshot, mut snapshot_search) = (sudoku.clone(), search.clone());
+        assert!(snapshot_search.depth() > 0);
+
+        search.run(&mut sudoku, &Budget::unlimited());
+        let report = snapshot_search.run(&mut snapshot, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        assert_eq!(snapshot, sudoku)
+    }
+    #[test]
+    fn test_search_next_solution() {
+        let mut puzzle = Alternating {
+            bits: vec![None; 4],
+        };
+        let mut search = Search::new();
+        let mut solutions = Vec::new();
+        while search.run(&mut puzzle, &Budget::unlimited()).outcome == Outcome::Solved {
+            solutions.push(puzzle.bits.clone());
+        }
+        assert_eq!(solutions.len(), 2);
+        assert_eq!(
+            solutions[0],
+            vec![Some(false), Some(true), Some(false), Some(true)]
+        );
+        assert_eq!(
+            search.run(&mut puzzle, &Budget::unlimited()).outcome,
+            Outcome::Unsolvable
+        )
+    }
+    #[test]
+    fn test_sat_sudoku() {
+        let mut searched = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        searched.solve();
+        let mut sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let report = solve_with_sat(&mut sudoku, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        assert_eq!(sudoku, searched)
+    }
+    #[test]
+    fn test_sat_unsolvable_sudoku() {
+        let mut contents = String::from("11") + &"0".repeat(79);
+        let mut sudoku = Sudoku::build(&mut contents).unwrap();
+        let report = solve_with_sat(&mut sudoku, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Unsolvable)
+    }
+    #[test]
+    fn test_sat_nonogram() {
+        let mut contents = String::from(
+        "5,4 1,1,3 2,2 1,2 1,2 9 3 4,1 2,1 2,3,1\n1,1 2,5 1,1,5 8 1,1 2,1 1,2,3 2,1,1 4,1 4,1,1",
+    );
+        let mut nonogram = Nonogram::build(&mut contents).unwrap();
+        let report = solve_with_sat(&mut nonogram, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        assert!(nonogram.is_complete())
+    }
+    #[test]
+    fn test_dimacs_round_trip() {
+        let sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let cnf = sudoku.encode();
+        let dimacs = cnf.to_dimacs();
+        assert!(dimacs.starts_with("p cnf 729 "));
+        let parsed = Cnf::from_dimacs(&format!("c exported sudoku\n{}", dimacs)).unwrap();
+        assert_eq!(parsed, cnf);
+
+        let (report, model) = solve_cnf(&parsed, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        let mut decoded = sudoku.clone();
+        decoded.decode(&model.unwrap());
+        assert!(decoded.board.iter().flatten().all(|&digit| digit != 0));
+        assert!(decoded.solve())
+    }
+    #[test]
+    fn test_bad_dimacs() {
+        assert!(Cnf::from_dimacs("1 -2 0").is_err());
+        assert!(Cnf::from_dimacs("p cnf 2 1\n1 -3 0").is_err());
+        let cnf = Cnf::from_dimacs("p cnf 2 2\n1 0\n-1 0").unwrap();
+        let (report, model) = solve_cnf(&cnf, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Unsolvable);
+        assert_eq!(model, None)
+    }
+    #[test]
+    fn test_solve_cnf_file() {
+        let args = ["puzzle-solver", "cnf", "-", "--output", "-"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut output = Vec::new();
+        let formula = "c a small formula\np cnf 3 3\n1 2 0\n-1 0\n-2 3 0\n";
+        run_with(command, formula.as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), "v -1 2 3 0\n");
+
+        let args = ["puzzle-solver", "dimacs", "-", "--output", "-"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        let result = run_with(command, "p cnf 1 2\n1 0\n-1 0\n".as_bytes(), Vec::new());
+        assert!(result.is_err())
+    }
+    #[test]
+    fn test_large_nonogram_encoding() {
+        // Lines of many short runs have far too many layouts to list one by one
+        let line = vec!["1"; 15].join(",");
+        let clues = vec![line.as_str(); 45].join(" ");
+        let nonogram = Nonogram::build(&mut format!("{}\n{}", clues, clues)).unwrap();
+        let cnf = nonogram.encode();
+        assert_eq!(cnf.variables, nonogram_variables(&nonogram));
+        assert!(cnf.clauses.len() < 200_000)
+    }
+    #[test]
+    fn test_backend_flags() {
+        let args = [
+            "puzzle-solver",
+            "sudoku",
+            "--backend",
+            "SAT",
+            "--dimacs",
+            "out.cnf",
+        ];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(command.backend, Backend::Sat);
+        assert_eq!(command.dimacs, Some("out.cnf".to_string()));
+        let args = ["puzzle-solver", "sudoku", "--backend", "magic"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(command, Err("--backend must be either search or sat."))
+    }
+    #[test]
+    fn test_canonical_form() {
+        let sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        // Rotate a quarter turn, swap the first two bands and relabel every digit
+        let transform = Transform {
+            transpose: true,
+            rows: [3, 4, 5, 0, 1, 2, 6, 7, 8],
+            cols: [8, 7, 6, 5, 4, 3, 2, 1, 0],
+            digits: [0, 9, 1, 8, 2, 7, 3, 6, 4, 5],
+        };
+        let transformed = transform.apply(&sudoku);
+        assert_ne!(transformed, sudoku);
+        let canonical = sudoku.canonical();
+        assert_eq!(transformed.canonical(), canonical);
+        assert_eq!(canonical.canonical(), canonical);
+
+        let mut different = sudoku.clone();
+        different.board[0][1] = 8;
+        assert_ne!(different.canonical(), canonical)
+    }
+    #[test]
+    fn test_dedupe_collection() {
+        const ESCARGOT: &str =
+            "100007090030020008009600500005300900010080002600004000300000010040000007007000300";
+        let sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let line = |sudoku: &Sudoku| -> String {
+            sudoku
+                .board
+                .iter()
+                .flatten()
+                .map(|digit| digit.to_string())
+                .collect()
+        };
+        let relabelled = Transform {
+            digits: [0, 2, 3, 4, 5, 6, 7, 8, 9, 1],
+            ..Transform::identity()
+        };
+        let contents = format!(
+            "{}\n\n{}\n{}\n",
+            line(&sudoku),
+            ESCARGOT,
+            line(&relabelled.apply(&sudoku)).replace('0', ".")
+        );
+        let (report, unique) = dedupe_collection(&contents).unwrap();
+        assert_eq!(
+            report,
+            "Line 4 is equivalent to line 1\n3 puzzles read, 1 duplicates found."
+        );
+        assert_eq!(unique, vec![line(&sudoku), ESCARGOT.to_string()]);
+        assert_eq!(
+            dedupe_collection("123\n").unwrap_err().to_string(),
+            "Line 1 is not an 81-digit Sudoku."
+        )
+    }
+    #[test]
+    fn test_dedupe_command() {
+        let args = ["puzzle-solver", "dedupe", "archive.txt", "--remove"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(command.mode, Mode::Dedupe);
+        assert_eq!(command.filename, "archive.txt");
+        assert!(command.remove)
+    }
+    #[test]
+    fn test_random_transforms_are_valid() {
+        let mut rng = Rng::new(7);
+        for _ in 0..100 {
+            assert!(Transform::random(&mut rng).is_valid())
+        }
+        let mixes_bands = Transform {
+            rows: [0, 1, 3, 2, 4, 5, 6, 7, 8],
+            ..Transform::identity()
+        };
+        assert!(!mixes_bands.is_valid())
+    }
+    #[test]
+    fn test_transform_composition() {
+        let sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let mut rng = Rng::new(11);
+        for _ in 0..20 {
+            let first = Transform::random(&mut rng);
+            let second = Transform::random(&mut rng);
+            assert_eq!(
+                first.then(&second).apply(&sudoku),
+                second.apply(&first.apply(&sudoku))
+            )
+        }
+        let mut turned = sudoku.clone();
+        for _ in 0..4 {
+            turned = Transform::rotation().apply(&turned);
+        }
+        assert_eq!(turned, sudoku)
+    }
+    #[test]
+    fn test_transform_preserves_solution() {
+        let mut sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let transform = Transform::random(&mut Rng::new(3));
+        let mut transformed = transform.apply(&sudoku);
+        assert!(sudoku.solve());
+        let mut search = Search::new();
+        let report = search.run(&mut transformed, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        assert_eq!(transformed, transform.apply(&sudoku));
+        // The original has a unique solution, so the variant must too
+        let report = search.run(&mut transformed, &Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Unsolvable)
+    }
+    #[test]
+    fn test_transform_seed() {
+        let sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let variants = transform_sudoku(&sudoku, 42, 5);
+        assert_eq!(variants.len(), 5);
+        assert_eq!(variants, transform_sudoku(&sudoku, 42, 5));
+        assert_ne!(variants, transform_sudoku(&sudoku, 43, 5));
+        for variant in variants {
+            assert_eq!(variant.canonical(), sudoku.canonical())
+        }
+    }
+    #[test]
+    fn test_transform_command() {
+        let args = ["puzzle-solver", "transform", "--seed", "9", "--count", "3"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(command.mode, Mode::Transform);
+        assert_eq!(command.filename, "sudoku.txt");
+        assert_eq!((command.seed, command.count), (Some(9), Some(3)));
+        let args = ["puzzle-solver", "transform", "--count", "0"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string()));
+        assert_eq!(command, Err("--count requires a positive whole number."))
+    }
+    #[test]
+    fn test_nonogram_line_logic() {
+        let contents = fs::read_to_string("benches/corpus/nonogram_readme.txt").unwrap();
+        let mut nonogram = Nonogram::build(&mut contents.clone()).unwrap();
+        let report = nonogram.solve_by_logic(&Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Solved);
+        assert!(report.stats.propagations > 0);
+        let mut searched = Nonogram::build(&mut contents.clone()).unwrap();
+        assert!(searched.solve());
+        assert_eq!(nonogram.format(), searched.format())
+    }
+    #[test]
+    fn test_nonogram_needs_guess() {
+        // Two diagonals both fit, so no cell can be decided without guessing
+        let mut nonogram = Nonogram::build(&mut "1 1\n1 1".to_string()).unwrap();
+        let report = nonogram.solve_by_logic(&Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::GaveUp(GiveUp::NeedsGuess));
+        assert_eq!(nonogram.format(), "??\n??");
+
+        // The top rows are forced, which leaves a square with two possible diagonals below
+        let mut contents = "1 1 1,1 1,1\n4 0 1 1".to_string();
+        let mut nonogram = Nonogram::build(&mut contents).unwrap();
+        let report = nonogram.solve_by_logic(&Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::GaveUp(GiveUp::NeedsGuess));
+        assert_eq!(nonogram.format(), "■■■■\n□□□□\n□□??\n□□??");
+
+        let mut nonogram = Nonogram::build(&mut "2 2\n1 1".to_string()).unwrap();
+        let report = nonogram.solve_by_logic(&Budget::unlimited());
+        assert_eq!(report.outcome, Outcome::Unsolvable)
+    }
+    #[test]
+    fn test_logic_only_command() {
+        let args = ["puzzle-solver", "nonogram", "--logic-only"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert!(command.logic_only);
+        let partial = nonogram_puzzle(&mut "1 1\n1 1".to_string(), &command).unwrap();
+        assert!(partial.contains(UNKNOWN))
+    }
+    #[test]
+    fn test_pipeline_solve() {
+        let args = ["puzzle-solver", "sudoku", "-", "--output", "-"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut output = Vec::new();
+        run_with(command, SOLVABLE_SUDOKU.as_bytes(), &mut output).unwrap();
+        let mut expected = Sudoku::read_from(SOLVABLE_SUDOKU.as_bytes()).unwrap();
+        assert!(expected.solve());
+        let mut written = Vec::new();
+        expected.write_to(&mut written).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            String::from_utf8(written).unwrap()
+        )
+    }
+    #[test]
+    fn test_pipeline_transform_and_dedupe() {
+        let args = [
+            "puzzle-solver",
+            "transform",
+            "-",
+            "--seed",
+            "5",
+            "--count",
+            "3",
+        ];
+        let mut command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        command.output = Some("-".to_string());
+        let mut variants = Vec::new();
+        run_with(command, SOLVABLE_SUDOKU.as_bytes(), &mut variants).unwrap();
+        assert_eq!(variants.iter().filter(|&&byte| byte == b'\n').count(), 3);
+
+        let args = ["puzzle-solver", "dedupe", "-", "--remove", "--output", "-"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut unique = Vec::new();
+        run_with(command, variants.as_slice(), &mut unique).unwrap();
+        assert_eq!(unique.iter().filter(|&&byte| byte == b'\n').count(), 1)
+    }
+    #[test]
+    fn test_play_from_stdin() {
+        let args = ["puzzle-solver", "play", "sudoku", "-"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        let result = run_with(command, "q\n".as_bytes(), Vec::new()).unwrap_err();
+        assert_eq!(
+            result.to_string(),
+            "Play mode needs the puzzle in a file, not stdin."
+        )
+    }
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_json_round_trip() {
+        use crate::Json;
+        let text = r#"{"input": "a\"b\\c\né😀", "list": [1, -2.5e1, true, null], "empty": {}}"#;
+        let json = Json::parse(text).unwrap();
+        assert_eq!(
+            json.get("input").and_then(Json::as_str),
+            Some("a\"b\\c\né😀")
+        );
+        assert_eq!(
+            json.to_string(),
+            r#"{"input":"a\"b\\c\né😀","list":[1,-25,true,null],"empty":{}}"#
+        );
+        assert_eq!(Json::parse(&json.to_string()).unwrap(), json);
+        assert!(Json::parse("[1, 2").is_err());
+        assert!(Json::parse("{\"a\": 1} x").is_err());
+        assert!(Json::parse(&"[".repeat(1000)).is_err())
+    }
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_server_requests() {
+        use crate::{handle_request, Json, ServerConfig};
+        let config = ServerConfig::default();
+        let response = handle_request("POST", "/solve/sudoku", None, SOLVABLE_SUDOKU, &config);
+        assert_eq!(response.status, 200);
+        assert_eq!(
+            response.body.get("outcome"),
+            Some(&Json::String("solved".to_string()))
+        );
+        assert_eq!(response.body.get("unique"), Some(&Json::Bool(true)));
+        let Some(Json::Array(rows)) = response.body.get("solution") else {
+            panic!("missing solution")
+        };
+        assert_eq!(rows.len(), 9);
+
+        // Both diagonals fit this nonogram
+        let body = r#"{"input": "1 1\n1 1", "backend": "sat"}"#;
+        let json = Some("application/json");
+        let response = handle_request("POST", "/solve/nonogram", json, body, &config);
+        assert_eq!(response.body.get("unique"), Some(&Json::Bool(false)));
+        let response = handle_request("POST", "/solve/nonogram", json, "{\"input\": 3}", &config);
+        assert_eq!(response.status, 400);
+        let tiny = ServerConfig {
+            max_sat_variables: 10,
+            ..ServerConfig::default()
+        };
+        let response = handle_request("POST", "/solve/nonogram", json, body, &tiny);
+        assert_eq!(response.status, 413);
+        let response = handle_request("POST", "/solve/nonogram", None, "1 1\n1 1", &tiny);
+        assert_eq!(response.status, 200);
+
+        assert_eq!(
+            handle_request("GET", "/solve/sudoku", None, "", &config).status,
+            405
+        );
+        assert_eq!(
+            handle_request("POST", "/solve/kakuro", None, "", &config).status,
+            404
+        );
+        let small = ServerConfig {
+            max_request_bytes: 10,
+            ..ServerConfig::default()
+        };
+        let response = handle_request("POST", "/solve/sudoku", None, SOLVABLE_SUDOKU, &small);
+        assert_eq!(response.status, 413)
+    }
+    #[cfg(feature = "server")]
+    #[test]
+    fn test_server_over_tcp() {
+        use crate::{serve, ServerConfig};
+        use std::{
+            io::{Read, Write},
+            net::{TcpListener, TcpStream},
+        };
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        // One worker has to serve every request in turn
+        let config = ServerConfig {
+            max_request_bytes: 1024,
+            workers: 1,
+            ..ServerConfig::default()
+        };
+        thread::spawn(move || serve(listener, config));
+        let send = |request: String| {
+            let mut stream = TcpStream::connect(address).unwrap();
+            stream.write_all(request.as_bytes()).unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).unwrap();
+            response
+        };
+
+        let response = send(format!(
+        "POST /solve/sudoku HTTP/1.1\r\nContent-Type: text/plain\r\nContent-Length: {}\r\n\r\n{}",
+        SOLVABLE_SUDOKU.len(),
+        SOLVABLE_SUDOKU
+    ));
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.contains("\"outcome\":\"solved\""));
+        let response =
+            send("POST /solve/sudoku HTTP/1.1\r\nContent-Length: 5000\r\n\r\n".to_string());
+        assert!(response.starts_with("HTTP/1.1 413 Payload Too Large\r\n"))
+    }
+    #[test]
+    fn test_property_solved_sudokus_are_valid() {
+        let mut rng = Rng::new(2024);
+        for _ in 0..30 {
+            let solution = random_solution(&mut rng);
+            assert!(satisfies_constraints(&solution));
+            // Removing clues from a solution always leaves a solvable puzzle
+            let mut puzzle = solution.clone();
+            for cell in puzzle.board.iter_mut().flatten() {
+                if rng.below(10) < 6 {
+                    *cell = 0;
+                }
+            }
+            for backend in [Backend::Search, Backend::Sat] {
+                let mut solved = puzzle.clone();
+                let report = match backend {
+                    Backend::Search => solved.solve_within(&Budget::unlimited()),
+                    Backend::Sat => solve_with_sat(&mut solved, &Budget::unlimited()),
+                };
+                assert_eq!(report.outcome, Outcome::Solved);
+                assert!(satisfies_constraints(&solved));
+                let givens = puzzle
+                    .board
+                    .iter()
+                    .flatten()
+                    .zip(solved.board.iter().flatten());
+                assert!(givens
+                    .into_iter()
+                    .all(|(&given, &digit)| given == 0 || given == digit))
+            }
+        }
+    }
+    #[test]
+    fn test_property_nonogram_solutions_match_clues() {
+        let mut rng = Rng::new(99);
+        for _ in 0..30 {
+            let (width, height) = (1 + rng.below(6), 1 + rng.below(6));
+            let grid: Vec<Vec<bool>> = (0..height)
+                .map(|_| (0..width).map(|_| rng.chance()).collect())
+                .collect();
+            let clues = Nonogram::from_solution(&grid).unwrap().format_clues();
+            let mut searched = Nonogram::build(&mut clues.clone()).unwrap();
+            assert!(searched.solve());
+            assert_eq!(
+                Nonogram::from_solution(&nonogram_grid(&searched))
+                    .unwrap()
+                    .format_clues(),
+                clues
+            );
+            let mut sat = Nonogram::build(&mut clues.clone()).unwrap();
+            assert_eq!(
+                solve_with_sat(&mut sat, &Budget::unlimited()).outcome,
+                Outcome::Solved
+            );
+            assert_eq!(
+                Nonogram::from_solution(&nonogram_grid(&sat))
+                    .unwrap()
+                    .format_clues(),
+                clues
+            );
+            let mut logic = Nonogram::build(&mut clues.clone()).unwrap();
+            if logic.solve_by_logic(&Budget::unlimited()).outcome == Outcome::Solved {
+                assert_eq!(
+                    Nonogram::from_solution(&nonogram_grid(&logic))
+                        .unwrap()
+                        .format_clues(),
+                    clues
+                )
+            }
+        }
+    }
+    #[test]
+    fn test_property_parsers_never_panic() {
+        let alphabet: Vec<char> = "0123456789 ,\n\r-x.\u{25A0}".chars().collect();
+        let mut rng = Rng::new(5);
+        for _ in 0..2000 {
+            let text: String = (0..rng.below(120))
+                .map(|_| alphabet[rng.below(alphabet.len())])
+                .collect();
+            let _ = Sudoku::build(&mut text.clone());
+            let _ = Nonogram::build(&mut text.clone());
+            let _ = build_runs(&text);
+        }
+    }
+    #[test]
+    fn test_build_runs() {
+        assert_eq!(
+            build_runs("10,2 0 3").unwrap(),
+            vec![vec![10, 2], vec![], vec![3]]
+        );
+        assert!(build_runs("1,,2").is_err());
+        assert!(build_runs("a").is_err());
+        assert!(build_runs("   ").is_err());
+        assert!(Nonogram::build(&mut "1 2".to_string()).is_err());
+        assert_eq!(
+            Nonogram::build(&mut "1 1\n3 1".to_string())
+                .unwrap_err()
+                .to_string(),
+            "Row 1 clues do not fit in 2 cells."
+        );
+        // A 10 wide row is read as a single run rather than a 1 and a 0
+        let mut nonogram = Nonogram::build(&mut format!("{}\n10", "1 ".repeat(10))).unwrap();
+        assert!(nonogram.solve());
+        assert_eq!(nonogram.format(), "\u{25A0}".repeat(10))
+    }
+    #[test]
+    fn test_candidate_grid_round_trip() {
+        let mut sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        sudoku.eliminate_candidates();
+        assert_eq!(sudoku.candidates(0, 1), vec![1, 8]);
+        assert_eq!(sudoku.candidates(0, 0), vec![6]);
+        let text = sudoku.format_candidates();
+        assert!(text.starts_with("6    18   1458 | 145"));
+        assert_eq!(Sudoku::build(&mut text.clone()).unwrap(), sudoku);
+
+        // Cells may also be written as 0 or ., and a lone pencil mark needs brackets
+        let mut text = vec!["."; 81];
+        text[0] = "[5]";
+        text[1] = "5";
+        let parsed = Sudoku::from_candidates(&text.join(" ")).unwrap();
+        assert_eq!((parsed.board[0][0], parsed.candidates(0, 0)), (0, vec![5]));
+        assert_eq!(parsed.board[0][1], 5);
+        text[2] = "[12";
+        assert_eq!(
+            Sudoku::from_candidates(&text.join(" "))
+                .unwrap_err()
+                .to_string(),
+            "Cell at row 1, column 3 has an invalid entry \"[12\"."
+        )
+    }
+    #[test]
+    fn test_solve_from_candidates() {
+        let mut empty = Sudoku::build(&mut "0".repeat(81)).unwrap();
+        empty.set_candidates(0, 0, &[9]);
+        let mut searched = empty.clone();
+        assert!(searched.solve());
+        assert_eq!(searched.board[0][0], 9);
+        let mut sat = empty.clone();
+        assert_eq!(
+            solve_with_sat(&mut sat, &Budget::unlimited()).outcome,
+            Outcome::Solved
+        );
+        assert_eq!(sat.board[0][0], 9);
+
+        // Rubbing out the right digit leaves nothing to find
+        let mut sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        sudoku.set_candidates(0, 1, &[1]);
+        assert!(!sudoku.clone().solve());
+        assert_eq!(
+            solve_with_sat(&mut sudoku, &Budget::unlimited()).outcome,
+            Outcome::Unsolvable
+        )
+    }
+    #[test]
+    fn test_transform_moves_candidates() {
+        let mut sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        sudoku.set_candidates(0, 1, &[1, 8]);
+        let transform = Transform::rotation().then(&Transform {
+            digits: [0, 2, 3, 4, 5, 6, 7, 8, 9, 1],
+            ..Transform::identity()
+        });
+        // A quarter turn clockwise moves the cell in row 0, column 1 to row 1, column 8
+        let transformed = transform.apply(&sudoku);
+        assert_eq!(transformed.candidates(1, 8), vec![2, 9]);
+        let args = ["puzzle-solver", "candidates", "marks.txt"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(command.mode, Mode::Candidates);
+        assert_eq!(command.filename, "marks.txt")
+    }
+    #[test]
+    fn test_samurai_sudoku() {
+        let contents = fs::read_to_string("benches/corpus/gattai_samurai.txt").unwrap();
+        let puzzle = Gattai::build(&mut contents.clone()).unwrap();
+        assert_eq!(
+            (puzzle.height(), puzzle.width(), puzzle.values.len()),
+            (21, 21, 369)
+        );
+        assert_eq!(puzzle.cell(0, 10), None);
+        for backend in [Backend::Search, Backend::Sat] {
+            let mut solved = puzzle.clone();
+            let report = match backend {
+                Backend::Search => solved.solve_within(&Budget::unlimited()),
+                Backend::Sat => solve_with_sat(&mut solved, &Budget::unlimited()),
+            };
+            assert_eq!(report.outcome, Outcome::Solved);
+            // Each of the five grids, including the shared corners, is a valid Sudoku
+            for (top, left) in [(0, 0), (0, 12), (6, 6), (12, 0), (12, 12)] {
+                let board = (top..top + 9)
+                    .map(|row| {
+                        (left..left + 9)
+                            .map(|col| solved.cell(row, col).unwrap())
+                            .collect()
+                    })
+                    .collect();
+                assert!(satisfies_constraints(&Sudoku::from_board(board)))
+            }
+            let givens = puzzle.values.iter().zip(&solved.values);
+            assert!(givens
+                .into_iter()
+                .all(|(&given, &digit)| given == 0 || given == digit));
+            // The layout is kept, so the solution reads back in under the same layout line
+            let mut reread = format!("samurai\n{}", solved.format());
+            assert_eq!(Gattai::build(&mut reread).unwrap(), solved)
+        }
+    }
+    #[test]
+    fn test_gattai_layouts() {
+        let mut twodoku = format!("6,6 0,0\n{}", "0".repeat(153));
+        assert_eq!(Gattai::build(&mut twodoku).unwrap().width(), 15);
+        let mut misaligned = format!("0,0 4,4\n{}", "0".repeat(146));
+        assert!(Gattai::build(&mut misaligned).is_err());
+        let mut short = "butterfly\n123".to_string();
+        assert_eq!(
+            Gattai::build(&mut short).unwrap_err().to_string(),
+            "Failed to read puzzle. This layout has 144 cells but 3 digits were found."
+        );
+        // The shared middle box (board rows and columns 6 to 8) follows the rules of both grids
+        let twodoku = Gattai::with_offsets(&[(0, 0), (6, 6)]).unwrap();
+        // Rows 0 to 5 hold 9 cells each, so board row 6 starts at index 54
+        let (shared, first_only, second_only) = (54 + 6, 54, 54 + 14);
+        for other in [first_only, second_only] {
+            let mut clash = twodoku.clone();
+            clash.values[shared] = 5;
+            clash.values[other] = 5;
+            assert_eq!(clash.cell(6, 6), Some(5));
+            assert!(!clash.solve())
+        }
+    }
+
+    #[test]
+    fn test_word_search() {
+        let mut contents =
+            "C A T X\nO W L D\nG O D E\nB I R D\n\ncat, owl\ndog bird\nTOAD\n".to_string();
+        let puzzle = WordSearch::build(&mut contents).unwrap();
+        assert!(!puzzle.clone().solve(), "TOAD is not in the grid");
+
+        let mut contents = "CATX\nOWLD\nGODE\nBIRD\n\ncat owl cog bird ode".to_string();
+        let mut puzzle = WordSearch::build(&mut contents).unwrap();
+        assert!(puzzle.solve());
+        // COG runs down the first column and shares its C with CAT
+        assert_eq!(puzzle.found(2), Some((0, 0, 2)));
+        assert_eq!(puzzle.found(4), Some((2, 1, 0)));
+        let solution = puzzle.format();
+        assert!(solution.starts_with("C A T .\nO W L .\nG O D E\nB I R D\n\n"));
+        assert!(solution.contains("COG: row 1, column 1, down"));
+
+        let mut ragged = "ABC\nDE\n\nab".to_string();
+        assert!(WordSearch::build(&mut ragged).is_err());
+        let mut no_words = "ABC\nDEF".to_string();
+        assert!(WordSearch::build(&mut no_words).is_err());
+    }
+
+    #[test]
+    fn test_crossword_fill() {
+        let mut contents = "...\n.#.\n...\n\ntop rot pet tar pit".to_string();
+        let mut crossword = Crossword::build(&mut contents).unwrap();
+        assert!(crossword.solve());
+        let rows: Vec<String> = (0..crossword.height())
+            .map(|row| {
+                (0..crossword.width())
+                    .map(|col| crossword.cell(row, col))
+                    .collect()
+            })
+            .collect();
+        // Every across and down slot must read as a different word from the list
+        let mut words = vec![
+            rows[0].clone(),
+            rows[2].clone(),
+            (0..3).map(|row| rows[row].as_bytes()[0] as char).collect(),
+            (0..3).map(|row| rows[row].as_bytes()[2] as char).collect(),
+        ];
+        words.sort();
+        words.dedup();
+        assert_eq!(words.len(), 4);
+        assert!(words
+            .iter()
+            .all(|word| ["TOP", "ROT", "PET", "TAR", "PIT"].contains(&word.as_str())));
+
+        // Letters already in the pattern have to be kept
+        let mut fixed = "Z..\n.#.\n...\n\ntop rot pet tar pit".to_string();
+        assert!(!Crossword::build(&mut fixed).unwrap().solve());
+        let mut few_words = "...\n.#.\n...\n\ntop rot".to_string();
+        assert!(Crossword::build(&mut few_words).is_err());
+        // Each word can only fill one slot
+        let mut reused = "..#..\n\nab xyz".to_string();
+        assert!(!Crossword::build(&mut reused).unwrap().solve());
+    }
+
+    #[test]
+    fn test_slitherlink() {
+        let mut contents = "2.2.0\n.3...\n2..2.\n..2.0\n2..31\n".to_string();
+        let mut slitherlink = Slitherlink::build(&mut contents).unwrap();
+        assert!(slitherlink.solve());
+        let (height, width) = (slitherlink.height(), slitherlink.width());
+        for row in 0..height {
+            for col in 0..width {
+                let Some(clue) = slitherlink.clue(row, col) else {
+                    continue;
+                };
+                let edges = [
+                    slitherlink.top(row, col),
+                    slitherlink.top(row + 1, col),
+                    slitherlink.left(row, col),
+                    slitherlink.left(row, col + 1),
+                ];
+                assert_eq!(edges.iter().filter(|&&on| on).count(), clue as usize);
+            }
+        }
+        // Walk the loop from its first edge and check it uses every edge that is on
+        let mut corners: Vec<Vec<(usize, usize)>> = vec![Vec::new(); (height + 1) * (width + 1)];
+        let mut total = 0;
+        for row in 0..=height {
+            for col in 0..=width {
+                let corner = row * (width + 1) + col;
+                if col < width && slitherlink.top(row, col) {
+                    corners[corner].push((row, col + 1));
+                    corners[corner + 1].push((row, col));
+                    total += 1;
+                }
+                if row < height && slitherlink.left(row, col) {
+                    corners[corner].push((row + 1, col));
+                    corners[corner + width + 1].push((row, col));
+                    total += 1;
+                }
+            }
+        }
+        assert!(corners
+            .iter()
+            .all(|ends| ends.is_empty() || ends.len() == 2));
+        let start = corners.iter().position(|ends| !ends.is_empty()).unwrap();
+        let (mut previous, mut corner, mut length) = (start, start, 0);
+        loop {
+            let next = corners[corner]
+                .iter()
+                .map(|&(row, col)| row * (width + 1) + col)
+                .find(|&next| next != previous)
+                .unwrap();
+            (previous, corner) = (corner, next);
+            length += 1;
+            if corner == start {
+                break;
+            }
+        }
+        assert_eq!(length, total);
+
+        let mut full = Slitherlink::build(&mut "4".to_string()).unwrap();
+        assert!(full.solve());
+        assert_eq!(full.format(), "+---+\n| 4 |\n+---+");
+        let mut impossible = "1".to_string();
+        assert!(!Slitherlink::build(&mut impossible).unwrap().solve());
+        let mut invalid = "5.\n..".to_string();
+        assert!(Slitherlink::build(&mut invalid).is_err());
+    }
+
+    #[test]
+    fn test_star_battle() {
+        let mut contents = "1\nAABBB\nAABBB\nCCCDD\nCEEDD\nEEEDD\n".to_string();
+        let mut star_battle = StarBattle::build(&mut contents).unwrap();
+        assert!(star_battle.solve());
+        let size = star_battle.size();
+        let stars: Vec<(usize, usize)> = (0..size * size)
+            .map(|cell| (cell / size, cell % size))
+            .filter(|&(row, col)| star_battle.is_star(row, col))
+            .collect();
+        assert_eq!(stars.len(), size);
+        for (i, &(row, col)) in stars.iter().enumerate() {
+            for &(other_row, other_col) in &stars[i + 1..] {
+                assert_ne!(row, other_row);
+                assert_ne!(col, other_col);
+                assert_ne!(
+                    star_battle.region(row, col),
+                    star_battle.region(other_row, other_col)
+                );
+                assert!(row.abs_diff(other_row) > 1 || col.abs_diff(other_col) > 1);
+            }
+        }
+
+        let mut too_many_regions = "1\nAB\nCD".to_string();
+        assert!(StarBattle::build(&mut too_many_regions).is_err());
+        // Two stars in a 2x2 grid would have to touch
+        let mut touching = "1\nAA\nBB".to_string();
+        assert!(!StarBattle::build(&mut touching).unwrap().solve());
+    }
+
+    #[test]
+    fn test_tents() {
+        let mut contents =
+            "1 1 0 1 1 1\n2 ....T.\n0 T.....\n1 ......\n1 ..T..T\n1 ...T..\n0 ......\n".to_string();
+        let mut tents = Tents::build(&mut contents).unwrap();
+        assert!(tents.solve());
+        assert_eq!(
+            tents.format(),
+            "A . . A T .\nT . . . . .\n. . . . . A\n. A T . . T\n. . . T A .\n. . . . . ."
+        );
+
+        // The counts ask for a tent that no tree can pair with
+        let mut unpaired = "1 1\n1 T.\n1 ..".to_string();
+        assert!(!Tents::build(&mut unpaired).unwrap().solve());
+        let mut short_row = "1 0\n1 T".to_string();
+        assert!(Tents::build(&mut short_row).is_err());
+    }
+
+    #[test]
+    fn test_ragged_grids() {
+        // The right number of cells overall, but spread over rows of the wrong lengths
+        let mut tents = "1 0 1\n1 ..T\n0 .\n1 .....".to_string();
+        assert_eq!(
+            Tents::build(&mut tents).unwrap_err().to_string(),
+            "Failed to read puzzle. The column counts give 3 columns, but the rows do not all have that many cells."
+        );
+        let mut binairo = "1..0\n..0\n.0..1\n...1".to_string();
+        assert_eq!(
+            Binairo::build(&mut binairo).unwrap_err().to_string(),
+            "Failed to read puzzle. A Binairo grid must be square with an even number of rows."
+        )
+    }
+    #[test]
+    fn test_binairo() {
+        let mut contents = "1..0..\n..0..1\n.0....\n...1..\n0.....\n..1..0\n".to_string();
+        let mut binairo = Binairo::build(&mut contents).unwrap();
+        assert!(binairo.solve());
+        let size = binairo.size();
+        let rows: Vec<Vec<u8>> = (0..size)
+            .map(|row| {
+                (0..size)
+                    .map(|col| binairo.cell(row, col).unwrap())
+                    .collect()
+            })
+            .collect();
+        let cols: Vec<Vec<u8>> = (0..size)
+            .map(|col| (0..size).map(|row| rows[row][col]).collect())
+            .collect();
+        for lines in [&rows, &cols] {
+            for (i, line) in lines.iter().enumerate() {
+                assert_eq!(line.iter().filter(|&&digit| digit == 1).count(), size / 2);
+                assert!(line
+                    .windows(3)
+                    .all(|run| run[0] != run[1] || run[1] != run[2]));
+                assert!(lines[i + 1..].iter().all(|other| other != line));
+            }
+        }
+        assert_eq!(binairo.cell(0, 0), Some(1));
+
+        let mut triple = "000.\n....\n....\n....".to_string();
+        assert!(!Binairo::build(&mut triple).unwrap().solve());
+        let mut odd = "0.1\n...\n...".to_string();
+        assert!(Binairo::build(&mut odd).is_err());
+    }
+
+    // Draws a Sudoku the way a printed page shows it, with thicker lines around the boxes
+    fn sudoku_image(sudoku: &Sudoku) -> GrayImage {
+        let (cell, scale, margin) = (44, 4, 12);
+        let size = 9 * cell + 2 * margin + 3;
+        let mut image = GrayImage::new(size, size);
+        for i in 0..=9 {
+            let thickness = if i % 3 == 0 { 3 } else { 1 };
+            for offset in 0..thickness {
+                for along in margin..=margin + 9 * cell + 2 {
+                    image.set(along, margin + i * cell + offset, 0);
+                    image.set(margin + i * cell + offset, along, 0);
+                }
+            }
+        }
+        for (row, digits) in sudoku.board.iter().enumerate() {
+            for (col, &digit) in digits.iter().enumerate() {
+                if digit != 0 {
+                    let x = margin + col * cell + (cell - 5 * scale) / 2;
+                    let y = margin + row * cell + (cell - 7 * scale) / 2;
+                    draw_digit(&mut image, digit, x, y, scale);
+                }
+            }
+        }
+        image
+    }
+
+    // Encodes a grayscale PNG without compression, giving each row a different filter so the
+    // decoder has to undo all five. Checksums are left as zero, since the decoder skips them.
+    fn png_bytes(image: &GrayImage) -> Vec<u8> {
+        let (width, height) = (image.width, image.height);
+        let mut raw = Vec::new();
+        for y in 0..height {
+            let filter = (y % 5) as u8;
+            raw.push(filter);
+            for x in 0..width {
+                let at = |x: Option<usize>, y: Option<usize>| match (x, y) {
+                    (Some(x), Some(y)) => image.get(x, y),
+                    _ => 0,
+                };
+                let (left, up, up_left) = (
+                    at(x.checked_sub(1), Some(y)),
+                    at(Some(x), y.checked_sub(1)),
+                    at(x.checked_sub(1), y.checked_sub(1)),
+                );
+                let estimate = left as i16 + up as i16 - up_left as i16;
+                let paeth = [left, up, up_left]
+                    .into_iter()
+                    .min_by_key(|&value| (estimate - value as i16).abs())
+                    .unwrap();
+                let predicted = match filter {
+                    0 => 0,
+                    1 => left,
+                    2 => up,
+                    3 => ((left as u16 + up as u16) / 2) as u8,
+                    _ => paeth,
+                };
+                raw.push(image.get(x, y).wrapping_sub(predicted));
+            }
+        }
+        let mut zlib = vec![120, 1];
+        let blocks: Vec<&[u8]> = raw.chunks(65535).collect();
+        for (index, block) in blocks.iter().enumerate() {
+            zlib.push((index + 1 == blocks.len()) as u8);
+            zlib.extend((block.len() as u16).to_le_bytes());
+            zlib.extend((!(block.len() as u16)).to_le_bytes());
+            zlib.extend_from_slice(block);
+        }
+        zlib.extend([0; 4]);
+
+        let mut header = (width as u32).to_be_bytes().to_vec();
+        header.extend((height as u32).to_be_bytes());
+        header.extend([8, 0, 0, 0, 0]);
+        let mut png = vec![137, 80, 78, 71, 13, 10, 26, 10];
+        for (kind, data) in [(b"IHDR", header), (b"IDAT", zlib), (b"IEND", Vec::new())] {
+            png.extend((data.len() as u32).to_be_bytes());
+            png.extend(kind);
+            png.extend(data);
+            png.extend([0; 4]);
+        }
+        png
+    }
+
+    #[test]
+    fn test_inflate() {
+        // Written by zlib at level 9, which picked a dynamic Huffman block
+        let dynamic = [
+            120, 218, 53, 139, 129, 13, 0, 48, 8, 194, 94, 2, 249, 255, 183, 89, 50, 141, 18, 161,
+            42, 57, 210, 182, 18, 175, 50, 72, 108, 79, 221, 50, 183, 116, 28, 28, 213, 19, 163,
+            247, 249, 87, 142, 61, 112, 130, 7, 193, 51, 19, 1,
+        ];
+        let expected = "0013003000331000100001003111201000300111111010001000100330101001110001100010000011000111011210010110";
+        assert_eq!(
+            zlib_decompress(&dynamic, 1000).unwrap(),
+            expected.as_bytes()
+        );
+        assert!(zlib_decompress(&dynamic, 50).is_err());
+        // Fixed Huffman codes with matches that copy earlier text
+        let fixed = [
+            120, 1, 43, 46, 77, 201, 207, 46, 85, 40, 70, 166, 0, 84, 212, 8, 18,
+        ];
+        assert_eq!(
+            zlib_decompress(&fixed, 1000).unwrap(),
+            b"sudoku sudoku sudoku"
+        );
+        assert!(zlib_decompress(&fixed[..8], 1000).is_err());
+    }
+
+    #[test]
+    fn test_image_decoding() {
+        let mut image = GrayImage::new(7, 6);
+        for (index, pixel) in image.pixels.iter_mut().enumerate() {
+            *pixel = (index * 37 % 256) as u8;
+        }
+        assert_eq!(GrayImage::decode(&png_bytes(&image)).unwrap(), image);
+
+        let mut pgm = b"P5\n# scanned\n7 6\n255\n".to_vec();
+        pgm.extend(&image.pixels);
+        assert_eq!(GrayImage::decode(&pgm).unwrap(), image);
+        let plain = GrayImage::decode(b"P2 2 1 15 0 15").unwrap();
+        assert_eq!(plain.pixels, vec![0, 255]);
+        assert!(GrayImage::decode(b"GIF89a").is_err());
+        assert!(GrayImage::decode(b"P5 100 100 255 short").is_err());
+    }
+
+    #[test]
+    fn test_recognize_sudoku() {
+        let sudoku = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let image = GrayImage::decode(&png_bytes(&sudoku_image(&sudoku))).unwrap();
+        assert_eq!(recognize_sudoku(&image).unwrap(), sudoku);
+        // Every digit has to be told apart from the others
+        let mut solved = sudoku.clone();
+        assert!(solved.solve());
+        assert_eq!(recognize_sudoku(&sudoku_image(&solved)).unwrap(), solved);
+        assert!(recognize_sudoku(&GrayImage::new(50, 50)).is_err());
+
+        let path = env::temp_dir().join("puzzle_solver_scan.png");
+        fs::write(&path, png_bytes(&sudoku_image(&sudoku))).unwrap();
+        let args = [
+            "puzzle-solver",
+            "sudoku",
+            path.to_str().unwrap(),
+            "--output",
+            "-",
+        ];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        let mut output = Vec::new();
+        run_with(command, "".as_bytes(), &mut output).unwrap();
+        let mut written = Vec::new();
+        solved.write_to(&mut written).unwrap();
+        assert_eq!(output, written);
+        fs::remove_file(path).unwrap();
+    }
+    #[test]
+    fn test_config_parsing() {
+        let text = "# Shared settings for the team
+puzzle = \"Nonogram\"
+output_dir = 'solutions' # relative to where the solver runs
+output_format = \"grid\"
+timeout = 2.5
+max_nodes = 1_000_000
+backend = \"sat\"
+stats = true
+";
+        let config = Config::parse(text).unwrap();
+        assert_eq!(config.puzzle, Some("nonogram".to_string()));
+        assert_eq!(config.output_dir, Some("solutions".to_string()));
+        assert_eq!(config.output_format, Some(OutputFormat::Grid));
+        assert_eq!(config.timeout, Some(Duration::from_millis(2500)));
+        assert_eq!(config.max_nodes, Some(1_000_000));
+        assert_eq!(config.backend, Some(Backend::Sat));
+        assert_eq!(config.stats, Some(true));
+        assert_eq!(config.input_format, None);
+
+        let error = Config::parse("timeout = 3\ncolour = \"red\"").unwrap_err();
+        assert_eq!(error, "Line 2 of the config: unknown setting \"colour\".");
+        let error = Config::parse("stats = true\ntimeout = 1e300").unwrap_err();
+        assert_eq!(
+            error,
+            "Line 2 of the config: timeout must be a positive number of seconds."
+        );
+        assert!(Config::parse("[solver]").is_err());
+        assert!(Config::parse("stats = \"yes\"").is_err());
+        assert!(Config::parse("puzzle = \"chess\"").is_err());
+        assert!(Config::parse("output_dir = \"unterminated").is_err());
+
+        // Project settings win over user settings, and unset ones fall through
+        let user = Config::parse("timeout = 10\nbackend = \"sat\"").unwrap();
+        let project = Config::parse("timeout = 1").unwrap();
+        let merged = user.merge(project);
+        assert_eq!(merged.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(merged.backend, Some(Backend::Sat));
+    }
+    #[test]
+    fn test_config_defaults_and_overrides() {
+        let config =
+            Config::parse("puzzle = \"nonogram\"\ntimeout = 5\nbackend = \"sat\"").unwrap();
+        let args = ["puzzle-solver", "clues.txt", "--timeout", "1"];
+        let command = Command::build_with_config(args.iter().map(|arg| arg.to_string()), &config);
+        let command = command.unwrap();
+        assert_eq!(command.puzzle, "nonogram");
+        assert_eq!(command.filename, "clues.txt");
+        assert_eq!(command.timeout, Some(Duration::from_secs(1)));
+        assert_eq!(command.backend, Backend::Sat);
+
+        // A puzzle type on the command line replaces the configured one
+        let args = ["puzzle-solver", "sudoku", "--backend", "search"];
+        let command = Command::build_with_config(args.iter().map(|arg| arg.to_string()), &config);
+        let command = command.unwrap();
+        assert_eq!(command.puzzle, "sudoku");
+        assert_eq!(command.filename, "sudoku.txt");
+        assert_eq!(command.backend, Backend::Search);
+
+        // Without a configured puzzle type an unknown one is still an error
+        let args = ["puzzle-solver", "clues.txt", "--config", "team.toml"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(command.puzzle, "clues.txt");
+        assert!(run(command).is_err());
+    }
+    #[test]
+    fn test_config_output_dir_and_format() {
+        let dir = env::temp_dir().join("puzzle_solver_config_output");
+        let _ = fs::remove_dir_all(&dir);
+        let config = Config::parse(&format!(
+            "output_dir = '{}'\noutput_format = \"grid\"",
+            dir.join("nested").display()
+        ))
+        .unwrap();
+        let args = ["puzzle-solver", "sudoku", "-"];
+        let command = Command::build_with_config(args.iter().map(|arg| arg.to_string()), &config);
+        run_with(command.unwrap(), SOLVABLE_SUDOKU.as_bytes(), Vec::new()).unwrap();
+        let mut solved = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        assert!(solved.solve());
+        let written = fs::read_to_string(dir.join("nested").join("solution.txt")).unwrap();
+        assert_eq!(written, solved.format() + "\n");
+
+        // --input-format text stops a .png name from being read as an image
+        let path = dir.join("grid.png");
+        fs::write(&path, SOLVABLE_SUDOKU).unwrap();
+        let args = [
+            "puzzle-solver",
+            "sudoku",
+            path.to_str().unwrap(),
+            "--input-format",
+            "text",
+            "--output",
+            "-",
+        ];
+        let command = Command::build_with_config(args.iter().map(|arg| arg.to_string()), &config);
+        let mut output = Vec::new();
+        run_with(command.unwrap(), "".as_bytes(), &mut output).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), solved.format() + "\n");
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn test_detect_puzzle_type() {
+        assert!(matches!(
+            detect_puzzle(SOLVABLE_SUDOKU),
+            Some(PuzzleType::Sudoku)
+        ));
+        let nonogram =
+        "5,4 1,1,3 2,2 1,2 1,2 9 3 4,1 2,1 2,3,1\n1,1 2,5 1,1,5 8 1,1 2,1 1,2,3 2,1,1 4,1 4,1,1";
+        assert!(matches!(
+            detect_puzzle(nonogram),
+            Some(PuzzleType::Nonogram)
+        ));
+        assert!(detect_puzzle("a shopping list").is_none());
+    }
+    #[test]
+    fn test_watch_directory() {
+        let dir = env::temp_dir().join("puzzle_solver_watch");
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("daily.txt"), SOLVABLE_SUDOKU).unwrap();
+        fs::write(dir.join("picture.txt"), "1 1\n1 1").unwrap();
+        fs::write(dir.join("notes.txt"), "not a puzzle").unwrap();
+        fs::write(dir.join("scan.png"), "ignored").unwrap();
+        let args = ["puzzle-solver", "watch", dir.to_str().unwrap()];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(command.mode, Mode::Watch);
+        assert_eq!(command.filename, dir.to_str().unwrap());
+
+        // Files are only picked up once they have stopped changing between polls
+        let mut watcher = Watcher::new(&dir);
+        assert!(watcher.poll(&command).unwrap().is_empty());
+        let handled = watcher.poll(&command).unwrap();
+        let names: Vec<String> = handled
+            .iter()
+            .map(|(path, _)| path.file_name().unwrap().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names, ["daily.txt", "notes.txt", "picture.txt"]);
+        assert!(handled[0].1.is_ok());
+        assert!(handled[1].1.is_err());
+        assert!(handled[2].1.is_ok());
+        let mut solved = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        assert!(solved.solve());
+        let written = fs::read_to_string(dir.join("daily.solution.txt")).unwrap();
+        assert!(written.ends_with(&solved.format()));
+        assert!(dir.join("picture.solution.txt").exists());
+
+        // Solutions are not solved again, and failures are only reported once
+        assert!(watcher.poll(&command).unwrap().is_empty());
+        assert!(watcher.poll(&command).unwrap().is_empty());
+        fs::remove_dir_all(dir).unwrap();
+    }
+    #[test]
+    fn test_sudoku_check() {
+        let mut solved = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        assert!(solved.solve());
+        assert!(solved.check().is_empty());
+        assert!(solved.is_valid());
+
+        // Two 5s in the same column and box used to slip through the box check
+        let mut grid = Sudoku::from_board(vec![vec![0; 9]; 9]);
+        grid.board[0][0] = 5;
+        grid.board[1][0] = 5;
+        assert!(!grid.is_valid());
+        assert_eq!(
+            grid.check(),
+            [
+                Violation::Duplicate {
+                    digit: 5,
+                    unit: Unit::Column,
+                    cells: [(0, 0), (1, 0)],
+                },
+                Violation::Duplicate {
+                    digit: 5,
+                    unit: Unit::Box,
+                    cells: [(0, 0), (1, 0)],
+                },
+            ]
+        );
+        assert_eq!(
+            grid.check()[0].to_string(),
+            "5 appears twice in the same column: row 1, column 1 and row 2, column 1"
+        );
+
+        let mut swapped = solved.clone();
+        swapped.board[8].swap(0, 8);
+        let violations = swapped.check();
+        assert_eq!(violations.len(), 4);
+        assert!(violations.iter().all(
+            |violation| matches!(violation, Violation::Duplicate { unit, .. } if *unit != Unit::Row)
+        ));
+    }
+    #[test]
+    fn test_sudoku_check_against_solution() {
+        let original = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        let mut solved = original.clone();
+        assert!(solved.solve());
+        assert!(solved
+            .check_against(&original, &Budget::unlimited())
+            .unwrap()
+            .is_empty());
+
+        // Find an empty cell and fill it with a digit that fits the rules but not the solution
+        let (row, col) = (0..81)
+            .map(|index| (index / 9, index % 9))
+            .find(|&(row, col)| original.board[row][col] == 0)
+            .unwrap();
+        let mut partial = original.clone();
+        let wrong = (1..=9)
+            .find(|&digit| {
+                partial.board[row][col] = digit;
+                digit != solved.board[row][col] && partial.check().is_empty()
+            })
+            .unwrap();
+        partial.board[row][col] = wrong;
+        let clue = (0..81)
+            .map(|index| (index / 9, index % 9))
+            .find(|&(row, col)| original.board[row][col] != 0)
+            .unwrap();
+        partial.board[clue.0][clue.1] = 0;
+        let violations = partial
+            .check_against(&original, &Budget::unlimited())
+            .unwrap();
+        assert_eq!(violations.len(), 2);
+        assert!(violations.contains(&Violation::ChangedClue {
+            cell: clue,
+            clue: original.board[clue.0][clue.1],
+            found: 0,
+        }));
+        assert!(violations.contains(&Violation::WrongDigit {
+            cell: (row, col),
+            found: wrong,
+            expected: solved.board[row][col],
+        }));
+
+        let empty = Sudoku::from_board(vec![vec![0; 9]; 9]);
+        assert!(solved.check_against(&empty, &Budget::unlimited()).is_err());
+        // Checking gives up rather than searching past its budget
+        let result = solved.check_against(&original, &Budget::unlimited().with_max_nodes(5));
+        assert!(result.unwrap_err().to_string().starts_with("Gave up"));
+
+        let args = ["puzzle-solver", "check", "-", "--original", "sudoku.txt"];
+        let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+        assert_eq!(command.mode, Mode::Check);
+        assert_eq!(command.puzzle, "sudoku");
+        assert_eq!(command.original, Some("sudoku.txt".to_string()));
+    }
+    #[test]
+    fn test_check_command() {
+        let path = env::temp_dir().join("puzzle_solver_check_original.txt");
+        fs::write(&path, SOLVABLE_SUDOKU).unwrap();
+        let check = |grid: &Sudoku| {
+            let args = [
+                "puzzle-solver",
+                "check",
+                "-",
+                "--original",
+                path.to_str().unwrap(),
+                "--output",
+                "-",
+            ];
+            let command = Command::build(args.iter().map(|arg| arg.to_string())).unwrap();
+            let mut output = Vec::new();
+            let result = run_with(command, grid.format().as_bytes(), &mut output);
+            (result, String::from_utf8(output).unwrap())
+        };
+
+        let mut solved = Sudoku::build(&mut SOLVABLE_SUDOKU.to_string()).unwrap();
+        assert!(solved.solve());
+        let (result, report) = check(&solved);
+        assert!(result.is_ok());
+        assert_eq!(report, "The grid is complete and correct.\n");
+
+        // Swapping two cells in a row breaks both columns and the solution
+        let mut swapped = solved.clone();
+        swapped.board[8].swap(0, 8);
+        let (result, report) = check(&swapped);
+        assert!(result.unwrap_err().to_string().ends_with("problems found."));
+        assert!(report.lines().count() > 1);
+        fs::remove_file(&path).unwrap();
+    }
+    #[test]
+    fn test_generate_sudoku() {
+        let puzzle = generate_sudoku(&mut Rng::new(7));
+        assert_eq!(puzzle, generate_sudoku(&mut Rng::new(7)));
+        assert!(puzzle.is_valid());
+        let clues = puzzle
+            .board
+            .iter()
+            .flatten()
+            .filter(|&&digit| digit != 0)
+            .count();
+        assert!(clues < 40);
+        let mut copy = puzzle.clone();
+        assert_eq!(count_solutions(&mut copy, 2, &Budget::unlimited()), Ok(1));
+    }
+    #[test]
+    fn test_generate_nonogram() {
+        let puzzle = generate_nonogram(&mut Rng::new(1), 10, 8).unwrap();
+        assert_eq!((puzzle.width(), puzzle.height()), (10, 8));
+        let mut read = Nonogram::build(&mut puzzle.format_clues()).unwrap();
+        assert_eq!(read.format_clues(), puzzle.format_clues());
+        assert_eq!(count_solutions(&mut read, 2, &Budget::unlimited()), Ok(1));
+        assert!(generate_nonogram(&mut Rng::new(1), 0, 5).is_err());
+        assert!(generate_nonogram(&mut Rng::new(1), 5, 0).is_err());
+        assert!(Nonogram::from_solution(&[]).is_err());
+        assert!(Nonogram::from_solution(&[vec![], vec![]]).is_err());
+        assert!(Nonogram::from_solution(&[vec![true, false], vec![true]]).is_err());
+    }
+    #[test]
+    fn test_nonogram_grid_check() {
+        let mut nonogram = Nonogram::build(&mut "1,1 1 1,1\n3 0 1,1".to_string()).unwrap();
+        nonogram.read_grid("###\n...\n#.#").unwrap();
+        assert!(nonogram.mismatched_lines().is_empty());
+        assert!(nonogram.is_complete());
+        nonogram.read_grid("##.\n...\n#.#").unwrap();
+        assert_eq!(
+            nonogram.mismatched_lines(),
+            [(Unit::Row, 0), (Unit::Column, 2)]
+        );
+        assert!(nonogram.read_grid("###\n...").is_err());
+        assert!(nonogram.read_grid("#x#\n...\n#.#").is_err());
+    }
+    #[cfg(feature = "wasm")]
+    #[test]
+    fn test_wasm_bindings() {
+        use crate::wasm::{check_nonogram, generate_nonogram, generate_sudoku, solve_nonogram};
+        use crate::wasm::{
+            check_sudoku, count_nonogram_solutions, count_sudoku_solutions, solve_sudoku,
+        };
+        // Only the successful paths run natively, since errors are built by JavaScript
+        let solution = solve_sudoku(SOLVABLE_SUDOKU, 0).unwrap();
+        assert_eq!(solution.len(), 81);
+        assert!(check_sudoku(&solution, SOLVABLE_SUDOKU, 0)
+            .unwrap()
+            .is_empty());
+        let puzzle = generate_sudoku(3);
+        assert_eq!(solve_sudoku(&puzzle, 1000).unwrap().len(), 81);
+        assert_eq!(count_sudoku_solutions(&puzzle, 2, 0).unwrap(), 1);
+
+        let clues = generate_nonogram(6, 5, 3).unwrap();
+        let picture = solve_nonogram(&clues, 0).unwrap();
+        assert_eq!(picture.lines().count(), 5);
+        assert_eq!(count_nonogram_solutions(&clues, 2, 0).unwrap(), 1);
+        assert!(check_nonogram(&clues, &picture).unwrap().is_empty());
+        let flipped: String = picture
+            .chars()
+            .map(|c| match c {
+                '#' => '.',
+                '.' => '#',
+                _ => c,
+            })
+            .collect();
+        assert_eq!(check_nonogram(&clues, &flipped).unwrap().len(), 11);
+    }
+    // Runs in a JavaScript engine with `wasm-pack test --node --features wasm`
+    #[cfg(all(target_arch = "wasm32", feature = "wasm"))]
+    #[wasm_bindgen_test::wasm_bindgen_test]
+    fn test_wasm_runtime() {
+        use crate::wasm::{check_sudoku, generate_sudoku, solve_nonogram, solve_sudoku};
+        let solution = solve_sudoku(SOLVABLE_SUDOKU, 5000).unwrap();
+        assert!(check_sudoku(&solution, "", 0).unwrap().is_empty());
+        assert!(solve_sudoku("123", 0).is_err());
+        assert!(solve_nonogram("1\n", 0).is_err());
+        let puzzle = generate_sudoku(11);
+        assert_eq!(puzzle, generate_sudoku(11));
+        let solved = solve_sudoku(&puzzle, 5000).unwrap();
+        assert!(check_sudoku(&solved, &puzzle, 5000).unwrap().is_empty());
+    }
+    #[cfg(feature = "ffi")]
+    #[test]
+    fn test_c_interface() {
+        use crate::ffi::*;
+        use std::ffi::{CStr, CString};
+        let read = |text: *mut std::ffi::c_char| {
+            assert!(!text.is_null());
+            let copy = unsafe { CStr::from_ptr(text) }
+                .to_str()
+                .unwrap()
+                .to_string();
+            unsafe { puzzle_free_string(text) };
+            copy
+        };
+        let input = CString::new(SOLVABLE_SUDOKU).unwrap();
+        let solution = read(unsafe { puzzle_solve_sudoku(input.as_ptr(), 0.0) });
+        assert_eq!(solution.len(), 81);
+        // A timeout too long to represent means no limit rather than a panic
+        let solution = read(unsafe { puzzle_solve_sudoku(input.as_ptr(), 1e300) });
+        assert_eq!(solution.len(), 81);
+        assert_eq!(
+            unsafe { puzzle_count_sudoku_solutions(input.as_ptr(), 2, 0.0) },
+            1
+        );
+        let puzzle = read(puzzle_generate_sudoku(5));
+        let puzzle = CString::new(puzzle).unwrap();
+        assert_eq!(
+            unsafe { puzzle_count_sudoku_solutions(puzzle.as_ptr(), 2, 0.0) },
+            1
+        );
+        let clues = CString::new(read(puzzle_generate_nonogram(5, 5, 2))).unwrap();
+        assert_eq!(
+            unsafe { puzzle_count_nonogram_solutions(clues.as_ptr(), 2, 0.0) },
+            1
+        );
+        assert_eq!(
+            read(unsafe { puzzle_solve_nonogram(clues.as_ptr(), 0.0) })
+                .lines()
+                .count(),
+            5
+        );
+
+        let bad = CString::new("123").unwrap();
+        assert!(unsafe { puzzle_solve_sudoku(bad.as_ptr(), 0.0) }.is_null());
+        let error = unsafe { CStr::from_ptr(puzzle_last_error()) };
+        assert!(error.to_str().unwrap().contains("81 digits"));
+        assert_eq!(
+            unsafe { puzzle_count_sudoku_solutions(std::ptr::null(), 2, 0.0) },
+            -1
+        );
+        unsafe { puzzle_free_string(std::ptr::null_mut()) };
+    }
+    #[test]
+    fn test_c_header_matches_interface() {
+        // Every exported function has to be declared in the header handed to C users
+        let source = include_str!("ffi.rs");
+        let header = include_str!("../include/puzzle_solver.h");
+        let exported: Vec<&str> = source
+            .split("extern \"C\" fn ")
+            .skip(1)
+            .map(|rest| rest.split('(').next().unwrap())
+            .collect();
+        assert_eq!(exported.len(), 8);
+        for name in exported {
+            assert!(
+                header.contains(&format!(" *{}(", name)) || header.contains(&format!(" {}(", name)),
+                "{} is missing from the header",
+                name
+            );
+        }
+        assert_eq!(header.matches(");").count(), 8);
+    }
 }
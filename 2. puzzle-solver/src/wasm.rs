@@ -0,0 +1,76 @@
+// Bindings for running the solvers in a browser. Puzzles go in and out as text in the same
+// formats as the puzzle files, and errors become JavaScript exceptions.
+use std::{fmt::Display, time::Duration};
+
+use wasm_bindgen::prelude::*;
+
+use crate::{api, Budget};
+
+fn js_error(err: impl Display) -> JsValue {
+    JsValue::from_str(&err.to_string())
+}
+
+// A timeout of 0 means no limit
+fn budget(timeout_ms: u32) -> Budget {
+    match timeout_ms {
+        0 => Budget::unlimited(),
+        _ => Budget::unlimited().with_timeout(Duration::from_millis(timeout_ms.into())),
+    }
+}
+
+/// Solves a Sudoku and returns the solution as a line of 81 digits.
+#[wasm_bindgen(js_name = solveSudoku)]
+pub fn solve_sudoku(input: &str, timeout_ms: u32) -> Result<String, JsValue> {
+    api::solve_sudoku(input, &budget(timeout_ms)).map_err(js_error)
+}
+
+/// Solves a Nonogram given as a line of column clues and a line of row clues, returning the
+/// picture with one line per row, # for filled cells and . for empty ones.
+#[wasm_bindgen(js_name = solveNonogram)]
+pub fn solve_nonogram(input: &str, timeout_ms: u32) -> Result<String, JsValue> {
+    api::solve_nonogram(input, &budget(timeout_ms)).map_err(js_error)
+}
+
+/// Counts the solutions of a Sudoku, stopping once `limit` have been found, so a limit of 2
+/// tells whether the solution is unique.
+#[wasm_bindgen(js_name = countSudokuSolutions)]
+pub fn count_sudoku_solutions(input: &str, limit: u32, timeout_ms: u32) -> Result<u32, JsValue> {
+    let count = api::count_sudoku_solutions(input, limit as usize, &budget(timeout_ms));
+    count.map(|count| count as u32).map_err(js_error)
+}
+
+/// Counts the solutions of a Nonogram, stopping once `limit` have been found.
+#[wasm_bindgen(js_name = countNonogramSolutions)]
+pub fn count_nonogram_solutions(input: &str, limit: u32, timeout_ms: u32) -> Result<u32, JsValue> {
+    let count = api::count_nonogram_solutions(input, limit as usize, &budget(timeout_ms));
+    count.map(|count| count as u32).map_err(js_error)
+}
+
+/// Checks a full or partial Sudoku grid and returns a description of every mistake. When
+/// `original` is not empty, the grid is also compared against that puzzle's solution, which
+/// must be found within the timeout.
+#[wasm_bindgen(js_name = checkSudoku)]
+pub fn check_sudoku(grid: &str, original: &str, timeout_ms: u32) -> Result<Vec<String>, JsValue> {
+    let original = Some(original).filter(|original| !original.trim().is_empty());
+    api::check_sudoku(grid, original, &budget(timeout_ms)).map_err(js_error)
+}
+
+/// Checks a Nonogram picture against its clues and names every row and column that does not
+/// match, counting from 1.
+#[wasm_bindgen(js_name = checkNonogram)]
+pub fn check_nonogram(clues: &str, grid: &str) -> Result<Vec<String>, JsValue> {
+    api::check_nonogram(clues, grid).map_err(js_error)
+}
+
+/// Makes a Sudoku with a unique solution, returned as a line of 81 digits with 0 for empty
+/// cells. The same seed always gives the same puzzle.
+#[wasm_bindgen(js_name = generateSudoku)]
+pub fn generate_sudoku(seed: u32) -> String {
+    api::generate_sudoku(seed.into())
+}
+
+/// Makes a Nonogram with a unique solution, returned as its two lines of clues.
+#[wasm_bindgen(js_name = generateNonogram)]
+pub fn generate_nonogram(width: usize, height: usize, seed: u32) -> Result<String, JsValue> {
+    api::generate_nonogram(width, height, seed.into()).map_err(js_error)
+}
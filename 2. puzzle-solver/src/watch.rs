@@ -0,0 +1,148 @@
+use std::{
+    collections::HashMap,
+    error::Error,
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::{Duration, SystemTime},
+};
+
+use crate::{nonogram_puzzle, sudoku_puzzle, Command, OutputFormat, PuzzleType};
+
+// Solutions are written next to each puzzle with this ending, which also keeps them from
+// being picked up as puzzles themselves
+const SOLUTION_SUFFIX: &str = ".solution.txt";
+
+// A puzzle file that was handled, with where its solution went or why it failed
+type Handled = (PathBuf, Result<PathBuf, String>);
+
+/// Guesses the type of a puzzle file from its contents. Two lines of numbers and commas are
+/// Nonogram clues, and anything else holding exactly 81 digits is a Sudoku.
+pub(crate) fn detect_puzzle(contents: &str) -> Option<PuzzleType> {
+    let lines: Vec<&str> = contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+    let is_clues = |line: &&str| {
+        line.chars()
+            .all(|c| c.is_ascii_digit() || c == ',' || c.is_whitespace())
+    };
+    let digits = contents.chars().filter(char::is_ascii_digit).count();
+    if lines.len() == 2 && lines.iter().all(is_clues) && (contents.contains(',') || digits != 81) {
+        return Some(PuzzleType::Nonogram);
+    }
+    match digits {
+        81 => Some(PuzzleType::Sudoku),
+        _ => None,
+    }
+}
+
+fn solution_path(puzzle: &Path) -> PathBuf {
+    let stem = puzzle.file_stem().unwrap_or_default().to_string_lossy();
+    puzzle.with_file_name(format!("{}{}", stem, SOLUTION_SUFFIX))
+}
+
+/// Solves one puzzle file and writes its solution next to it, returning where it went.
+pub(crate) fn solve_file(path: &Path, command: &Command) -> Result<PathBuf, Box<dyn Error>> {
+    let mut contents = fs::read_to_string(path)?;
+    let solution = match detect_puzzle(&contents) {
+        Some(PuzzleType::Sudoku) => sudoku_puzzle(&mut contents, command)?,
+        Some(PuzzleType::Nonogram) => nonogram_puzzle(&mut contents, command)?,
+        _ => return Err(Box::from("Could not tell what kind of puzzle this is.")),
+    };
+    let output = solution_path(path);
+    let contents = match command.output_format {
+        OutputFormat::Grid => solution + "\n",
+        OutputFormat::Report => format!("Solution to puzzle: {}\n{}", path.display(), solution),
+    };
+    fs::write(&output, contents)?;
+    Ok(output)
+}
+
+/// Keeps track of the puzzle files in a directory between polls.
+pub(crate) struct Watcher {
+    dir: PathBuf,
+    // The last modification time and size seen for each file still being written
+    pending: HashMap<PathBuf, (SystemTime, u64)>,
+    // The modification time of each file when it was last solved or failed
+    attempted: HashMap<PathBuf, SystemTime>,
+}
+
+impl Watcher {
+    pub(crate) fn new(dir: impl Into<PathBuf>) -> Watcher {
+        Watcher {
+            dir: dir.into(),
+            pending: HashMap::new(),
+            attempted: HashMap::new(),
+        }
+    }
+
+    // Puzzle files that are new or changed since they were last handled. A file is only
+    // handed out once it looks the same on two polls in a row, so one still being copied in
+    // is not read half-written.
+    fn ready_files(&mut self) -> Result<Vec<PathBuf>, Box<dyn Error>> {
+        let mut ready = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            let name = path.file_name().unwrap_or_default().to_string_lossy();
+            if !name.ends_with(".txt") || name.ends_with(SOLUTION_SUFFIX) || !path.is_file() {
+                continue;
+            }
+            // The file may have been moved away since the directory was listed
+            let Ok(metadata) = fs::metadata(&path) else {
+                continue;
+            };
+            let modified = metadata.modified()?;
+            let solved = fs::metadata(solution_path(&path))
+                .and_then(|solution| solution.modified())
+                .is_ok_and(|solved| solved >= modified);
+            if solved || self.attempted.get(&path) == Some(&modified) {
+                continue;
+            }
+            let seen = (modified, metadata.len());
+            match self.pending.insert(path.clone(), seen) {
+                Some(previous) if previous == seen => ready.push(path),
+                _ => {}
+            }
+        }
+        ready.sort();
+        Ok(ready)
+    }
+
+    /// Looks through the directory once, solving every puzzle file that is ready. Returns
+    /// each file handled along with where its solution went or why it failed.
+    pub(crate) fn poll(&mut self, command: &Command) -> Result<Vec<Handled>, Box<dyn Error>> {
+        let mut handled = Vec::new();
+        for path in self.ready_files()? {
+            let result = solve_file(&path, command).map_err(|err| err.to_string());
+            self.pending.remove(&path);
+            if let Ok(modified) = fs::metadata(&path).and_then(|metadata| metadata.modified()) {
+                self.attempted.insert(path.clone(), modified);
+            }
+            handled.push((path, result));
+        }
+        Ok(handled)
+    }
+}
+
+/// Watches a directory until the program is stopped, solving puzzle files as they appear.
+pub(crate) fn watch(
+    dir: &str,
+    command: &Command,
+    interval: Duration,
+) -> Result<(), Box<dyn Error>> {
+    if !Path::new(dir).is_dir() {
+        return Err(Box::from(format!("{} is not a directory.", dir)));
+    }
+    eprintln!("Watching {} for puzzle files. Press Ctrl+C to stop.", dir);
+    let mut watcher = Watcher::new(dir);
+    loop {
+        for (path, result) in watcher.poll(command)? {
+            match result {
+                Ok(output) => eprintln!("Solved {} -> {}", path.display(), output.display()),
+                Err(err) => eprintln!("Failed to solve {}: {}", path.display(), err),
+            }
+        }
+        thread::sleep(interval);
+    }
+}
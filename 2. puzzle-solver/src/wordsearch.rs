@@ -0,0 +1,198 @@
+use std::error::Error;
+
+use crate::{
+    budget::{Budget, Report},
+    search::{Backtrack, Search},
+    Puzzle,
+};
+
+// Row and column steps for each direction a word can run in, with its name
+const DIRECTIONS: [(isize, isize, &str); 8] = [
+    (0, 1, "right"),
+    (1, 1, "down and right"),
+    (1, 0, "down"),
+    (1, -1, "down and left"),
+    (0, -1, "left"),
+    (-1, -1, "up and left"),
+    (-1, 0, "up"),
+    (-1, 1, "up and right"),
+];
+
+// The row and column a word starts at and the index of its direction
+type Placement = (usize, usize, usize);
+
+/// A grid of letters with a list of words hidden in it, running in any of the 8 directions.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WordSearch {
+    grid: Vec<Vec<char>>,
+    words: Vec<String>,
+    found: Vec<Option<Placement>>,
+}
+
+impl WordSearch {
+    // The cells a word would cover from a starting cell, if it fits inside the grid
+    fn covered(
+        &self,
+        length: usize,
+        (row, col, direction): Placement,
+    ) -> Option<Vec<(usize, usize)>> {
+        let (row_step, col_step, _) = DIRECTIONS[direction];
+        (0..length as isize)
+            .map(|step| {
+                let r = row.checked_add_signed(row_step * step)?;
+                let c = col.checked_add_signed(col_step * step)?;
+                (r < self.grid.len() && c < self.grid[r].len()).then_some((r, c))
+            })
+            .collect()
+    }
+
+    fn placements(&self, word: &str) -> Vec<Placement> {
+        let letters: Vec<char> = word.chars().collect();
+        let mut placements = Vec::new();
+        for row in 0..self.grid.len() {
+            for col in 0..self.grid[row].len() {
+                for direction in 0..DIRECTIONS.len() {
+                    let Some(cells) = self.covered(letters.len(), (row, col, direction)) else {
+                        continue;
+                    };
+                    if cells
+                        .iter()
+                        .zip(&letters)
+                        .all(|(&(r, c), &l)| self.grid[r][c] == l)
+                    {
+                        placements.push((row, col, direction));
+                    }
+                }
+            }
+        }
+        placements
+    }
+
+    /// Where a word starts and the index of its direction, by its position in the word list.
+    pub fn found(&self, word: usize) -> Option<Placement> {
+        self.found[word]
+    }
+}
+
+// Reads a word list separated by commas or whitespace, ignoring case
+pub(crate) fn read_words(text: &str) -> Result<Vec<String>, Box<dyn Error>> {
+    let words: Vec<String> = text
+        .split(|c: char| c == ',' || c.is_whitespace())
+        .filter(|word| !word.is_empty())
+        .map(str::to_uppercase)
+        .collect();
+    if let Some(word) = words
+        .iter()
+        .find(|word| !word.chars().all(char::is_alphabetic))
+    {
+        return Err(Box::from(format!(
+            "\"{}\" is not a word. Words may only contain letters.",
+            word
+        )));
+    }
+    match words.is_empty() {
+        true => Err(Box::from("Failed to read puzzle. The word list is empty.")),
+        false => Ok(words),
+    }
+}
+
+// Splits a puzzle into the grid above the first blank line and the word list below it
+pub(crate) fn split_word_list(contents: &str) -> Option<(Vec<&str>, &str)> {
+    let contents = contents.trim_start_matches(['\n', '\r']);
+    let (grid, words) = contents
+        .split_once("\n\n")
+        .or_else(|| contents.split_once("\r\n\r\n"))?;
+    Some((grid.lines().collect(), words))
+}
+
+impl Backtrack for WordSearch {
+    type Value = Placement;
+
+    fn cells(&self) -> usize {
+        self.words.len()
+    }
+
+    // Words may cross and share letters, so any place a word appears will do
+    fn options(&self, index: usize) -> Option<Vec<Placement>> {
+        Some(self.placements(&self.words[index]))
+    }
+
+    fn place(&mut self, index: usize, placement: Placement) -> bool {
+        self.found[index] = Some(placement);
+        true
+    }
+
+    fn clear(&mut self, index: usize) {
+        self.found[index] = None;
+    }
+}
+
+impl Puzzle for WordSearch {
+    // The letter grid comes first, one row per line, then a blank line and the word list
+    fn build(contents: &mut String) -> Result<Self, Box<dyn Error>> {
+        let (rows, words) = split_word_list(contents).ok_or(
+            "Failed to read puzzle. For word search, put the word list after a blank line below the grid.",
+        )?;
+        let grid: Vec<Vec<char>> = rows
+            .iter()
+            .map(|row| {
+                row.chars()
+                    .filter(|c| !c.is_whitespace())
+                    .flat_map(char::to_uppercase)
+                    .collect()
+            })
+            .collect();
+        if grid.iter().any(|row| row.len() != grid[0].len()) {
+            return Err(Box::from(
+                "Failed to read puzzle. Every row of the letter grid must be the same length.",
+            ));
+        }
+        let words = read_words(words)?;
+        Ok(WordSearch {
+            grid,
+            found: vec![None; words.len()],
+            words,
+        })
+    }
+
+    fn solve_within(&mut self, budget: &Budget) -> Report {
+        Search::new().run(self, budget)
+    }
+
+    // Shows the letters of every found word with the rest of the grid blanked out, followed by
+    // where each word starts and which way it runs
+    fn format(&self) -> String {
+        let mut highlighted: Vec<Vec<char>> =
+            self.grid.iter().map(|row| vec!['.'; row.len()]).collect();
+        let mut lines = Vec::new();
+        for (word, found) in self.words.iter().zip(&self.found) {
+            let Some(placement @ (row, col, direction)) = *found else {
+                lines.push(format!("{}: not found", word));
+                continue;
+            };
+            for (r, c) in self
+                .covered(word.chars().count(), placement)
+                .unwrap_or_default()
+            {
+                highlighted[r][c] = self.grid[r][c];
+            }
+            lines.push(format!(
+                "{}: row {}, column {}, {}",
+                word,
+                row + 1,
+                col + 1,
+                DIRECTIONS[direction].2
+            ));
+        }
+        let grid: Vec<String> = highlighted
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(char::to_string)
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            })
+            .collect();
+        format!("{}\n\n{}", grid.join("\n"), lines.join("\n"))
+    }
+}
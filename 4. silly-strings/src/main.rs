@@ -1,20 +1,27 @@
 use std::{
+    collections::HashSet,
     fs::File,
-    io::{BufReader, Read},
-    ops::RangeInclusive,
+    io::{self, BufWriter, Read, Write},
+    path::{Path, PathBuf},
 };
 
 use clap::{Parser, ValueEnum};
+use glob::Pattern;
 use regex::Regex;
+use walkdir::WalkDir;
+
+mod scanner;
+use scanner::Scanner;
 
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
 struct CliOptions {
-    #[arg(help = "The path to the file")]
-    path: std::path::PathBuf,
+    #[arg(default_value = "-",
+        help = "The files or directories to scan (directories are scanned recursively, - is standard input)")]
+    paths: Vec<PathBuf>,
 
     #[arg(short, long, default_value="ASCII", value_parser=valid_encoding,
-        help = "Parse the file with the given encoding [supports: ASCII, UTF-8]")]
+        help = "Parse the file with the given encoding [supports: ASCII, UTF-8, UTF-16LE (l), UTF-16BE (b), UTF-32LE (L), UTF-32BE (B)]")]
     encoding: Encoding,
 
     #[arg(short, long,
@@ -26,7 +33,7 @@ struct CliOptions {
     substring: String,
 
     #[arg(short='n', long="bytes", default_value_t=4, value_parser=valid_word_length,
-        help = "Minimum number of bytes considered a string")]
+        help = "Minimum number of bytes considered a string (characters for UTF-16 and UTF-32)")]
     min_len: u16,
 
     #[arg(short, long, default_value = "",
@@ -40,87 +47,67 @@ struct CliOptions {
     #[arg(short, long,
         help = "Only print unique strings (matching strings are merged to the first instance)")]
     uniq: bool,
-}
 
-#[derive(Debug)]
-struct IndexedString {
-    string: String,
-    index: usize,
+    #[arg(short='f', long="print-file-name",
+        help = "Print the name of the file before each string (on by default when scanning more than one file)")]
+    print_file_name: bool,
+
+    #[arg(long, value_name="GLOB", value_parser=valid_glob,
+        help = "Only scan files in directories whose name or path matches the glob (can be repeated)")]
+    include: Vec<Pattern>,
+
+    #[arg(long, value_name="GLOB", value_parser=valid_glob,
+        help = "Skip files and directories whose name or path matches the glob (can be repeated)")]
+    exclude: Vec<Pattern>,
 }
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum Encoding {
     Ascii,
     Utf8,
+    Utf16Le,
+    Utf16Be,
+    Utf32Le,
+    Utf32Be,
 }
 
 fn valid_encoding(encoding: &str) -> Result<Encoding, String> {
+    // The single letters are the ones GNU strings uses, so their case matters
+    match encoding {
+        "l" => return Ok(Encoding::Utf16Le),
+        "b" => return Ok(Encoding::Utf16Be),
+        "L" => return Ok(Encoding::Utf32Le),
+        "B" => return Ok(Encoding::Utf32Be),
+        _ => (),
+    }
     match encoding.to_lowercase().as_str() {
         "ascii" => Ok(Encoding::Ascii),
         "utf8" | "utf-8" => Ok(Encoding::Utf8),
-        _ => Err(format!("acceptable encodings: ASCII, UTF-8")),   
+        "utf16le" | "utf-16le" => Ok(Encoding::Utf16Le),
+        "utf16be" | "utf-16be" => Ok(Encoding::Utf16Be),
+        "utf32le" | "utf-32le" => Ok(Encoding::Utf32Le),
+        "utf32be" | "utf-32be" => Ok(Encoding::Utf32Be),
+        _ => Err(String::from(
+            "acceptable encodings: ASCII, UTF-8, UTF-16LE (l), UTF-16BE (b), UTF-32LE (L), UTF-32BE (B)"
+        )),
     }
 }
 
-const PRINTABLE_ASCII_RANGE: RangeInclusive<u8> = 0x20..=0x7F;
-const CONTROL_ASCII_RANGE: RangeInclusive<u8> = 0x00..=0x1F;
-
-fn is_printable_ascii(byte: &u8) -> bool {
-    PRINTABLE_ASCII_RANGE.contains(byte)
-}
-
-fn is_control_ascii(byte: &u8) -> bool {
-    CONTROL_ASCII_RANGE.contains(byte)
-}
-
-fn is_valid_utf8(
-    byte: &u8,
-    reader: &mut BufReader<File>,
-    read_buffer: &mut Vec<u8>,
-    string_buffer: &mut Vec<u8>)
-    -> bool {
-    let mut bytes_left;
-    if byte & 0b1111_1000 == 0b1111_0000 { bytes_left = 3; }
-    else if byte & 0b1111_0000 == 0b1110_0000 { bytes_left = 2; }
-    else if byte & 0b1110_0000 == 0b1100_0000 { bytes_left = 1; }
-    else if byte & 0b1000_0000 == 0b0000_0000 { bytes_left = 0; }
-    else { return false; }
-
-    let mut bytes_read = 0;
-    while bytes_left > 0 {
-        if reader.read(read_buffer).is_ok() {
-            if read_buffer[0] & 0b1100_0000 == 0b1000_0000 {
-                string_buffer.push(read_buffer[0]);
-                bytes_read += 1;
-            }
-            else {
-                while bytes_read > 0 {
-                    string_buffer.pop();
-                    bytes_read -= 1;
-                }
-                return false;
-            }
-        }
-        bytes_left -= 1;
-    }
-    true
-}
-
 fn valid_word_length(bytes: &str) -> Result<u16, String> {
-    if let Ok(_) = bytes.parse::<f64>() {
-        let parsed_number = bytes.parse::<u16>();
-        if parsed_number.is_ok() && parsed_number.clone().unwrap() > 0 {
-            return Ok(parsed_number.unwrap());
+    if bytes.parse::<f64>().is_ok() {
+        if let Ok(parsed_number @ 1..) = bytes.parse::<u16>() {
+            return Ok(parsed_number);
         }
-        return Err(format!(
+        return Err(String::from(
             "number of bytes must be an integer between 1 and 65535 (inclusive)"
         ));
     }
     Err(format!("{} is not numeric", bytes))
 }
 
-#[derive(Debug)]
-struct RegexError(String);
+fn valid_glob(glob: &str) -> Result<Pattern, String> {
+    Pattern::new(glob).map_err(|err| format!("{} is not a valid glob: {}", glob, err))
+}
 
 #[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, ValueEnum)]
 enum Radix {
@@ -136,33 +123,140 @@ fn valid_radix(radix: &str) -> Result<Radix, String> {
         "d" => Ok(Radix::Decimal),
         "x" => Ok(Radix::Hexadecimal),
         "n" => Ok(Radix::None),
-        _ => Err(format!("acceptable values: o,d,x")),
+        _ => Err(String::from("acceptable values: o,d,x")),
     }
 }
 
 fn format_index(radix: Radix, index: usize) -> String {
     match radix {
-        Radix::Octal => String::from(format!("{:o>7} ", index)),
-        Radix::Decimal => String::from(format!("{:>7} ", index)),
-        Radix::Hexadecimal => String::from(format!("{:x>7} ", index)),
+        Radix::Octal => format!("{:>7o} ", index),
+        Radix::Decimal => format!("{:>7} ", index),
+        Radix::Hexadecimal => format!("{:>7x} ", index),
         Radix::None => String::from(""),
     }
 }
 
-fn reset_str_buffer(buffer: &mut Vec<u8>, index: &mut usize) {
-    *index += buffer.len() + 1;
-    buffer.clear();
+fn print_string(
+    out: &mut impl Write,
+    file_name: &str,
+    radix: &Radix,
+    index: &usize,
+    string: &str)
+    -> io::Result<()> {
+    let displayed_index = format_index(*radix, *index);
+    writeln!(out, "{0}{1}{2}", file_name, displayed_index, string)
 }
 
-fn print_string(radix: &Radix, index: &usize, string: &String) {
-    let displayed_index = format_index(*radix, *index);
-    println!("{0}{1}", displayed_index, *string);
+// Globs are matched against both the file name and the path inside the scanned directory
+fn matches_glob(patterns: &[Pattern], root: &Path, path: &Path) -> bool {
+    let relative_path = path.strip_prefix(root).unwrap_or(path);
+    patterns.iter().any(|pattern| {
+        pattern.matches_path(relative_path)
+            || path.file_name().is_some_and(|name| pattern.matches(&name.to_string_lossy()))
+    })
 }
 
-fn main() -> std::io::Result<()> {
-    let args = CliOptions::parse();
-    let file = File::open(args.path)?;
+fn is_stdin(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+fn display_name(path: &Path) -> String {
+    match is_stdin(path) {
+        true => String::from("{standard input}"),
+        false => path.display().to_string(),
+    }
+}
+
+// The files to scan for one path argument, or a message for each one that can't be listed
+fn input_files(path: &Path, args: &CliOptions) -> Vec<Result<PathBuf, String>> {
+    if is_stdin(path) || !path.is_dir() {
+        return vec![Ok(path.to_path_buf())];
+    }
+    WalkDir::new(path)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| entry.depth() == 0 || !matches_glob(&args.exclude, path, entry.path()))
+        .filter_map(|entry| match entry {
+            Ok(entry) if !entry.file_type().is_file() => None,
+            Ok(entry) if !args.include.is_empty() && !matches_glob(&args.include, path, entry.path()) => None,
+            Ok(entry) => Some(Ok(entry.into_path())),
+            Err(err) => Some(Err(err.to_string())),
+        })
+        .collect()
+}
+
+// Reading a file can fail without stopping the other files, while failing to write the output
+// stops everything
+#[derive(Debug)]
+enum ScanError {
+    Read(io::Error),
+    Write(io::Error),
+}
+
+fn scan_input(
+    args: &CliOptions,
+    regex_string: &Regex,
+    input: impl Read,
+    file_name: &str,
+    out: &mut impl Write)
+    -> Result<(), ScanError> {
     let min_bytes: usize = args.min_len.into();
+
+    // Strings are filtered as they are found, so only --uniq has to remember anything, and then
+    // just the distinct strings. The first instance of each is the one printed.
+    let mut seen_strings: HashSet<String> = HashSet::new();
+    let mut write_failed = false;
+    let mut scanner = Scanner::new(args.encoding, min_bytes);
+    let result = scanner.scan_reader(input, &mut |found_string, index| {
+        let mut output_string: String = found_string.to_owned();
+        if args.lowercase {
+            output_string = output_string.to_lowercase();
+        }
+        if !args.substring.is_empty() && !output_string.contains(&args.substring) {
+            return Ok(());
+        }
+        if !args.regex.is_empty() && !regex_string.is_match(&output_string) {
+            return Ok(());
+        }
+        if args.uniq && seen_strings.contains(&output_string) {
+            return Ok(());
+        }
+        print_string(out, file_name, &args.radix, &index, &output_string).inspect_err(|_| {
+            write_failed = true;
+        })?;
+        if args.uniq {
+            seen_strings.insert(output_string);
+        }
+        Ok(())
+    });
+    match result {
+        Ok(()) => Ok(()),
+        Err(err) if write_failed => Err(ScanError::Write(err)),
+        Err(err) => Err(ScanError::Read(err)),
+    }
+}
+
+// Scans one file, or standard input for -
+fn scan_path(
+    args: &CliOptions,
+    regex_string: &Regex,
+    path: &Path,
+    print_file_name: bool,
+    out: &mut impl Write)
+    -> Result<(), ScanError> {
+    let file_name = match print_file_name {
+        true => format!("{}: ", display_name(path)),
+        false => String::new(),
+    };
+    if is_stdin(path) {
+        return scan_input(args, regex_string, io::stdin().lock(), &file_name, out);
+    }
+    let file = File::open(path).map_err(ScanError::Read)?;
+    scan_input(args, regex_string, file, &file_name, out)
+}
+
+fn main() -> io::Result<()> {
+    let args = CliOptions::parse();
     let regex_obj = Regex::new(&args.regex);
     let regex_string = match regex_obj {
         Ok(regex_obj) => regex_obj,
@@ -171,77 +265,122 @@ fn main() -> std::io::Result<()> {
             std::process::exit(1);
         },
     };
-    let analysis_mode = !args.substring.is_empty() || !args.regex.is_empty() || args.uniq;
-
-    let mut file_index: usize = 0;
-    let mut reader = BufReader::new(file);
-    let mut read_buffer: Vec<u8> = vec![0; 1];
-    let mut string_buffer: Vec<u8> = Vec::new();
-    let mut all_strings: Vec<IndexedString> = Vec::new();
+    let print_file_name = args.print_file_name
+        || args.paths.len() > 1
+        || args.paths.iter().any(|path| path.is_dir());
 
-    while let Ok(num_bytes) = reader.read(&mut read_buffer) {
-        if num_bytes == 0 {
-            break;
-        }
-
-        let byte: u8 = read_buffer[0];
-        match args.encoding {
-            Encoding::Ascii => {
-                if is_printable_ascii(&byte) {
-                    string_buffer.push(byte);
-                    continue;
-                }
-            },
-            Encoding::Utf8 => {
-                if !is_control_ascii(&byte) {
-                    string_buffer.push(byte);
-                    if is_valid_utf8(&byte, &mut reader, &mut read_buffer, &mut string_buffer) {
-                        continue;
+    // Strings are written through one buffer instead of locking stdout for each of them
+    let mut out = BufWriter::new(io::stdout().lock());
+    // A file that can't be opened or read is reported and skipped, so one bad file doesn't stop
+    // a sweep
+    let mut failed = false;
+    for path in &args.paths {
+        for file_path in input_files(path, &args) {
+            let message = match file_path {
+                Ok(file_path) => {
+                    match scan_path(&args, &regex_string, &file_path, print_file_name, &mut out) {
+                        Ok(()) => continue,
+                        Err(ScanError::Write(err)) => return Err(err),
+                        Err(ScanError::Read(err)) => format!("{}: {}", display_name(&file_path), err),
                     }
-                    string_buffer.pop();
-                }
-            },
-        };
-
-        let length_check = string_buffer.len() >= min_bytes;
-        if !length_check {
-            reset_str_buffer(&mut string_buffer, &mut file_index);
-            continue;
+                },
+                Err(message) => message,
+            };
+            out.flush()?;
+            eprintln!("silly-strings: {}", message);
+            failed = true;
         }
+    }
+    out.flush()?;
+    if failed {
+        std::process::exit(1);
+    }
+    Ok(())
+}
 
-        if let Ok(found_string) = std::str::from_utf8(&string_buffer) {
-            let mut output_string: String = found_string.to_owned();
-            if args.lowercase {
-                output_string = output_string.to_lowercase();
-            }
-            if analysis_mode {
-                all_strings.push(IndexedString {
-                    string: output_string,
-                    index: file_index,
-                });
-            } else {
-                print_string(&args.radix, &file_index, &output_string);
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    // A fresh directory tree under the system temp directory, removed again when dropped
+    struct TempTree(PathBuf);
+
+    impl TempTree {
+        fn new(name: &str, files: &[&str]) -> Self {
+            let root = std::env::temp_dir()
+                .join(format!("silly-strings-{}-{}", name, std::process::id()));
+            let _ = fs::remove_dir_all(&root);
+            for file in files {
+                let path = root.join(file);
+                fs::create_dir_all(path.parent().unwrap()).unwrap();
+                fs::write(path, "text").unwrap();
             }
+            TempTree(root)
         }
-        reset_str_buffer(&mut string_buffer, &mut file_index);
     }
 
-    if !all_strings.is_empty() {
-        if args.uniq {
-            all_strings.sort_by(|s1, s2| s1.string.cmp(&s2.string));
-            all_strings.dedup_by(|s1, s2| s1.string.eq(&s2.string));
-            all_strings.sort_by(|s1, s2| s1.index.cmp(&s2.index));
-        }
-        if !args.substring.is_empty() {
-            all_strings.retain(|s| s.string.contains(&args.substring));
-        }
-        if !args.regex.is_empty() {
-            
-            all_strings.retain(|s| regex_string.is_match(&s.string));
-        }
-        for indexed_string in all_strings {
-            print_string(&args.radix, &indexed_string.index, &indexed_string.string);
+    impl Drop for TempTree {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
         }
     }
-    Ok(())
+
+    // The files input_files picks for the tree, relative to its root
+    fn picked(tree: &TempTree, options: &[&str]) -> Vec<String> {
+        let args = CliOptions::parse_from(["silly-strings"].iter().chain(options));
+        input_files(&tree.0, &args)
+            .into_iter()
+            .map(|path| {
+                let path = path.unwrap();
+                path.strip_prefix(&tree.0).unwrap().to_string_lossy().replace('\\', "/")
+            })
+            .collect()
+    }
+
+    const FILES: [&str; 5] = ["b.txt", "a.dll", "lib/c.so", "lib/.git/d.txt", "out/e.so"];
+
+    #[test]
+    fn test_walk_sorted() {
+        let tree = TempTree::new("walk", &FILES);
+        assert_eq!(
+            picked(&tree, &[]),
+            ["a.dll", "b.txt", "lib/.git/d.txt", "lib/c.so", "out/e.so"]
+        );
+    }
+
+    #[test]
+    fn test_include() {
+        let tree = TempTree::new("include", &FILES);
+        // File names match anywhere, while paths match from the scanned directory
+        assert_eq!(picked(&tree, &["--include", "*.so"]), ["lib/c.so", "out/e.so"]);
+        assert_eq!(
+            picked(&tree, &["--include", "lib/*.so", "--include", "*.dll"]),
+            ["a.dll", "lib/c.so"]
+        );
+    }
+
+    #[test]
+    fn test_exclude() {
+        let tree = TempTree::new("exclude", &FILES);
+        // Excluding a directory skips everything inside it
+        assert_eq!(
+            picked(&tree, &["--exclude", ".git", "--exclude", "out"]),
+            ["a.dll", "b.txt", "lib/c.so"]
+        );
+        assert_eq!(
+            picked(&tree, &["--include", "*.txt", "--exclude", "lib"]),
+            ["b.txt"]
+        );
+    }
+
+    #[test]
+    fn test_single_paths() {
+        // Files and standard input are scanned whatever the globs say
+        let args = CliOptions::parse_from(["silly-strings", "--include", "*.so"]);
+        let paths = input_files(Path::new("notes.txt"), &args);
+        assert_eq!(paths, [Ok(PathBuf::from("notes.txt"))]);
+        let paths = input_files(Path::new("-"), &args);
+        assert_eq!(paths, [Ok(PathBuf::from("-"))]);
+    }
 }
@@ -0,0 +1,374 @@
+use std::{
+    io::{self, ErrorKind, Read},
+    ops::RangeInclusive,
+};
+
+use crate::Encoding;
+
+// Bytes read from the file at a time
+const CHUNK_SIZE: usize = 1 << 20;
+
+const PRINTABLE_ASCII_RANGE: RangeInclusive<u8> = 0x20..=0x7F;
+const CONTROL_ASCII_RANGE: RangeInclusive<u8> = 0x00..=0x1F;
+
+fn is_printable_ascii(byte: &u8) -> bool {
+    PRINTABLE_ASCII_RANGE.contains(byte)
+}
+
+fn is_control_ascii(byte: &u8) -> bool {
+    CONTROL_ASCII_RANGE.contains(byte)
+}
+
+// The number of continuation bytes that follow a UTF-8 lead byte
+fn continuation_bytes(byte: &u8) -> Option<usize> {
+    if byte & 0b1111_1000 == 0b1111_0000 { Some(3) }
+    else if byte & 0b1111_0000 == 0b1110_0000 { Some(2) }
+    else if byte & 0b1110_0000 == 0b1100_0000 { Some(1) }
+    else if byte & 0b1000_0000 == 0b0000_0000 { Some(0) }
+    else { None }
+}
+
+fn is_continuation(byte: &u8) -> bool {
+    byte & 0b1100_0000 == 0b1000_0000
+}
+
+// Finds strings in bytes that are fed in chunks of any size. A string or UTF-8 character
+// that is cut off at the end of one chunk carries on into the next.
+pub struct Scanner {
+    encoding: Encoding,
+    min_len: usize,
+    // Offset of the next byte fed in
+    offset: usize,
+    // Offset of the first byte of the current string
+    start: usize,
+    string: Vec<u8>,
+    // The bytes read so far of a UTF-8 character or of a UTF-16/UTF-32 code unit
+    character: Vec<u8>,
+    bytes_left: usize,
+}
+
+impl Scanner {
+    pub fn new(encoding: Encoding, min_len: usize) -> Self {
+        Scanner {
+            encoding,
+            min_len,
+            offset: 0,
+            start: 0,
+            string: Vec::new(),
+            character: Vec::new(),
+            bytes_left: 0,
+        }
+    }
+
+    // Scans everything the reader has, calling `found` with each string and its offset
+    pub fn scan_reader<F>(&mut self, mut reader: impl Read, found: &mut F) -> io::Result<()>
+    where
+        F: FnMut(&str, usize) -> io::Result<()>,
+    {
+        let mut chunk = vec![0; CHUNK_SIZE];
+        loop {
+            match reader.read(&mut chunk) {
+                Ok(0) => return self.finish(found),
+                Ok(num_bytes) => self.scan(&chunk[..num_bytes], found)?,
+                Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    pub fn scan<F>(&mut self, chunk: &[u8], found: &mut F) -> io::Result<()>
+    where
+        F: FnMut(&str, usize) -> io::Result<()>,
+    {
+        match self.encoding {
+            Encoding::Ascii => self.scan_ascii(chunk, found),
+            Encoding::Utf8 => self.scan_utf8(chunk, found),
+            Encoding::Utf16Le => self.scan_wide(chunk, 2, true, found),
+            Encoding::Utf16Be => self.scan_wide(chunk, 2, false, found),
+            Encoding::Utf32Le => self.scan_wide(chunk, 4, true, found),
+            Encoding::Utf32Be => self.scan_wide(chunk, 4, false, found),
+        }
+    }
+
+    // Reports the string still being read when the input ends
+    pub fn finish<F>(&mut self, found: &mut F) -> io::Result<()>
+    where
+        F: FnMut(&str, usize) -> io::Result<()>,
+    {
+        self.character.clear();
+        self.bytes_left = 0;
+        self.end_string(found)
+    }
+
+    fn scan_ascii<F>(&mut self, chunk: &[u8], found: &mut F) -> io::Result<()>
+    where
+        F: FnMut(&str, usize) -> io::Result<()>,
+    {
+        let mut rest = chunk;
+        while let Some(end) = rest.iter().position(|byte| !is_printable_ascii(byte)) {
+            self.extend(&rest[..end]);
+            self.offset += end + 1;
+            self.end_string(found)?;
+            rest = &rest[end + 1..];
+        }
+        self.extend(rest);
+        self.offset += rest.len();
+        Ok(())
+    }
+
+    fn scan_utf8<F>(&mut self, chunk: &[u8], found: &mut F) -> io::Result<()>
+    where
+        F: FnMut(&str, usize) -> io::Result<()>,
+    {
+        for byte in chunk {
+            if self.bytes_left > 0 {
+                if is_continuation(byte) {
+                    self.character.push(*byte);
+                    self.bytes_left -= 1;
+                    self.offset += 1;
+                    if self.bytes_left == 0 {
+                        self.end_character();
+                    }
+                    continue;
+                }
+                // A cut-off character ends the string, but the byte after it can start the next
+                self.character.clear();
+                self.bytes_left = 0;
+                self.end_string(found)?;
+            }
+
+            self.offset += 1;
+            match continuation_bytes(byte) {
+                Some(bytes_left) if !is_control_ascii(byte) => {
+                    self.character.push(*byte);
+                    self.bytes_left = bytes_left;
+                    if bytes_left == 0 {
+                        self.end_character();
+                    }
+                }
+                _ => self.end_string(found)?,
+            }
+        }
+        Ok(())
+    }
+
+    // Like GNU strings, only printable ASCII characters are looked for in the wider encodings,
+    // since nearly any pair of bytes is a valid UTF-16 character.
+    fn scan_wide<F>(
+        &mut self,
+        chunk: &[u8],
+        unit_bytes: usize,
+        little_endian: bool,
+        found: &mut F,
+    ) -> io::Result<()>
+    where
+        F: FnMut(&str, usize) -> io::Result<()>,
+    {
+        for byte in chunk {
+            self.character.push(*byte);
+            self.offset += 1;
+            if self.character.len() < unit_bytes {
+                continue;
+            }
+            let unit = match little_endian {
+                true => self.character.iter().rev().fold(0, |unit, byte| unit << 8 | u32::from(*byte)),
+                false => self.character.iter().fold(0, |unit, byte| unit << 8 | u32::from(*byte)),
+            };
+            match u8::try_from(unit) {
+                Ok(ascii) if is_printable_ascii(&ascii) => {
+                    if self.string.is_empty() {
+                        self.start = self.offset - unit_bytes;
+                    }
+                    self.string.push(ascii);
+                    self.character.clear();
+                }
+                _ => {
+                    // The next code unit is tried one byte later, which finds strings that
+                    // are not aligned to the unit size
+                    self.character.remove(0);
+                    self.end_string(found)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn extend(&mut self, bytes: &[u8]) {
+        if self.string.is_empty() {
+            self.start = self.offset;
+        }
+        self.string.extend_from_slice(bytes);
+    }
+
+    // Called once the last byte of a character has been counted in the offset
+    fn end_character(&mut self) {
+        if self.string.is_empty() {
+            self.start = self.offset - self.character.len();
+        }
+        self.string.append(&mut self.character);
+    }
+
+    fn end_string<F>(&mut self, found: &mut F) -> io::Result<()>
+    where
+        F: FnMut(&str, usize) -> io::Result<()>,
+    {
+        let result = match std::str::from_utf8(&self.string) {
+            Ok(string) if self.string.len() >= self.min_len => found(string, self.start),
+            _ => Ok(()),
+        };
+        self.string.clear();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Every string found with its offset, feeding the input in chunks of the given size
+    fn scan_chunks(
+        encoding: Encoding,
+        min_len: usize,
+        input: &[u8],
+        chunk_size: usize,
+    ) -> Vec<(String, usize)> {
+        let mut strings = Vec::new();
+        let mut found = |string: &str, index: usize| {
+            strings.push((string.to_owned(), index));
+            Ok(())
+        };
+        let mut scanner = Scanner::new(encoding, min_len);
+        for chunk in input.chunks(chunk_size) {
+            scanner.scan(chunk, &mut found).unwrap();
+        }
+        scanner.finish(&mut found).unwrap();
+        strings
+    }
+
+    // Scans the input whole and in small chunks, which must all find the same strings
+    fn scan_all(encoding: Encoding, min_len: usize, input: &[u8]) -> Vec<(String, usize)> {
+        let whole = scan_chunks(encoding, min_len, input, input.len().max(1));
+        for chunk_size in 1..=3 {
+            let chunked = scan_chunks(encoding, min_len, input, chunk_size);
+            assert_eq!(chunked, whole, "chunks of {} bytes", chunk_size);
+        }
+        whole
+    }
+
+    fn expected(strings: &[(&str, usize)]) -> Vec<(String, usize)> {
+        strings.iter().map(|(string, index)| (string.to_string(), *index)).collect()
+    }
+
+    #[test]
+    fn test_ascii() {
+        let input = b"\x00\x01hello\x00ab\x00world wide\xff\x02tail";
+        assert_eq!(
+            scan_all(Encoding::Ascii, 4, input),
+            expected(&[("hello", 2), ("world wide", 11), ("tail", 23)])
+        );
+    }
+
+    #[test]
+    fn test_ascii_reader() {
+        let input: &[u8] = b"\x00first\x00second";
+        let mut strings = Vec::new();
+        Scanner::new(Encoding::Ascii, 4)
+            .scan_reader(input, &mut |string, index| {
+                strings.push((string.to_owned(), index));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(strings, expected(&[("first", 1), ("second", 7)]));
+    }
+
+    #[test]
+    fn test_utf8() {
+        // Two, three and four byte characters, which the small chunks cut apart
+        let input = "\u{1}été\u{0}x€42\u{2}😀 ok\u{3}".as_bytes();
+        assert_eq!(
+            scan_all(Encoding::Utf8, 4, input),
+            expected(&[("été", 1), ("x€42", 7), ("😀 ok", 14)])
+        );
+    }
+
+    #[test]
+    fn test_utf8_broken_character() {
+        // A lead byte without its continuation bytes ends the string, and the byte after it
+        // starts the next one
+        let input = b"abcd\xe2\x82efgh\xc3";
+        assert_eq!(
+            scan_all(Encoding::Utf8, 4, input),
+            expected(&[("abcd", 0), ("efgh", 6)])
+        );
+        // Stray continuation bytes end a string too
+        let input = b"\x80abcd\x80\x80efgh";
+        assert_eq!(
+            scan_all(Encoding::Utf8, 4, input),
+            expected(&[("abcd", 1), ("efgh", 7)])
+        );
+    }
+
+    #[test]
+    fn test_min_len() {
+        let input = b"a\x00ab\x00abc\x00abcd";
+        assert_eq!(
+            scan_all(Encoding::Ascii, 3, input),
+            expected(&[("abc", 5), ("abcd", 9)])
+        );
+        assert_eq!(scan_all(Encoding::Ascii, 5, input), expected(&[]));
+    }
+
+    // The text as code units of the given size, least significant byte first if `little_endian`
+    fn wide(text: &str, unit_bytes: usize, little_endian: bool) -> Vec<u8> {
+        text.chars()
+            .flat_map(|c| {
+                let bytes = u32::from(c).to_be_bytes()[4 - unit_bytes..].to_vec();
+                match little_endian {
+                    true => bytes.into_iter().rev().collect::<Vec<u8>>(),
+                    false => bytes,
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_utf16() {
+        // The first string starts on an odd offset, and the junk after it is not a character
+        let mut input = vec![0x01];
+        input.extend(wide("Hello", 2, true));
+        input.extend([0xff, 0xff]);
+        input.extend(wide("World", 2, true));
+        input.extend(wide("\u{1}abc", 2, true));
+        assert_eq!(
+            scan_all(Encoding::Utf16Le, 4, &input),
+            expected(&[("Hello", 1), ("World", 13)])
+        );
+
+        let mut input = wide("é big", 2, false);
+        input.extend([0x00]);
+        input.extend(wide("endian", 2, false));
+        assert_eq!(
+            scan_all(Encoding::Utf16Be, 4, &input),
+            expected(&[(" big", 2), ("endian", 11)])
+        );
+    }
+
+    #[test]
+    fn test_utf32() {
+        let mut input = vec![0x41, 0x41];
+        input.extend(wide("Wide", 4, true));
+        input.extend(wide("\u{7}abc\u{0}long", 4, true));
+        assert_eq!(
+            scan_all(Encoding::Utf32Le, 4, &input),
+            expected(&[("Wide", 2), ("long", 38)])
+        );
+
+        let mut input = vec![0x00];
+        input.extend(wide("Big", 4, false));
+        input.extend(wide("\u{0}four", 4, false));
+        assert_eq!(
+            scan_all(Encoding::Utf32Be, 3, &input),
+            expected(&[("Big", 1), ("four", 17)])
+        );
+    }
+}